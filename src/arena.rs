@@ -0,0 +1,200 @@
+//! Random layouts for modes with `random_map`: ground at a few heights, pits
+//! and floating ledges, all from the run's dice so a replay gets the same one.
+//! Every layout is checked against how far the cat can jump before it's used,
+//! and if the dice keep making ones it can't get around, it's a flat floor.
+
+use glam::*;
+use oorandom::Rand32;
+
+use crate::campaign::{Stage, MAP_HEIGHT, MAP_WIDTH};
+use crate::terrain::FEET;
+use crate::trajectory::TILE_SIZE;
+use crate::tuning::Tuning;
+use crate::SCREEN_SIZE;
+
+/// Layouts to try before giving up on the dice.
+const TRIES: u32 = 50;
+/// Tallest the ground gets, in tiles.
+const MAX_GROUND: usize = 5;
+/// Nothing goes above this row, counting up from the bottom, so there's sky
+/// for candy to come down through.
+const MAX_ROW: usize = 9;
+
+/// How far the cat gets with one jump, in whole tiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Reach {
+    pub up: usize,
+    pub across: usize,
+}
+
+impl Reach {
+    /// From a full-height jump, without the double jump since that has to be
+    /// unlocked first.
+    pub fn new(tuning: &Tuning) -> Reach {
+        let rise = (tuning.gravity * tuning.rise_gravity_scale).max(1.0);
+        let fall = (tuning.gravity * tuning.fall_gravity_scale).max(1.0);
+        let apex = tuning.jump_velocity.max(0.0).powi(2) / (2.0 * rise);
+        let airtime = tuning.jump_velocity.max(0.0) / rise + (2.0 * apex / fall).sqrt();
+        Reach {
+            up: (apex / TILE_SIZE).floor() as usize,
+            across: (tuning.player_vel * airtime / TILE_SIZE).floor() as usize,
+        }
+    }
+}
+
+/// Solid tiles, bottom row first like `Stage::layout`.
+type Grid = Vec<Vec<bool>>;
+
+fn range(rng: &mut Rand32, low: usize, high: usize) -> usize {
+    low + rng.rand_range(0..(high - low + 1) as u32) as usize
+}
+
+/// How many solid tiles are stacked up from the bottom of `column`.
+fn ground(grid: &Grid, column: usize) -> usize {
+    grid.iter().take_while(|row| row[column]).count()
+}
+
+/// The first empty row above everything in `column`.
+fn top(grid: &Grid, column: usize) -> usize {
+    (0..MAP_HEIGHT).rev().find(|&y| grid[y][column]).map_or(0, |y| y + 1)
+}
+
+fn flat() -> Grid {
+    let mut grid = vec![vec![false; MAP_WIDTH]; MAP_HEIGHT];
+    grid[0] = vec![true; MAP_WIDTH];
+    grid[1] = vec![true; MAP_WIDTH];
+    grid
+}
+
+/// Ground in runs of a few columns, each a step up or down from the last,
+/// with now and then a pit down to the bottom row. Then ledges, each as far
+/// above what's under it as the cat can jump.
+fn candidate(rng: &mut Rand32, reach: Reach) -> Grid {
+    let mut grid = vec![vec![false; MAP_WIDTH]; MAP_HEIGHT];
+    let (mut column, mut height) = (0, 2);
+    while column < MAP_WIDTH {
+        let width;
+        let pit = column > 0 && height > 1 && height - 1 <= reach.up && rng.rand_float() < 0.25;
+        if pit {
+            width = range(rng, 1, 2);
+        } else {
+            width = range(rng, 2, 5);
+            let step = range(rng, 0, 2 * reach.up.min(1)) as isize - reach.up.min(1) as isize;
+            height = (height as isize + step).clamp(1, MAX_GROUND as isize) as usize;
+        }
+        for x in column..(column + width).min(MAP_WIDTH) {
+            for row in grid.iter_mut().take(if pit { 1 } else { height }) {
+                row[x] = true;
+            }
+        }
+        column += width;
+    }
+
+    if reach.up >= 2 {
+        for _ in 0..range(rng, 2, 5) {
+            let width = range(rng, 2, 4);
+            let left = range(rng, 0, MAP_WIDTH - width);
+            let under = (left..left + width).map(|x| top(&grid, x)).max().unwrap_or(0);
+            let row = under + reach.up - 1;
+            if row <= MAX_ROW {
+                grid[row][left..left + width].fill(true);
+            }
+        }
+    }
+    grid
+}
+
+/// Heights the cat can stand at, in tiles up from the bottom, per column.
+fn surfaces(grid: &Grid) -> Vec<(usize, usize)> {
+    (0..MAP_WIDTH)
+        .flat_map(|x| (0..MAP_HEIGHT).filter(move |&y| grid[y][x] && grid.get(y + 1).is_none_or(|row| !row[x]))
+            .map(move |y| (x, y + 1)))
+        .collect()
+}
+
+/// Whether every surface can be got to from where the cat starts. One jump
+/// goes up to `reach.up` tiles and across `reach.across`, through ledges but
+/// not over ground that's higher than both ends.
+fn reachable(grid: &Grid, start: usize, reach: Reach) -> bool {
+    let surfaces = surfaces(grid);
+    let can_go = |(ax, ah): (usize, usize), (bx, bh): (usize, usize)| {
+        let (low, high) = (ax.min(bx), ax.max(bx));
+        let wall = (low + 1..high).map(|x| ground(grid, x)).max().unwrap_or(0);
+        high - low <= reach.across && bh <= ah + reach.up && wall <= ah.max(bh)
+    };
+    let Some(first) = surfaces.iter().position(|&(x, h)| x == start && h == ground(grid, x)) else {
+        return false;
+    };
+    let mut seen = vec![false; surfaces.len()];
+    seen[first] = true;
+    let mut open = vec![first];
+    while let Some(at) = open.pop() {
+        for next in 0..surfaces.len() {
+            if !seen[next] && can_go(surfaces[at], surfaces[next]) {
+                seen[next] = true;
+                open.push(next);
+            }
+        }
+    }
+    seen.iter().all(|&s| s)
+}
+
+/// A layout the cat can get all the way around, and where it starts on it.
+pub fn generate(rng: &mut Rand32, tuning: &Tuning) -> (Vec<String>, Vec2) {
+    let reach = Reach::new(tuning);
+    let start = MAP_WIDTH / 2;
+    let grid = (0..TRIES)
+        .map(|_| candidate(rng, reach))
+        .find(|grid| reachable(grid, start, reach))
+        .unwrap_or_else(flat);
+
+    let map = grid.iter().rev()
+        .map(|row| row.iter().map(|&solid| if solid { '#' } else { '.' }).collect())
+        .collect();
+    let bottom = -SCREEN_SIZE.1 / 2.0;
+    let at = vec2(
+        -SCREEN_SIZE.0 / 2.0 + (start as f32 + 0.5) * TILE_SIZE,
+        bottom + ground(&grid, start) as f32 * TILE_SIZE + FEET);
+    (map, at)
+}
+
+/// `stage` with a random layout, starting the cat on it. Its decoration was
+/// placed for its own map, so that's left off.
+pub fn arena(stage: &Stage, rng: &mut Rand32, tuning: &Tuning) -> Stage {
+    let (map, start) = generate(rng, tuning);
+    Stage { map, start: Some(start), decor: Vec::new(), ..stage.clone() }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn layouts_fit_and_can_be_got_around() {
+        let tuning = Tuning::default();
+        let reach = Reach::new(&tuning);
+        assert_eq!(reach, Reach { up: 2, across: 9 });
+        let mut rng = Rand32::new(11);
+        let mut flat_ones = 0;
+        for _ in 0..100 {
+            let (map, start) = generate(&mut rng, &tuning);
+            assert_eq!(map.len(), MAP_HEIGHT);
+            assert!(map.iter().all(|row| row.chars().count() == MAP_WIDTH));
+            let grid = map.iter().rev().map(|row| row.chars().map(|c| c == '#').collect()).collect::<Grid>();
+            assert!(reachable(&grid, MAP_WIDTH / 2, reach));
+            assert!(grid[0].iter().all(|&solid| solid), "no bottomless pits");
+            assert!(start.y > -SCREEN_SIZE.1 / 2.0);
+            flat_ones += (grid == flat()) as u32;
+        }
+        assert!(flat_ones < 10, "{} of 100 gave up on the dice", flat_ones);
+    }
+
+    #[test]
+    fn a_wall_too_tall_to_jump_is_caught() {
+        let mut grid = flat();
+        for row in grid.iter_mut().take(6) {
+            row[15] = true;
+        }
+        assert!(!reachable(&grid, MAP_WIDTH / 2, Reach { up: 2, across: 9 }));
+    }
+}
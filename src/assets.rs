@@ -0,0 +1,70 @@
+//! Images and sounds the game draws with, loaded once in `main`.
+
+use std::sync::Arc;
+
+use ggez::audio::Source;
+use ggez::graphics::{self, Color};
+use ggez::{Context, GameResult};
+use glam::*;
+
+use crate::ecs::{SpriteId, SpriteRef};
+use crate::sfx::Sfx;
+use crate::sprite;
+
+pub type SpriteFrame = graphics::Image;
+
+#[derive(Clone)]
+pub struct Sprite {
+    pub frames: Arc<Vec<SpriteFrame>>
+}
+
+pub struct Assets {
+    pub player: Sprite,
+    pub candy: SpriteFrame,
+    pub collect_animation: Sprite,
+    /// Not every build can decode it, so it's fine for this to be missing.
+    pub bgm: Option<Source>,
+    pub collect_sheet: sprite::SpriteSheet,
+    pub cat_sheet: sprite::SpriteSheet,
+    /// Stand-ins for hazards until they get art. Drawn around their middle.
+    pub bat: graphics::Mesh,
+    pub ghost: graphics::Mesh,
+    pub pumpkin: graphics::Mesh,
+    pub sfx: Sfx,
+}
+
+impl Assets {
+    pub fn draw_sprite(&self, ctx: &mut Context, sprite: &SpriteRef, dest: Vec2) -> GameResult {
+        let params = graphics::DrawParam::new()
+            .dest(dest)
+            .offset(Vec2::new(0.5, 0.5))
+            .scale(sprite.scale);
+        match sprite.id {
+            SpriteId::Cat => self.cat_sheet.draw_frame(ctx, sprite.frame, params),
+            SpriteId::Candy => graphics::draw(ctx, &self.candy, params),
+            SpriteId::Sparkle => graphics::draw(ctx, &self.collect_animation.frames[sprite.frame], params),
+            SpriteId::Bat => graphics::draw(ctx, &self.bat, (dest,)),
+            SpriteId::Ghost => graphics::draw(ctx, &self.ghost, (dest,)),
+            SpriteId::Pumpkin => graphics::draw(ctx, &self.pumpkin, (dest,)),
+        }
+    }
+}
+
+pub fn hazard_meshes(ctx: &mut Context) -> GameResult<(graphics::Mesh, graphics::Mesh, graphics::Mesh)> {
+    use graphics::{DrawMode, MeshBuilder, Rect};
+    let bat = MeshBuilder::new()
+        .polygon(DrawMode::fill(), &[
+            vec2(-14.0, -6.0), vec2(-4.0, 0.0), vec2(0.0, -4.0), vec2(4.0, 0.0),
+            vec2(14.0, -6.0), vec2(8.0, 4.0), vec2(0.0, 6.0), vec2(-8.0, 4.0),
+        ], Color::from_rgb(60, 30, 80))?
+        .build(ctx)?;
+    let ghost = MeshBuilder::new()
+        .circle(DrawMode::fill(), vec2(0.0, -4.0), 10.0, 0.5, Color::new(1.0, 1.0, 1.0, 0.7))?
+        .rectangle(DrawMode::fill(), Rect::new(-10.0, -4.0, 20.0, 14.0), Color::new(1.0, 1.0, 1.0, 0.7))?
+        .build(ctx)?;
+    let pumpkin = MeshBuilder::new()
+        .circle(DrawMode::fill(), vec2(0.0, 2.0), 11.0, 0.5, Color::from_rgb(240, 130, 20))?
+        .rectangle(DrawMode::fill(), Rect::new(-2.0, -12.0, 4.0, 6.0), Color::from_rgb(60, 140, 40))?
+        .build(ctx)?;
+    Ok((bat, ghost, pumpkin))
+}
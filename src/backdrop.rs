@@ -0,0 +1,148 @@
+//! The stage background: layers drawn back to front, each scrolling with the
+//! camera by its own amount and drifting on its own. Stages list their layers
+//! in `stages.toml`.
+
+use std::f32::consts::TAU;
+
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Image, Mesh, MeshBuilder};
+use ggez::{Context, GameResult};
+use glam::*;
+use serde::Deserialize;
+
+use crate::camera::Camera;
+use crate::SCREEN_SIZE;
+
+/// Seconds for one slow bob up and down.
+const BOB_PERIOD: f32 = 6.0;
+
+/// What a layer shows. Images come from the resources folder, the rest are
+/// drawn by the game.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Look {
+    Image(String),
+    Moon,
+    Clouds,
+    Fog,
+}
+
+/// Positions here are in screen pixels, y down.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Layer {
+    pub look: Look,
+    /// How much it moves with the camera: 0 stays put, 1 would move with the game.
+    #[serde(default)]
+    pub scroll: f32,
+    /// Repeat sideways so no edges show however far it moves.
+    #[serde(default)]
+    pub tile: bool,
+    #[serde(default)]
+    pub offset: [f32; 2],
+    /// Pixels per second it moves on its own.
+    #[serde(default)]
+    pub drift: [f32; 2],
+    /// Pixels it bobs up and down.
+    #[serde(default)]
+    pub bob: f32,
+}
+
+enum Drawable {
+    Image(Image),
+    Mesh(Mesh),
+}
+
+pub struct Backdrop {
+    layers: Vec<(Layer, Drawable)>,
+    /// Seconds of ambient motion so far.
+    time: f32,
+}
+
+fn moon(ctx: &mut Context) -> GameResult<Mesh> {
+    MeshBuilder::new()
+        .circle(DrawMode::fill(), vec2(480.0, 90.0), 44.0, 0.5, Color::new(1.0, 0.95, 0.7, 0.12))?
+        .circle(DrawMode::fill(), vec2(480.0, 90.0), 28.0, 0.5, Color::new(1.0, 0.95, 0.75, 1.0))?
+        .circle(DrawMode::fill(), vec2(490.0, 82.0), 6.0, 0.5, Color::new(0.9, 0.85, 0.65, 1.0))?
+        .circle(DrawMode::fill(), vec2(472.0, 100.0), 4.0, 0.5, Color::new(0.9, 0.85, 0.65, 1.0))?
+        .build(ctx)
+}
+
+/// Soft ellipses spread across one screen width, so copies side by side line up.
+fn puffs(ctx: &mut Context, puffs: &[(f32, f32, f32, f32)], color: Color) -> GameResult<Mesh> {
+    let mut mb = MeshBuilder::new();
+    for &(x, y, rx, ry) in puffs {
+        mb.ellipse(DrawMode::fill(), vec2(x, y), rx, ry, 0.5, color)?;
+    }
+    mb.build(ctx)
+}
+
+fn clouds(ctx: &mut Context) -> GameResult<Mesh> {
+    puffs(ctx, &[
+        (40.0, 60.0, 90.0, 18.0),
+        (200.0, 110.0, 70.0, 14.0),
+        (330.0, 50.0, 110.0, 20.0),
+        (520.0, 130.0, 80.0, 16.0),
+        (610.0, 70.0, 60.0, 12.0),
+    ], Color::new(0.25, 0.2, 0.35, 0.55))
+}
+
+fn fog(ctx: &mut Context) -> GameResult<Mesh> {
+    puffs(ctx, &[
+        (0.0, 390.0, 160.0, 30.0),
+        (180.0, 405.0, 140.0, 24.0),
+        (330.0, 385.0, 180.0, 34.0),
+        (520.0, 400.0, 150.0, 26.0),
+    ], Color::new(0.85, 0.85, 1.0, 0.12))
+}
+
+impl Backdrop {
+    pub fn load(ctx: &mut Context, layers: &[Layer]) -> GameResult<Backdrop> {
+        let layers = layers.iter()
+            .map(|layer| {
+                let drawable = match &layer.look {
+                    Look::Image(path) => Drawable::Image(Image::new(ctx, path)?),
+                    Look::Moon => Drawable::Mesh(moon(ctx)?),
+                    Look::Clouds => Drawable::Mesh(clouds(ctx)?),
+                    Look::Fog => Drawable::Mesh(fog(ctx)?),
+                };
+                Ok((layer.clone(), drawable))
+            })
+            .collect::<GameResult<Vec<_>>>()?;
+        Ok(Backdrop { layers, time: 0.0 })
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    pub fn draw(&self, ctx: &mut Context, camera: &Camera) -> GameResult {
+        let screen_width = SCREEN_SIZE.0;
+        for (layer, drawable) in &self.layers {
+            let look = camera.offset();
+            let bob = layer.bob * (self.time * TAU / BOB_PERIOD).sin();
+            let pos = Vec2::from(layer.offset) + Vec2::from(layer.drift) * self.time
+                - look * layer.scroll
+                + vec2(0.0, bob);
+
+            let width = match drawable {
+                Drawable::Image(image) => image.width() as f32,
+                Drawable::Mesh(_) => screen_width,
+            };
+            let xs = if layer.tile {
+                // Starts a whole width early for anything hanging over from the left.
+                let first = pos.x.rem_euclid(width) - width * 2.0;
+                (0..).map(|i| first + i as f32 * width).take_while(|&x| x < screen_width).collect()
+            } else {
+                vec![pos.x]
+            };
+
+            for x in xs {
+                let params = DrawParam::new().dest(vec2(x, pos.y));
+                match drawable {
+                    Drawable::Image(image) => graphics::draw(ctx, image, params)?,
+                    Drawable::Mesh(mesh) => graphics::draw(ctx, mesh, params)?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,296 @@
+//! Plays stages over and over with the bot (see `bot.rs`), no window, and
+//! prints how it went, so changes to tuning or modes can be compared by
+//! numbers instead of by feel.
+//!
+//! ```text
+//! cargo run --release --bin balance -- [options]
+//!   --runs N          seeds to play each stage with (100)
+//!   --seed N          first seed (0)
+//!   --mode ID         mode from modes.toml, or "all" (campaign)
+//!   --stage NAME      just this stage (all of them)
+//!   --max-time S      give up on a run after this many seconds (600)
+//!   --resources DIR   a resources folder to read tuning, modes and stages from
+//!   --format F        csv or json (csv)
+//!   --summary         spreads per mode and stage instead of every run
+//! ```
+
+use std::collections::BTreeMap;
+use std::env;
+use std::path::PathBuf;
+
+use ggez::{GameError, GameResult};
+use oorandom::Rand32;
+use serde::Serialize;
+
+use pogin::campaign::{self, Stage};
+use pogin::modes::{self, Mode};
+use pogin::rank::{Grading, Rank};
+use pogin::sim::{End, Run, TICKS_PER_SECOND};
+use pogin::terrain::Terrain;
+use pogin::tuning::Tuning;
+use pogin::{arena, bot};
+
+struct Options {
+    runs: u64,
+    seed: u64,
+    mode: String,
+    stage: Option<String>,
+    max_time: f32,
+    resource_dir: PathBuf,
+    json: bool,
+    summary: bool,
+}
+
+fn bad(message: String) -> GameError {
+    GameError::ConfigError(message)
+}
+
+fn options() -> GameResult<Options> {
+    let resource_dir = match env::var("CARGO_MANIFEST_DIR") {
+        Ok(manifest_dir) => PathBuf::from(manifest_dir).join("resources"),
+        Err(_) => PathBuf::from("./resources"),
+    };
+    let mut options = Options {
+        runs: 100,
+        seed: 0,
+        mode: modes::CAMPAIGN.to_string(),
+        stage: None,
+        max_time: 600.0,
+        resource_dir,
+        json: false,
+        summary: false,
+    };
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--summary" {
+            options.summary = true;
+            continue;
+        }
+        let value = args.next().ok_or_else(|| bad(format!("{} needs a value", arg)))?;
+        let number = |value: &str| value.parse::<u64>().map_err(|_| bad(format!("{} wants a number, not {:?}", arg, value)));
+        match arg.as_str() {
+            "--runs" => options.runs = number(&value)?,
+            "--seed" => options.seed = number(&value)?,
+            "--mode" => options.mode = value,
+            "--stage" => options.stage = Some(value),
+            "--max-time" => options.max_time = number(&value)? as f32,
+            "--resources" => options.resource_dir = PathBuf::from(value),
+            "--format" => match value.as_str() {
+                "csv" => options.json = false,
+                "json" => options.json = true,
+                _ => return Err(bad(format!("--format is csv or json, not {:?}", value))),
+            },
+            _ => return Err(bad(format!("don't know {:?}, see the top of bin/balance.rs", arg))),
+        }
+    }
+    Ok(options)
+}
+
+/// How one run went.
+#[derive(Serialize)]
+struct Outcome {
+    mode: String,
+    stage: String,
+    seed: u64,
+    score: u32,
+    rank: Rank,
+    points: f32,
+    caught: u32,
+    missed: u32,
+    max_combo: u32,
+    damage: i32,
+    time: f32,
+    /// "out_of_life", "finished", or "time_up" if it hit `--max-time`.
+    end: &'static str,
+    /// Finished with the stage's rank or better.
+    cleared: bool,
+}
+
+fn play(mode: &Mode, stage: &Stage, seed: u64, options: &Options, tuning: &Tuning, grading: &Grading) -> Outcome {
+    let rules = &mode.rules;
+    let mut rng = Rand32::new(seed);
+    let played = if rules.random_map { arena::arena(stage, &mut rng, tuning) } else { stage.clone() };
+    let mut run = Run::new(rules.start_life(tuning), rng, &played);
+    if rules.random_map {
+        run.terrain = Terrain::from_layout(&played.layout());
+    }
+    let max_ticks = (options.max_time * TICKS_PER_SECOND as f32) as u64;
+    let mut end = "time_up";
+    for _ in 0..max_ticks {
+        let input = bot::think(&run, tuning);
+        match run.tick(&input, tuning, rules, stage, false).end {
+            Some(End::OutOfLife) => end = "out_of_life",
+            Some(End::Finished) => end = "finished",
+            None => continue,
+        }
+        break;
+    }
+    let grade = grading.get(rules.policy_name(stage)).grade(&run.performance());
+    Outcome {
+        mode: mode.id.clone(),
+        stage: stage.name.clone(),
+        seed,
+        score: run.score,
+        rank: grade.rank,
+        points: grade.points,
+        caught: run.caught,
+        missed: run.missed,
+        max_combo: run.max_combo,
+        damage: run.damage,
+        time: run.stage_time,
+        end,
+        cleared: end == "finished" && grade.rank >= stage.rank,
+    }
+}
+
+/// Where the values of one number fell across the runs.
+#[derive(Serialize)]
+struct Spread {
+    mean: f32,
+    min: f32,
+    p10: f32,
+    median: f32,
+    p90: f32,
+    max: f32,
+}
+
+impl Spread {
+    fn of(mut values: Vec<f32>) -> Spread {
+        values.sort_by(f32::total_cmp);
+        let at = |q: f32| values[((values.len() - 1) as f32 * q).round() as usize];
+        Spread {
+            mean: values.iter().sum::<f32>() / values.len() as f32,
+            min: at(0.0),
+            p10: at(0.1),
+            median: at(0.5),
+            p90: at(0.9),
+            max: at(1.0),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct Summary {
+    mode: String,
+    stage: String,
+    runs: usize,
+    score: Spread,
+    caught: Spread,
+    missed: Spread,
+    damage: Spread,
+    time: Spread,
+    /// Fractions of the runs.
+    out_of_life: f32,
+    cleared: f32,
+    ranks: BTreeMap<Rank, usize>,
+}
+
+fn summarise(outcomes: &[Outcome]) -> Summary {
+    let spread = |f: fn(&Outcome) -> f32| Spread::of(outcomes.iter().map(f).collect());
+    let fraction = |f: fn(&Outcome) -> bool| outcomes.iter().filter(|o| f(o)).count() as f32 / outcomes.len() as f32;
+    let mut ranks = BTreeMap::new();
+    for o in outcomes {
+        *ranks.entry(o.rank).or_insert(0) += 1;
+    }
+    Summary {
+        mode: outcomes[0].mode.clone(),
+        stage: outcomes[0].stage.clone(),
+        runs: outcomes.len(),
+        score: spread(|o| o.score as f32),
+        caught: spread(|o| o.caught as f32),
+        missed: spread(|o| o.missed as f32),
+        damage: spread(|o| o.damage as f32),
+        time: spread(|o| o.time),
+        out_of_life: fraction(|o| o.end == "out_of_life"),
+        cleared: fraction(|o| o.cleared),
+        ranks,
+    }
+}
+
+/// Quoted if it has to be.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn print_runs_csv(outcomes: &[Outcome]) {
+    println!("mode,stage,seed,score,rank,points,caught,missed,max_combo,damage,time,end,cleared");
+    for o in outcomes {
+        println!("{},{},{},{},{},{:.1},{},{},{},{},{:.2},{},{}",
+            csv_field(&o.mode), csv_field(&o.stage), o.seed, o.score, o.rank, o.points,
+            o.caught, o.missed, o.max_combo, o.damage, o.time, o.end, o.cleared);
+    }
+}
+
+fn print_summaries_csv(summaries: &[Summary]) {
+    let spreads = ["score", "caught", "missed", "damage", "time"];
+    let columns = spreads.iter()
+        .flat_map(|name| ["mean", "min", "p10", "median", "p90", "max"].map(|s| format!("{}_{}", name, s)))
+        .collect::<Vec<_>>();
+    println!("mode,stage,runs,{},out_of_life,cleared,ranks", columns.join(","));
+    for s in summaries {
+        let values = [&s.score, &s.caught, &s.missed, &s.damage, &s.time].iter()
+            .flat_map(|v| [v.mean, v.min, v.p10, v.median, v.p90, v.max].map(|x| format!("{:.2}", x)))
+            .collect::<Vec<_>>();
+        let ranks = s.ranks.iter().map(|(rank, count)| format!("{}={}", rank, count)).collect::<Vec<_>>();
+        println!("{},{},{},{},{:.3},{:.3},{}",
+            csv_field(&s.mode), csv_field(&s.stage), s.runs, values.join(","),
+            s.out_of_life, s.cleared, csv_field(&ranks.join(" ")));
+    }
+}
+
+fn print_json<T: Serialize>(value: &T) -> GameResult {
+    let text = serde_json::to_string_pretty(value).map_err(|e| bad(e.to_string()))?;
+    println!("{}", text);
+    Ok(())
+}
+
+fn main() -> GameResult {
+    let options = options()?;
+    let dir = &options.resource_dir;
+    let tuning = Tuning::load(dir);
+    let grading = Grading::load(dir)?;
+    let all_modes = modes::load_modes(dir)?;
+    let stages = campaign::load_stages(dir)?;
+
+    let modes = all_modes.iter()
+        .filter(|m| options.mode == "all" || m.id == options.mode)
+        .collect::<Vec<_>>();
+    if modes.is_empty() {
+        return Err(bad(format!("no mode {:?} in {}", options.mode, modes::MODES_FILE)));
+    }
+    let stages = stages.iter()
+        .filter(|s| options.stage.as_ref().is_none_or(|name| &s.name == name))
+        .collect::<Vec<_>>();
+    if stages.is_empty() {
+        return Err(bad(format!("no stage {:?} in {}", options.stage.unwrap_or_default(), campaign::STAGES_FILE)));
+    }
+    if options.runs == 0 {
+        return Err(bad("--runs has to be at least 1".to_string()));
+    }
+
+    let mut groups = Vec::new();
+    for mode in &modes {
+        for stage in &stages {
+            let outcomes = (options.seed..options.seed + options.runs)
+                .map(|seed| play(mode, stage, seed, &options, &tuning, &grading))
+                .collect::<Vec<_>>();
+            groups.push(outcomes);
+        }
+    }
+
+    match (options.summary, options.json) {
+        (true, json) => {
+            let summaries = groups.iter().map(|g| summarise(g)).collect::<Vec<_>>();
+            if json { print_json(&summaries)? } else { print_summaries_csv(&summaries) }
+        }
+        (false, json) => {
+            let outcomes = groups.into_iter().flatten().collect::<Vec<_>>();
+            if json { print_json(&outcomes)? } else { print_runs_csv(&outcomes) }
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,68 @@
+//! A scripted player for the balance simulator. It runs under whichever candy
+//! will come down to the cat soonest, out of the ones it can still get to.
+//! It only walks, hopping up onto ground that's in its way in random arenas
+//! but no other jumps, dashes or dodging, so what it scores is a floor for how
+//! a stage plays rather than how well it can be played.
+
+use crate::sim::Run;
+use crate::trajectory::{self, Ground};
+use crate::tuning::Tuning;
+use crate::{ControllerState, SCREEN_SIZE};
+
+/// Close enough to the spot to stop pushing, in pixels.
+const NEAR: f32 = 4.0;
+/// How hard it leans into the gap: wanted speed per pixel of it.
+const EAGERNESS: f32 = 6.0;
+/// How far ahead it looks for ground in the way, in pixels.
+const LOOK_AHEAD: f32 = 16.0;
+
+/// Where and when a candy will reach the cat's height.
+struct Landing {
+    x: f32,
+    time: f32,
+}
+
+fn landings(run: &Run, tuning: &Tuning, height: f32) -> Vec<Landing> {
+    let half_width = SCREEN_SIZE.0 / 2.0;
+    let ground = Ground::flat(height);
+    let world = &run.world;
+    world.catchables.iter()
+        .filter_map(|(e, _)| {
+            let (transform, body) = (world.transforms.get(e)?, world.bodies.get(e)?);
+            let gravity = tuning.gravity * body.gravity_scale;
+            let landing = trajectory::landing(transform.pos, body.velocity, gravity, &ground)?;
+            Some(Landing { x: landing.point.x.clamp(-half_width, half_width), time: landing.time })
+        })
+        .collect()
+}
+
+/// The buttons it would hold this tick.
+pub fn think(run: &Run, tuning: &Tuning) -> ControllerState {
+    let world = &run.world;
+    let (Some(transform), Some(body)) = (world.transforms.get(run.player), world.bodies.get(run.player)) else {
+        return ControllerState::default();
+    };
+    let (pos, velocity) = (transform.pos, body.velocity);
+
+    // Soonest it can make it to in time, or just the soonest if it can't make any.
+    let candy = landings(run, tuning, pos.y);
+    let reachable = |l: &&Landing| (l.x - pos.x).abs() / tuning.player_vel <= l.time;
+    let soonest = |a: &&Landing, b: &&Landing| a.time.total_cmp(&b.time);
+    let target = candy.iter().filter(reachable).min_by(soonest)
+        .or_else(|| candy.iter().min_by(soonest));
+    let Some(target) = target else {
+        return ControllerState::default();
+    };
+
+    let gap = target.x - pos.x;
+    let wanted = (gap * EAGERNESS).clamp(-tuning.player_vel, tuning.player_vel);
+    let mut input = ControllerState::default();
+    if gap.abs() > NEAR || velocity.x.abs() > tuning.player_vel * 0.5 {
+        input.right = velocity.x < wanted;
+        input.left = velocity.x > wanted;
+    }
+    // Held all the way up, since letting go early cuts the jump short.
+    let blocked = run.terrain.blocks(pos.x + LOOK_AHEAD * gap.signum(), pos.y);
+    input.up = if run.player().grounded { blocked } else { velocity.y > 0.0 };
+    input
+}
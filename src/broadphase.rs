@@ -0,0 +1,120 @@
+//! Finds what might be touching what, without checking every pair. Everything
+//! with a collider goes in a uniform grid of map-tile-sized cells, covering the
+//! whole path it took this tick, so fast things can't skip through each other.
+
+use std::collections::{BTreeSet, HashMap};
+
+use glam::*;
+
+use crate::ecs::{Entity, World};
+
+/// One map tile.
+pub const CELL_SIZE: f32 = 32.0;
+
+pub struct Grid {
+    cells: HashMap<IVec2, Vec<Entity>>,
+}
+
+fn cell_of(p: Vec2) -> IVec2 {
+    (p / CELL_SIZE).floor().as_ivec2()
+}
+
+/// Box around a circle moving from `from` to `to`.
+fn swept_bounds(from: Vec2, to: Vec2, radius: f32) -> (Vec2, Vec2) {
+    (from.min(to) - Vec2::splat(radius), from.max(to) + Vec2::splat(radius))
+}
+
+impl Grid {
+    /// Call after everything has moved for the tick.
+    pub fn build(world: &World) -> Grid {
+        let mut cells: HashMap<IVec2, Vec<Entity>> = HashMap::new();
+        for (e, collider) in world.colliders.iter() {
+            let Some(transform) = world.transforms.get(e) else { continue };
+            let (min, max) = swept_bounds(transform.prev_pos, transform.pos, collider.radius);
+            let (min, max) = (cell_of(min), cell_of(max));
+            for x in min.x..=max.x {
+                for y in min.y..=max.y {
+                    cells.entry(ivec2(x, y)).or_default().push(e);
+                }
+            }
+        }
+        Grid { cells }
+    }
+
+    /// Everything whose path this tick went through the box. Might include
+    /// things that only came near it. In entity order rather than hash order,
+    /// so whatever acts on them does it the same way every run.
+    pub fn query_aabb(&self, min: Vec2, max: Vec2) -> BTreeSet<Entity> {
+        let (min, max) = (cell_of(min), cell_of(max));
+        let mut found = BTreeSet::new();
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                if let Some(cell) = self.cells.get(&ivec2(x, y)) {
+                    found.extend(cell.iter().copied());
+                }
+            }
+        }
+        found
+    }
+
+    /// Everything that touched `entity` at some point this tick, with how far
+    /// through the tick (0 to 1) they first touched. Soonest first, and in
+    /// entity order when that's a tie, like everything already overlapping.
+    pub fn touching(&self, world: &World, entity: Entity) -> Vec<(Entity, f32)> {
+        let (Some(a), Some(a_collider)) = (world.transforms.get(entity), world.colliders.get(entity)) else {
+            return Vec::new();
+        };
+        let (min, max) = swept_bounds(a.prev_pos, a.pos, a_collider.radius);
+        let mut hits = self.query_aabb(min, max).into_iter()
+            .filter(|&other| other != entity)
+            .filter_map(|other| {
+                let b = world.transforms.get(other)?;
+                let radius = a_collider.radius + world.colliders.get(other)?.radius;
+                swept_circles(a.prev_pos, a.pos, b.prev_pos, b.pos, radius).map(|t| (other, t))
+            })
+            .collect::<Vec<_>>();
+        hits.sort_by(|x, y| x.1.total_cmp(&y.1).then(x.0.cmp(&y.0)));
+        hits
+    }
+}
+
+/// Two circles moving in straight lines over a tick, `a` from `a0` to `a1` and
+/// `b` from `b0` to `b1`. If they come within `radius` (both radii added
+/// together) of each other, returns how far through the tick that first happens.
+pub fn swept_circles(a0: Vec2, a1: Vec2, b0: Vec2, b1: Vec2, radius: f32) -> Option<f32> {
+    let start = b0 - a0;
+    if start.length_squared() <= radius * radius {
+        return Some(0.0);
+    }
+    let motion = (b1 - a1) - start;
+    // |start + motion t| = radius
+    let a = motion.length_squared();
+    let b = 2.0 * start.dot(motion);
+    let c = start.length_squared() - radius * radius;
+    let disc = b * b - 4.0 * a * c;
+    if a == 0.0 || disc < 0.0 {
+        return None;
+    }
+    let t = (-b - disc.sqrt()) / (2.0 * a);
+    (0.0..=1.0).contains(&t).then_some(t)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fast_candy_cannot_pass_through_the_cat() {
+        // Far enough in one tick to be well clear on both sides.
+        let cat = vec2(0.0, 0.0);
+        let t = swept_circles(cat, cat, vec2(0.0, 100.0), vec2(0.0, -100.0), 20.0);
+        assert!(t.is_some());
+        assert!((t.unwrap() - 0.4).abs() < 1e-4);
+    }
+
+    #[test]
+    fn near_miss_is_a_miss() {
+        let cat = vec2(0.0, 0.0);
+        assert_eq!(swept_circles(cat, cat, vec2(30.0, 100.0), vec2(30.0, -100.0), 20.0), None);
+    }
+}
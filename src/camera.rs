@@ -0,0 +1,37 @@
+use glam::*;
+use serde::{Deserialize, Serialize};
+
+use crate::FLOOR_Y;
+
+/// How fast the camera catches up, per second. Bigger is snappier.
+const FOLLOW_RATE: f32 = 4.0;
+
+/// Where the camera sits with the cat standing in the middle. Backgrounds
+/// are laid out for this.
+const REST: Vec2 = Vec2::new(0.0, FLOOR_Y);
+
+/// The playfield always fits the screen, so the camera never moves the game
+/// itself. It trails the cat, and background layers slide against it to look deep.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Camera {
+    /// World coordinates, like everything else.
+    pub pos: Vec2,
+}
+
+impl Default for Camera {
+    fn default() -> Self {
+        Camera { pos: REST }
+    }
+}
+
+impl Camera {
+    pub fn follow(&mut self, target: Vec2, dt: f32) {
+        self.pos += (target - self.pos) * (1.0 - (-FOLLOW_RATE * dt).exp());
+    }
+
+    /// How far it is from resting, in screen pixels (y down).
+    pub fn offset(&self) -> Vec2 {
+        let d = self.pos - REST;
+        vec2(d.x, -d.y)
+    }
+}
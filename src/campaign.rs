@@ -0,0 +1,355 @@
+//! The stages you play through, read from `resources/stages.toml`, and how far
+//! the player has got, saved in the user dir.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use ggez::{Context, GameError, GameResult};
+use glam::*;
+use serde::{Deserialize, Serialize};
+
+use crate::backdrop::{Backdrop, Layer, Look};
+use crate::decor::{Decor, DecorLayer};
+use crate::ecs::HazardKind;
+use crate::hud::{self, Hud};
+use crate::locale::Locale;
+use crate::rank::{self, Rank};
+use crate::storage;
+use crate::tileset::TileSet;
+use crate::{player_start, SCREEN_SIZE, SPAWN_SPREAD};
+
+pub const STAGES_FILE: &str = "stages.toml";
+pub const PROGRESS_FILE: &str = "progress.toml";
+
+/// Map size in tiles.
+pub const MAP_WIDTH: usize = 20;
+pub const MAP_HEIGHT: usize = 15;
+
+/// What finishes a stage.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Goal {
+    /// Catch this many candy.
+    Candy(u32),
+    /// Last this many seconds.
+    Time(f32),
+}
+
+/// Candy drops in from somewhere between these, in world x.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct SpawnZone {
+    pub left: f32,
+    pub right: f32,
+}
+
+/// A hazard that's there from the start, in modes that have them.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct PlacedHazard {
+    pub kind: HazardKind,
+    /// In world coordinates.
+    pub at: Vec2,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Stage {
+    pub name: String,
+    /// One string per row, top first. `#` is ground, anything else is empty.
+    pub map: Vec<String>,
+    pub tileset: String,
+    /// Background layers, back to front.
+    #[serde(rename = "layer")]
+    pub layers: Vec<Layer>,
+    pub goal: Goal,
+    /// Rank needed at the end to clear the stage and unlock the next one.
+    pub rank: Rank,
+    /// Grading policy in grading.toml.
+    #[serde(default = "default_grading")]
+    pub grading: String,
+    /// HUD layout file in resources.
+    #[serde(default = "default_hud")]
+    pub hud: String,
+    /// Where the cat starts, in world coordinates, if not the usual spot.
+    #[serde(default)]
+    pub start: Option<Vec2>,
+    /// Where candy comes from. None means the usual spread across the middle.
+    #[serde(default, rename = "spawn")]
+    pub spawns: Vec<SpawnZone>,
+    #[serde(default, rename = "hazard")]
+    pub hazards: Vec<PlacedHazard>,
+    /// Tile layers drawn behind or in front of the cat, on top of `map`.
+    #[serde(default)]
+    pub decor: Vec<DecorLayer>,
+}
+
+fn default_grading() -> String {
+    rank::DEFAULT_POLICY.to_string()
+}
+
+fn default_hud() -> String {
+    hud::DEFAULT_HUD.to_string()
+}
+
+#[derive(Deserialize)]
+struct StagesFile {
+    stage: Vec<Stage>,
+}
+
+pub fn load_stages(resource_dir: &Path) -> GameResult<Vec<Stage>> {
+    let path = resource_dir.join(STAGES_FILE);
+    let stages = storage::read_toml::<StagesFile>(&path)?
+        .ok_or_else(|| GameError::ResourceNotFound(path.display().to_string(), Vec::new()))?
+        .stage;
+    if stages.is_empty() {
+        return Err(GameError::ConfigError(format!("{}: no stages", path.display())));
+    }
+    for (i, stage) in stages.iter().enumerate() {
+        // Progress, saves and replays all find stages by name.
+        if stages[..i].iter().any(|s| s.name == stage.name) {
+            return Err(GameError::ConfigError(format!("{}: more than one stage is called {:?}", path.display(), stage.name)));
+        }
+        let fits = stage.map.len() == MAP_HEIGHT && stage.map.iter().all(|row| row.chars().count() == MAP_WIDTH);
+        if !fits {
+            return Err(GameError::ConfigError(format!(
+                "{}: map for {:?} should be {} rows of {}", path.display(), stage.name, MAP_HEIGHT, MAP_WIDTH)));
+        }
+        if let Some(problem) = stage.decor.iter().find_map(|layer| layer.problem()) {
+            return Err(GameError::ConfigError(format!("{}: decor for {:?}: {}", path.display(), stage.name, problem)));
+        }
+    }
+    Ok(stages)
+}
+
+/// Writes `stages` back over the stages file the way it's written by hand,
+/// keeping the comments at the top. Any further down are lost.
+pub fn save_stages(resource_dir: &Path, stages: &[Stage]) -> GameResult {
+    let path = resource_dir.join(STAGES_FILE);
+    let mut text = storage::header(&path);
+    for stage in stages {
+        text += &stage_toml(stage);
+    }
+    fs::write(&path, text.trim_end().to_string() + "\n")?;
+    Ok(())
+}
+
+fn quote(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// With a `.0` on whole numbers, which toml would otherwise read as integers.
+fn float(x: f32) -> String {
+    let text = x.to_string();
+    if text.contains(['.', 'e', 'N', 'i']) { text } else { text + ".0" }
+}
+
+fn pair([x, y]: [f32; 2]) -> String {
+    format!("[{}, {}]", float(x), float(y))
+}
+
+fn stage_toml(stage: &Stage) -> String {
+    let mut out = String::from("[[stage]]\n");
+    out += &format!("name = {}\n", quote(&stage.name));
+    out += &format!("tileset = {}\n", quote(&stage.tileset));
+    out += &match stage.goal {
+        Goal::Candy(n) => format!("goal = {{ candy = {} }}\n", n),
+        Goal::Time(t) => format!("goal = {{ time = {} }}\n", float(t)),
+    };
+    out += &format!("rank = {}\n", quote(stage.rank.as_str()));
+    if stage.grading != default_grading() {
+        out += &format!("grading = {}\n", quote(&stage.grading));
+    }
+    if stage.hud != default_hud() {
+        out += &format!("hud = {}\n", quote(&stage.hud));
+    }
+    if let Some(start) = stage.start {
+        out += &format!("start = {}\n", pair(start.into()));
+    }
+    out += "map = [\n";
+    for row in &stage.map {
+        out += &format!("    {},\n", quote(row));
+    }
+    out += "]\n\n";
+    for zone in &stage.spawns {
+        out += &format!("[[stage.spawn]]\nleft = {}\nright = {}\n\n", float(zone.left), float(zone.right));
+    }
+    for hazard in &stage.hazards {
+        out += &format!("[[stage.hazard]]\nkind = {}\nat = {}\n\n", quote(&format!("{:?}", hazard.kind)), pair(hazard.at.into()));
+    }
+    for layer in &stage.decor {
+        out += &format!("[[stage.decor]]\ntileset = {}\n", quote(&layer.tileset));
+        if layer.front {
+            out += "front = true\n";
+        }
+        out += "map = [\n";
+        for row in &layer.map {
+            out += &format!("    {},\n", quote(row));
+        }
+        out += "]\n\n";
+    }
+    for layer in &stage.layers {
+        out += "[[stage.layer]]\n";
+        out += &match &layer.look {
+            Look::Image(path) => format!("look = {{ image = {} }}\n", quote(path)),
+            Look::Moon => "look = \"moon\"\n".to_string(),
+            Look::Clouds => "look = \"clouds\"\n".to_string(),
+            Look::Fog => "look = \"fog\"\n".to_string(),
+        };
+        if layer.scroll != 0.0 {
+            out += &format!("scroll = {}\n", float(layer.scroll));
+        }
+        if layer.tile {
+            out += "tile = true\n";
+        }
+        if layer.offset != [0.0; 2] {
+            out += &format!("offset = {}\n", pair(layer.offset));
+        }
+        if layer.drift != [0.0; 2] {
+            out += &format!("drift = {}\n", pair(layer.drift));
+        }
+        if layer.bob != 0.0 {
+            out += &format!("bob = {}\n", float(layer.bob));
+        }
+        out += "\n";
+    }
+    out
+}
+
+/// An empty map with an easy goal, for tests that need a stage to play.
+#[cfg(test)]
+pub(crate) fn test_stage() -> Stage {
+    Stage {
+        name: "Test".to_string(),
+        map: vec![".".repeat(MAP_WIDTH); MAP_HEIGHT],
+        tileset: String::new(),
+        layers: Vec::new(),
+        goal: Goal::Candy(10),
+        rank: Rank::C,
+        grading: String::new(),
+        hud: String::new(),
+        start: None,
+        spawns: Vec::new(),
+        hazards: Vec::new(),
+        decor: Vec::new(),
+    }
+}
+
+impl Stage {
+    /// Tiles the way the game draws them, bottom row first.
+    pub fn layout(&self) -> Vec<Vec<usize>> {
+        self.map.iter().rev()
+            .map(|row| row.chars().map(|c| (c == '#') as usize).collect())
+            .collect()
+    }
+
+    /// Whether the tile at `column`, `row` (top first, like `map`) is ground.
+    pub fn tile(&self, column: usize, row: usize) -> bool {
+        self.map[row].chars().nth(column) == Some('#')
+    }
+
+    pub fn set_tile(&mut self, column: usize, row: usize, solid: bool) {
+        let line = &mut self.map[row];
+        *line = line.chars().enumerate()
+            .map(|(x, c)| if x == column { if solid { '#' } else { '.' } } else { c })
+            .collect();
+    }
+
+    pub fn start(&self) -> Vec2 {
+        self.start.unwrap_or_else(player_start)
+    }
+
+    /// `spawns`, or the usual spread if there aren't any.
+    pub fn spawn_zones(&self) -> Vec<SpawnZone> {
+        if self.spawns.is_empty() {
+            let spread = SCREEN_SIZE.0 * SPAWN_SPREAD;
+            return vec![SpawnZone { left: -spread, right: spread }];
+        }
+        self.spawns.clone()
+    }
+
+    /// Where a candy drops from for a roll of the dice from 0 to 1. Zones
+    /// get candy in proportion to how wide they are.
+    pub fn spawn_x(&self, roll: f32) -> f32 {
+        let zones = self.spawn_zones();
+        let mut along = roll * zones.iter().map(|z| (z.right - z.left).abs()).sum::<f32>();
+        for zone in &zones {
+            let width = (zone.right - zone.left).abs();
+            if along < width {
+                return zone.left.min(zone.right) + along;
+            }
+            along -= width;
+        }
+        zones.last().map_or(0.0, |z| z.left.max(z.right))
+    }
+
+    pub fn load_map(&self, ctx: &mut Context) -> GameResult<(Vec<Vec<usize>>, TileSet)> {
+        Ok((self.layout(), TileSet::autotiled(ctx, &self.tileset)?))
+    }
+
+    pub fn load_decor(&self, ctx: &mut Context) -> GameResult<Decor> {
+        Decor::load(ctx, &self.decor)
+    }
+
+    pub fn load_backdrop(&self, ctx: &mut Context) -> GameResult<Backdrop> {
+        Backdrop::load(ctx, &self.layers)
+    }
+
+    pub fn load_hud(&self, ctx: &mut Context) -> GameResult<Hud> {
+        Hud::load(ctx, &self.hud)
+    }
+
+    pub fn goal_text(&self, locale: &Locale) -> String {
+        match self.goal {
+            Goal::Candy(n) => locale.fmt("goal.candy", &[("count", &n)]),
+            Goal::Time(t) => locale.fmt("goal.time", &[("seconds", &t.round())]),
+        }
+    }
+}
+
+/// Which stages are open and the best rank on each, by stage name.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Progress {
+    /// How many stages, from the first, can be played.
+    pub unlocked: usize,
+    pub best: BTreeMap<String, Rank>,
+}
+
+impl Default for Progress {
+    fn default() -> Self {
+        Progress { unlocked: 1, best: BTreeMap::new() }
+    }
+}
+
+impl Progress {
+    pub fn load() -> Progress {
+        match storage::load(PROGRESS_FILE) {
+            Ok(Some(progress)) => progress,
+            Ok(None) => Progress::default(),
+            Err(e) => {
+                println!("Couldn't read progress, starting over: {}", e);
+                Progress::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = storage::save(PROGRESS_FILE, self) {
+            println!("Couldn't save progress: {}", e);
+        }
+    }
+
+    pub fn is_unlocked(&self, stage: usize) -> bool {
+        stage < self.unlocked.max(1)
+    }
+
+    /// Records a finished stage. Returns true if it was cleared.
+    pub fn finish(&mut self, stages: &[Stage], stage: usize, rank: Rank) -> bool {
+        let cleared = rank >= stages[stage].rank;
+        if cleared {
+            self.unlocked = self.unlocked.max(stage + 2).min(stages.len());
+            let best = self.best.entry(stages[stage].name.clone()).or_default();
+            *best = rank.max(*best);
+        }
+        cleared
+    }
+}
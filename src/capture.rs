@@ -0,0 +1,270 @@
+//! Screenshots and clips. Both go in a `screenshots` folder in the user dir,
+//! named for when they were taken. For clips, a few small frames a second are
+//! kept in a rolling buffer while the game draws, and saving one writes the
+//! last few seconds out as a GIF or a folder of PNGs on another thread.
+//! `FrameDump` is the offline kind, every frame of a replay at full size.
+
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use ggez::graphics;
+use ggez::{Context, GameError, GameResult};
+use image::codecs::gif::{GifEncoder, Repeat};
+use image::imageops::{self, FilterType};
+use image::{Delay, Frame, ImageResult, RgbaImage};
+
+use crate::settings::{Capture, ClipFormat};
+use crate::{storage, SCREEN_SIZE};
+
+pub const FOLDER: &str = "screenshots";
+
+/// How hard the GIF encoder works on its palette, 1 to 30. Higher is faster
+/// and a bit blotchier.
+const GIF_SPEED: i32 = 10;
+
+pub fn folder() -> GameResult<PathBuf> {
+    Ok(storage::user_dir()?.join(FOLDER))
+}
+
+/// `2021-10-31_18-04-05`, in UTC so it doesn't need a timezone database.
+fn timestamp(now: SystemTime) -> String {
+    let secs = now.duration_since(UNIX_EPOCH).map_or(0, |d| d.as_secs());
+    let (days, time) = ((secs / 86400) as i64, secs % 86400);
+    // Days since 1970 to a date, from http://howardhinnant.github.io/date_algorithms.html
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let day_of_era = z - era * 146097;
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36524 - day_of_era / 146096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + (month <= 2) as i64;
+    format!("{}-{:02}-{:02}_{:02}-{:02}-{:02}", year, month, day, time / 3600, time / 60 % 60, time % 60)
+}
+
+/// `dir/<prefix><timestamp><ext>`, with `-2`, `-3`... on the end if there's
+/// already one from the same second.
+pub fn free_path(dir: &Path, prefix: &str, ext: &str) -> PathBuf {
+    let stem = format!("{}{}", prefix, timestamp(SystemTime::now()));
+    let mut path = dir.join(format!("{}{}", stem, ext));
+    let mut n = 2;
+    while path.exists() {
+        path = dir.join(format!("{}-{}{}", stem, n, ext));
+        n += 1;
+    }
+    path
+}
+
+fn save_error(path: &Path, e: impl std::fmt::Display) -> GameError {
+    GameError::FilesystemError(format!("{}: {}", path.display(), e))
+}
+
+/// What's been drawn so far this frame, the size the window really is.
+fn grab(ctx: &mut Context) -> GameResult<RgbaImage> {
+    let image = graphics::screenshot(ctx)?;
+    let (width, height) = (image.width() as u32, image.height() as u32);
+    RgbaImage::from_raw(width, height, image.to_rgba8(ctx)?)
+        .ok_or_else(|| GameError::RenderError("Screenshot came back the wrong size".to_string()))
+}
+
+/// Saves what's been drawn so far this frame, so call it before `present`.
+pub fn screenshot(ctx: &mut Context) -> GameResult<PathBuf> {
+    let frame = grab(ctx)?;
+    let dir = folder()?;
+    fs::create_dir_all(&dir)?;
+    let path = free_path(&dir, "", ".png");
+    frame.save(&path).map_err(|e| save_error(&path, e))?;
+    Ok(path)
+}
+
+fn write_gif(path: &Path, frames: Vec<RgbaImage>, fps: u32) -> ImageResult<()> {
+    let mut encoder = GifEncoder::new_with_speed(BufWriter::new(File::create(path)?), GIF_SPEED);
+    encoder.set_repeat(Repeat::Infinite)?;
+    let delay = Delay::from_numer_denom_ms(1000, fps);
+    encoder.encode_frames(frames.into_iter().map(|f| Frame::from_parts(f, 0, 0, delay)))
+}
+
+fn write_pngs(dir: &Path, frames: Vec<RgbaImage>) -> ImageResult<()> {
+    fs::create_dir_all(dir)?;
+    for (i, frame) in frames.iter().enumerate() {
+        frame.save(dir.join(format!("{:04}.png", i)))?;
+    }
+    Ok(())
+}
+
+/// The last few seconds of frames, see `Capture` in the settings.
+#[derive(Default)]
+pub struct Recorder {
+    frames: VecDeque<RgbaImage>,
+    /// Real seconds since the last frame was kept.
+    since: f32,
+    /// Set when a frame couldn't be grabbed, so it stops trying.
+    failed: bool,
+    /// The clip being written, if one is.
+    saving: Option<Receiver<Result<PathBuf, String>>>,
+}
+
+impl Recorder {
+    /// Keeps what's been drawn so far this frame if it's time for another,
+    /// so call it before `present`. Once grabbing a frame fails it gives up
+    /// for good, and only that first error comes back.
+    pub fn capture(&mut self, ctx: &mut Context, settings: &Capture, dt: f32) -> GameResult {
+        if settings.clip_seconds <= 0.0 || self.failed {
+            self.frames.clear();
+            return Ok(());
+        }
+        let every = 1.0 / settings.clip_fps.max(1) as f32;
+        self.since += dt;
+        if self.since < every {
+            return Ok(());
+        }
+        // Not trying to catch up after a slow frame, just keeping time.
+        self.since = (self.since - every).min(every);
+
+        let frame = grab(ctx).inspect_err(|_| self.failed = true)?;
+        let scale = settings.clip_scale.clamp(0.1, 1.0);
+        let (width, height) = ((SCREEN_SIZE.0 * scale) as u32, (SCREEN_SIZE.1 * scale) as u32);
+        self.frames.push_back(imageops::resize(&frame, width, height, FilterType::Triangle));
+        let keep = (settings.clip_seconds * settings.clip_fps.max(1) as f32).ceil() as usize;
+        while self.frames.len() > keep {
+            self.frames.pop_front();
+        }
+        Ok(())
+    }
+
+    pub fn is_saving(&self) -> bool {
+        self.saving.is_some()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Starts writing out what's in the buffer, and keeps recording. Check on
+    /// it with `finished`.
+    pub fn save(&mut self, settings: &Capture) -> GameResult {
+        let dir = folder()?;
+        fs::create_dir_all(&dir)?;
+        let frames = self.frames.iter().cloned().collect::<Vec<_>>();
+        let (format, fps) = (settings.clip_format, settings.clip_fps.max(1));
+        let (send, receive) = mpsc::channel();
+        thread::spawn(move || {
+            let result = match format {
+                ClipFormat::Gif => {
+                    let path = free_path(&dir, "clip_", ".gif");
+                    write_gif(&path, frames, fps).map(|_| path.clone()).map_err(|e| save_error(&path, e))
+                }
+                ClipFormat::Png => {
+                    let path = free_path(&dir, "clip_", "");
+                    write_pngs(&path, frames).map(|_| path.clone()).map_err(|e| save_error(&path, e))
+                }
+            };
+            // Only the message is needed, for the toast.
+            let _ = send.send(result.map_err(|e| e.to_string()));
+        });
+        self.saving = Some(receive);
+        Ok(())
+    }
+
+    /// Where the clip went, or why it didn't, once it's done.
+    pub fn finished(&mut self) -> Option<Result<PathBuf, String>> {
+        let result = match self.saving.as_ref()?.try_recv() {
+            Ok(result) => result,
+            Err(mpsc::TryRecvError::Empty) => return None,
+            Err(mpsc::TryRecvError::Disconnected) => Err("The clip writer stopped".to_string()),
+        };
+        self.saving = None;
+        Some(result)
+    }
+}
+
+enum Output {
+    /// A numbered PNG per frame.
+    Folder(PathBuf),
+    /// Raw RGBA frames into a program's stdin, e.g. ffmpeg.
+    Pipe(Child),
+}
+
+/// Every frame drawn, at whatever size the window is, for rendering replays.
+/// Nothing here knows about time, it just takes a frame each call.
+pub struct FrameDump {
+    output: Output,
+    frames: u32,
+    /// Of the first frame, which the rest have to match for a pipe.
+    size: Option<(u32, u32)>,
+}
+
+impl FrameDump {
+    pub fn folder(dir: &Path) -> GameResult<FrameDump> {
+        fs::create_dir_all(dir)?;
+        Ok(FrameDump { output: Output::Folder(dir.to_path_buf()), frames: 0, size: None })
+    }
+
+    /// Runs `command` through the shell and writes frames into it.
+    pub fn pipe(command: &str) -> GameResult<FrameDump> {
+        let shell = if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") };
+        let child = Command::new(shell.0).args([shell.1, command])
+            .stdin(Stdio::piped())
+            .spawn()
+            .map_err(|e| GameError::CustomError(format!("Couldn't run {:?}: {}", command, e)))?;
+        Ok(FrameDump { output: Output::Pipe(child), frames: 0, size: None })
+    }
+
+    /// Takes what's been drawn so far this frame, so call it before `present`.
+    pub fn write(&mut self, ctx: &mut Context) -> GameResult {
+        let frame = grab(ctx)?;
+        let size = frame.dimensions();
+        if self.size.is_none() {
+            println!("Frames are {}x{}", size.0, size.1);
+        }
+        if *self.size.get_or_insert(size) != size {
+            return Err(GameError::RenderError(format!("The window changed size to {}x{} partway through", size.0, size.1)));
+        }
+        match &mut self.output {
+            Output::Folder(dir) => {
+                let path = dir.join(format!("{:06}.png", self.frames));
+                frame.save(&path).map_err(|e| save_error(&path, e))?;
+            }
+            Output::Pipe(child) => {
+                let stdin = child.stdin.as_mut().expect("spawned with a piped stdin");
+                stdin.write_all(frame.as_raw())?;
+            }
+        }
+        self.frames += 1;
+        Ok(())
+    }
+
+    /// Lets the program know that's all and waits for it. How many frames
+    /// there were.
+    pub fn finish(self) -> GameResult<u32> {
+        if let Output::Pipe(mut child) = self.output {
+            drop(child.stdin.take());
+            let status = child.wait()?;
+            if !status.success() {
+                return Err(GameError::CustomError(format!("The frame pipe exited with {}", status)));
+            }
+        }
+        Ok(self.frames)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn timestamps_are_utc_dates() {
+        let at = |secs| timestamp(UNIX_EPOCH + Duration::from_secs(secs));
+        assert_eq!(at(0), "1970-01-01_00-00-00");
+        assert_eq!(at(951782400), "2000-02-29_00-00-00");
+        assert_eq!(at(1635703445), "2021-10-31_18-04-05");
+    }
+}
@@ -0,0 +1,85 @@
+//! Shows what the simulation sees: colliders, velocities, where candy will
+//! land, which tiles are solid, and the lines the game checks against.
+//! F3 toggles it. Only built into debug builds.
+
+use ggez::graphics::{self, Color, DrawMode, MeshBuilder, Rect};
+use ggez::{Context, GameResult};
+use glam::*;
+
+use pogin::trajectory;
+
+use crate::{world_to_screen_coords, MainState, FLOOR_Y, SCREEN_SIZE, SPAWN_HEIGHT};
+
+const COLLIDER: Color = Color::new(0.2, 1.0, 0.2, 1.0);
+const VELOCITY: Color = Color::new(0.3, 0.6, 1.0, 1.0);
+const LANDING: Color = Color::new(1.0, 0.3, 0.3, 1.0);
+const SOLID: Color = Color::new(1.0, 0.0, 0.0, 0.25);
+const GUIDE: Color = Color::new(1.0, 1.0, 0.0, 0.6);
+
+/// Velocity lines show where something will be this far ahead, in seconds.
+const VELOCITY_LOOKAHEAD: f32 = 0.25;
+
+pub fn draw(ctx: &mut Context, state: &MainState) -> GameResult {
+    let (w, h) = SCREEN_SIZE;
+    let to_screen = |p: Vec2| world_to_screen_coords(w, h, p);
+    let mut mb = MeshBuilder::new();
+
+    // Tiles, same layout the map is drawn with: row 0 is the bottom.
+    for (row, tiles) in state.map.0.iter().enumerate() {
+        for (x, &tile) in tiles.iter().enumerate() {
+            if tile != 0 {
+                let y = (state.map.0.len() - 1 - row) as f32 * 32.0;
+                mb.rectangle(DrawMode::fill(), Rect::new(x as f32 * 32.0, y, 32.0, 32.0), SOLID)?;
+                mb.rectangle(DrawMode::stroke(1.0), Rect::new(x as f32 * 32.0, y, 32.0, 32.0), SOLID)?;
+            }
+        }
+    }
+
+    // The floor the cat stands on and where candy spawns.
+    let floor = to_screen(vec2(0.0, FLOOR_Y)).y;
+    mb.line(&[vec2(0.0, floor), vec2(w, floor)], 1.0, GUIDE)?;
+    let spawn_y = to_screen(vec2(0.0, SPAWN_HEIGHT)).y;
+    for zone in state.stages[state.stage].spawn_zones() {
+        let (left, right) = (to_screen(vec2(zone.left, SPAWN_HEIGHT)), to_screen(vec2(zone.right, SPAWN_HEIGHT)));
+        mb.line(&[left, right], 1.0, GUIDE)?;
+        mb.line(&[vec2(left.x, spawn_y - 4.0), vec2(left.x, spawn_y + 4.0)], 1.0, GUIDE)?;
+        mb.line(&[vec2(right.x, spawn_y - 4.0), vec2(right.x, spawn_y + 4.0)], 1.0, GUIDE)?;
+    }
+
+    let world = &state.run.world;
+    for (e, collider) in world.colliders.iter() {
+        let Some(transform) = world.transforms.get(e) else { continue };
+        let pos = to_screen(transform.pos);
+        mb.circle(DrawMode::stroke(1.0), pos, collider.radius, 0.5, COLLIDER)?;
+        if let Some(body) = world.bodies.get(e) {
+            if body.velocity.length() > 1.0 {
+                mb.line(&[pos, to_screen(transform.pos + body.velocity * VELOCITY_LOOKAHEAD)], 1.0, VELOCITY)?;
+            }
+        }
+    }
+
+    for (e, _) in world.catchables.iter() {
+        let (Some(transform), Some(body)) = (world.transforms.get(e), world.bodies.get(e)) else { continue };
+        let gravity = state.tuning.gravity * body.gravity_scale;
+        if let Some(landing) = trajectory::landing(transform.pos, body.velocity, gravity, &state.ground) {
+            let landing = to_screen(landing.point);
+            mb.line(&[landing - vec2(4.0, 4.0), landing + vec2(4.0, 4.0)], 1.0, LANDING)?;
+            mb.line(&[landing - vec2(4.0, -4.0), landing + vec2(4.0, -4.0)], 1.0, LANDING)?;
+        }
+    }
+
+    let mesh = mb.build(ctx)?;
+    graphics::draw(ctx, &mesh, graphics::DrawParam::new())?;
+
+    let readout = format!(
+        "FPS: {:.0}\nEntities: {} ({} candy, {} hazards, {} animated)\nDifficulty: {:.2}",
+        ggez::timer::fps(ctx),
+        world.len(),
+        world.catchables.len(),
+        world.hazards.len(),
+        world.animators.len(),
+        state.run.difficulty,
+    );
+    let readout = state.locale.text(readout, 12.0);
+    graphics::draw(ctx, &readout, (vec2(w - 220.0, h - 60.0), 0.0, Color::WHITE))
+}
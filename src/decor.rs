@@ -0,0 +1,123 @@
+//! Tile layers that go with the ground: decoration behind the cat, like
+//! gravestones and candles, and overlays in front of it, like grass and
+//! cobwebs. None of it is solid, what the cat stands on is still the stage's
+//! `map`.
+
+use std::collections::HashMap;
+
+use ggez::graphics::{self, DrawParam};
+use ggez::{Context, GameResult};
+use glam::*;
+use serde::Deserialize;
+
+use crate::campaign::{MAP_HEIGHT, MAP_WIDTH};
+use crate::tileset::TileSet;
+use crate::trajectory::TILE_SIZE;
+
+/// Positions in the map are in tiles, like the stage's.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct DecorLayer {
+    /// A decoration tileset file, see `resources/decor.toml`.
+    pub tileset: String,
+    /// Drawn over the cat and candy instead of under them.
+    #[serde(default)]
+    pub front: bool,
+    /// The same shape as the stage's `map`, top first. `.` is empty, and `0`
+    /// to `9` then `a` to `z` pick a tile off the tileset.
+    pub map: Vec<String>,
+}
+
+impl DecorLayer {
+    /// The tile at each spot, if any, bottom row first like `Stage::layout`.
+    pub fn layout(&self) -> Vec<Vec<Option<usize>>> {
+        self.map.iter().rev()
+            .map(|row| row.chars().map(|c| c.to_digit(36).map(|d| d as usize)).collect())
+            .collect()
+    }
+
+    /// Why it won't load, if it won't.
+    pub fn problem(&self) -> Option<String> {
+        if self.map.len() != MAP_HEIGHT || self.map.iter().any(|row| row.chars().count() != MAP_WIDTH) {
+            return Some(format!("{} map should be {} rows of {}", self.tileset, MAP_HEIGHT, MAP_WIDTH));
+        }
+        let bad = self.map.iter().flat_map(|row| row.chars()).find(|&c| c != '.' && c.to_digit(36).is_none());
+        bad.map(|c| format!("{} map has {:?}, which isn't a tile", self.tileset, c))
+    }
+}
+
+struct Loaded {
+    layout: Vec<Vec<Option<usize>>>,
+    front: bool,
+    tileset: usize,
+}
+
+/// A stage's decoration layers, ready to draw.
+#[derive(Default)]
+pub struct Decor {
+    layers: Vec<Loaded>,
+    tilesets: Vec<TileSet>,
+    /// Seconds of animation so far.
+    time: f32,
+}
+
+impl Decor {
+    /// Layers sharing a tileset file share it loaded.
+    pub fn load(ctx: &mut Context, layers: &[DecorLayer]) -> GameResult<Decor> {
+        let mut decor = Decor::default();
+        let mut loaded = HashMap::new();
+        for layer in layers {
+            let tileset = match loaded.get(&layer.tileset) {
+                Some(&i) => i,
+                None => {
+                    decor.tilesets.push(TileSet::decor(ctx, &layer.tileset)?);
+                    loaded.insert(layer.tileset.clone(), decor.tilesets.len() - 1);
+                    decor.tilesets.len() - 1
+                }
+            };
+            decor.layers.push(Loaded { layout: layer.layout(), front: layer.front, tileset });
+        }
+        Ok(decor)
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.time += dt;
+    }
+
+    /// The layers in front of the cat, or the ones behind it, in order.
+    pub fn draw(&self, ctx: &mut Context, front: bool) -> GameResult {
+        for layer in self.layers.iter().filter(|l| l.front == front) {
+            let tileset = &self.tilesets[layer.tileset];
+            let image = tileset.img();
+            let scale = TILE_SIZE / image.height() as f32;
+            for (y, row) in layer.layout.iter().enumerate() {
+                for (x, tile) in row.iter().enumerate() {
+                    let Some(tile) = *tile else { continue };
+                    let frame = tileset.frame(tile, self.time, y * MAP_WIDTH + x);
+                    let params = DrawParam::new()
+                        .dest(vec2(x as f32, (MAP_HEIGHT - 1 - y) as f32) * TILE_SIZE)
+                        .src(tileset.tile_src(frame))
+                        .scale(Vec2::splat(scale));
+                    graphics::draw(ctx, &image, params)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reads_tiles_in_base_36() {
+        let mut map = vec![".".repeat(MAP_WIDTH); MAP_HEIGHT];
+        map[MAP_HEIGHT - 1] = format!("0a{}", ".".repeat(MAP_WIDTH - 2));
+        let layer = DecorLayer { tileset: "/decor.toml".to_string(), front: false, map };
+        assert_eq!(layer.problem(), None);
+        assert_eq!(&layer.layout()[0][..3], &[Some(0), Some(10), None]);
+
+        let bad = DecorLayer { map: vec!["!".repeat(MAP_WIDTH); MAP_HEIGHT], ..layer };
+        assert!(bad.problem().is_some());
+    }
+}
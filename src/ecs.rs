@@ -0,0 +1,273 @@
+//! A small entity/component store. Entities are just ids; what they are comes
+//! from which components they have. Each component type gets a column on
+//! `World`, and systems (see `systems.rs`) walk the columns they care about.
+//!
+//! To add a new kind of thing, add its components here and a spawn function
+//! in `systems.rs`; `MainState` doesn't need to know.
+
+use glam::*;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::player::Player;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Entity {
+    index: u32,
+    /// Bumped every time the index is reused, so old ids stop matching.
+    generation: u32,
+}
+
+/// One component type, indexed by entity.
+#[derive(Clone)]
+pub struct Storage<T> {
+    slots: Vec<Option<(Entity, T)>>,
+}
+
+impl<T> Default for Storage<T> {
+    fn default() -> Self {
+        Storage { slots: Vec::new() }
+    }
+}
+
+/// Saved as just the filled slots, since toml has no way to write an empty one.
+#[derive(Serialize, Deserialize)]
+struct Slot<T> {
+    entity: Entity,
+    component: T,
+}
+
+impl<T: Serialize> Serialize for Storage<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.slots.iter().flatten().map(|(entity, component)| Slot { entity: *entity, component }))
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Storage<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut storage = Storage::default();
+        for slot in Vec::<Slot<T>>::deserialize(deserializer)? {
+            storage.insert(slot.entity, slot.component);
+        }
+        Ok(storage)
+    }
+}
+
+impl<T> Storage<T> {
+    pub fn insert(&mut self, entity: Entity, component: T) {
+        let index = entity.index as usize;
+        if self.slots.len() <= index {
+            self.slots.resize_with(index + 1, || None);
+        }
+        self.slots[index] = Some((entity, component));
+    }
+
+    pub fn remove(&mut self, entity: Entity) -> Option<T> {
+        let slot = self.slots.get_mut(entity.index as usize)?;
+        match slot {
+            Some((e, _)) if *e == entity => slot.take().map(|(_, c)| c),
+            _ => None,
+        }
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&T> {
+        match self.slots.get(entity.index as usize)? {
+            Some((e, c)) if *e == entity => Some(c),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<&mut T> {
+        match self.slots.get_mut(entity.index as usize)? {
+            Some((e, c)) if *e == entity => Some(c),
+            _ => None,
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, &T)> {
+        self.slots.iter().filter_map(|s| s.as_ref().map(|(e, c)| (*e, c)))
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, &mut T)> {
+        self.slots.iter_mut().filter_map(|s| s.as_mut().map(|(e, c)| (*e, c)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.iter().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.iter().next().is_none()
+    }
+}
+
+/// Where something is. World coordinates: (0, 0) is the middle of the screen, y is up.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Transform {
+    pub pos: Vec2,
+    /// Where it was at the start of the tick, for swept collisions.
+    pub prev_pos: Vec2,
+}
+
+impl Transform {
+    pub fn new(pos: Vec2) -> Transform {
+        Transform { pos, prev_pos: pos }
+    }
+}
+
+/// Moves by its velocity every tick and falls with `gravity * gravity_scale`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Body {
+    pub velocity: Vec2,
+    pub gravity_scale: f32,
+}
+
+/// Images the game knows how to draw. `Assets` turns these into real images,
+/// so nothing here needs a graphics context.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum SpriteId {
+    Cat,
+    Candy,
+    Sparkle,
+    Bat,
+    Ghost,
+    Pumpkin,
+}
+
+impl SpriteId {
+    /// Has to match the images loaded for it in `main`.
+    pub fn frames(self) -> usize {
+        match self {
+            SpriteId::Cat => 4,
+            SpriteId::Candy => 1,
+            SpriteId::Sparkle => 5,
+            SpriteId::Bat | SpriteId::Ghost | SpriteId::Pumpkin => 1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SpriteRef {
+    pub id: SpriteId,
+    pub frame: usize,
+    pub scale: Vec2,
+    /// Higher draws on top.
+    pub layer: i32,
+}
+
+impl SpriteRef {
+    pub fn new(id: SpriteId, layer: i32) -> SpriteRef {
+        SpriteRef { id, frame: 0, scale: vec2(1.0, 1.0), layer }
+    }
+}
+
+/// Steps the sprite through all its frames, once.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Animator {
+    pub time: f32,
+    pub fps: f32,
+}
+
+/// Touches things within `radius` of its middle.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Collider {
+    pub radius: f32,
+}
+
+/// Goes away after this many seconds.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Lifetime {
+    pub remaining: f32,
+}
+
+/// The cat can catch it for points, and it costs a life if it hits the bottom.
+/// Braces rather than a unit struct, which toml can't save.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Catchable {}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HazardKind {
+    /// Swoops across the screen and knocks candy out of the way.
+    Bat,
+    /// Drifts through everything towards candy and carries it off.
+    Ghost,
+    /// Falls like candy, but stuns the cat.
+    Pumpkin,
+}
+
+/// Hurts the cat on contact. What else it does is up to `hazards.rs`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Hazard {
+    pub kind: HazardKind,
+    /// Life lost when it touches the cat.
+    pub damage: i32,
+    /// Seconds since it spawned.
+    pub age: f32,
+    pub origin: Vec2,
+    /// Ghosts leave once they've got some candy.
+    pub carrying: bool,
+}
+
+/// Saves as it is, so a run can be put down and picked up again (see `save.rs`).
+#[derive(Default, Clone, Serialize, Deserialize)]
+pub struct World {
+    generations: Vec<u32>,
+    alive: Vec<bool>,
+    free: Vec<u32>,
+    pub transforms: Storage<Transform>,
+    pub bodies: Storage<Body>,
+    pub sprites: Storage<SpriteRef>,
+    pub animators: Storage<Animator>,
+    pub colliders: Storage<Collider>,
+    pub lifetimes: Storage<Lifetime>,
+    pub catchables: Storage<Catchable>,
+    pub hazards: Storage<Hazard>,
+    pub players: Storage<Player>,
+}
+
+impl World {
+    pub fn spawn(&mut self) -> Entity {
+        let index = match self.free.pop() {
+            Some(index) => index,
+            None => {
+                self.generations.push(0);
+                self.alive.push(false);
+                (self.generations.len() - 1) as u32
+            }
+        };
+        self.alive[index as usize] = true;
+        Entity { index, generation: self.generations[index as usize] }
+    }
+
+    pub fn despawn(&mut self, entity: Entity) {
+        if !self.is_alive(entity) {
+            return;
+        }
+        self.transforms.remove(entity);
+        self.bodies.remove(entity);
+        self.sprites.remove(entity);
+        self.animators.remove(entity);
+        self.colliders.remove(entity);
+        self.lifetimes.remove(entity);
+        self.catchables.remove(entity);
+        self.hazards.remove(entity);
+        self.players.remove(entity);
+
+        let index = entity.index as usize;
+        self.alive[index] = false;
+        self.generations[index] += 1;
+        self.free.push(entity.index);
+    }
+
+    pub fn is_alive(&self, entity: Entity) -> bool {
+        self.alive.get(entity.index as usize) == Some(&true)
+            && self.generations[entity.index as usize] == entity.generation
+    }
+
+    pub fn len(&self) -> usize {
+        self.alive.iter().filter(|&&a| a).count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        !self.alive.contains(&true)
+    }
+}
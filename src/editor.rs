@@ -0,0 +1,539 @@
+//! Level editor for `stages.toml`. F4 opens it over the game, which waits
+//! underneath, and closing it plays the stage it was on with the changes.
+//! Only built into debug builds.
+//!
+//! 1 to 6 pick a tool: ground, candy spawn zones, where the cat starts, and
+//! bats, ghosts and pumpkins. Left mouse paints or places, right erases or
+//! takes away. The rest is on screen. Decoration layers show and are saved
+//! along with everything else, but are edited in the file.
+
+use std::path::Path;
+
+use ggez::event::{KeyCode, KeyMods, MouseButton};
+use ggez::graphics::{self, Color, DrawMode, DrawParam, MeshBuilder, Rect};
+use ggez::{filesystem, Context, GameResult};
+use glam::*;
+
+use pogin::assets::Assets;
+use pogin::backdrop::Backdrop;
+use pogin::camera::Camera;
+use pogin::decor::Decor;
+use pogin::campaign::{self, Goal, PlacedHazard, SpawnZone, Stage, MAP_HEIGHT, MAP_WIDTH};
+use pogin::ecs::HazardKind;
+use pogin::locale::Locale;
+use pogin::rank::Rank;
+use pogin::tileset::TileSet;
+use pogin::trajectory::TILE_SIZE;
+
+use crate::{draw_map, world_to_screen_coords, FLOOR_Y, SCREEN_SIZE, SPAWN_HEIGHT};
+
+const GRID: Color = Color::new(1.0, 1.0, 1.0, 0.08);
+const CURSOR: Color = Color::new(1.0, 0.8, 0.2, 0.9);
+const ZONE: Color = Color::new(1.0, 0.9, 0.3, 0.18);
+const GUIDE: Color = Color::new(1.0, 1.0, 0.0, 0.6);
+/// How close a right click has to be to take a hazard away, in pixels.
+const PICK_RADIUS: f32 = 24.0;
+/// Undo steps kept.
+const HISTORY: usize = 100;
+/// Ranks a stage can ask for, `R` steps through them.
+const RANKS: [Rank; 7] = [Rank::F, Rank::D, Rank::C, Rank::B, Rank::A, Rank::S, Rank::SS];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Tool {
+    Ground,
+    Spawn,
+    Start,
+    Hazard(HazardKind),
+}
+
+const TOOLS: [(Tool, &str); 6] = [
+    (Tool::Ground, "Ground"),
+    (Tool::Spawn, "Spawn zone"),
+    (Tool::Start, "Cat start"),
+    (Tool::Hazard(HazardKind::Bat), "Bat"),
+    (Tool::Hazard(HazardKind::Ghost), "Ghost"),
+    (Tool::Hazard(HazardKind::Pumpkin), "Pumpkin"),
+];
+
+/// What a held mouse button is doing.
+#[derive(Debug, Clone, Copy)]
+enum Drag {
+    /// Painting ground, or rubbing it out. `changed` once a tile has, so a
+    /// whole stroke is one undo.
+    Paint { solid: bool, changed: bool },
+    /// A spawn zone from this world x to the mouse.
+    Zone(f32),
+}
+
+/// Every stage and which one was on screen, before an edit.
+type Snapshot = (Vec<Stage>, usize);
+
+/// Undo and redo, as whole copies of the stages. They're small.
+#[derive(Default)]
+struct History {
+    undo: Vec<Snapshot>,
+    redo: Vec<Snapshot>,
+}
+
+impl History {
+    /// Call before changing anything.
+    fn push(&mut self, before: Snapshot) {
+        self.undo.push(before);
+        if self.undo.len() > HISTORY {
+            self.undo.remove(0);
+        }
+        self.redo.clear();
+    }
+
+    fn undo(&mut self, now: Snapshot) -> Option<Snapshot> {
+        let before = self.undo.pop()?;
+        self.redo.push(now);
+        Some(before)
+    }
+
+    fn redo(&mut self, now: Snapshot) -> Option<Snapshot> {
+        let after = self.redo.pop()?;
+        self.undo.push(now);
+        Some(after)
+    }
+}
+
+/// Layout pixels (y down) to world coordinates (y up, 0 in the middle).
+fn to_world(p: Vec2) -> Vec2 {
+    vec2(p.x - SCREEN_SIZE.0 / 2.0, SCREEN_SIZE.1 / 2.0 - p.y)
+}
+
+/// Where a mouse event at window pixel `x`, `y` is in layout pixels.
+pub fn mouse_pos(ctx: &Context, x: f32, y: f32) -> Vec2 {
+    let (width, height) = graphics::drawable_size(ctx);
+    vec2(x * SCREEN_SIZE.0 / width, y * SCREEN_SIZE.1 / height)
+}
+
+/// Ground tiles in the resources folder, e.g. `/ground2.png`.
+fn ground_sheets(ctx: &mut Context) -> Vec<String> {
+    let mut sheets = filesystem::read_dir(ctx, "/").map(|files| {
+        files.filter_map(|path| {
+            let name = path.file_name()?.to_str()?.to_string();
+            (name.starts_with("ground") && name.ends_with(".png")).then(|| format!("/{}", name))
+        }).collect::<Vec<_>>()
+    }).unwrap_or_default();
+    sheets.sort();
+    sheets.dedup();
+    sheets
+}
+
+pub struct Editor {
+    stages: Vec<Stage>,
+    /// Index into `stages` of the one on screen.
+    selected: usize,
+    tool: Tool,
+    /// The selected stage's, to draw it with.
+    tileset: TileSet,
+    backdrop: Backdrop,
+    decor: Decor,
+    tilesets: Vec<String>,
+    /// In layout pixels.
+    mouse: Vec2,
+    drag: Option<Drag>,
+    history: History,
+    /// The name before renaming started, while typing a new one.
+    renaming: Option<String>,
+    /// Changed since it was last saved or loaded.
+    unsaved: bool,
+    /// Last thing that happened, e.g. "Saved".
+    status: String,
+}
+
+impl Editor {
+    pub fn new(ctx: &mut Context, stages: Vec<Stage>, selected: usize) -> GameResult<Editor> {
+        let stage = &stages[selected];
+        Ok(Editor {
+            tileset: TileSet::autotiled(ctx, &stage.tileset)?,
+            backdrop: stage.load_backdrop(ctx)?,
+            decor: stage.load_decor(ctx)?,
+            tilesets: ground_sheets(ctx),
+            stages,
+            selected,
+            tool: Tool::Ground,
+            mouse: Vec2::ZERO,
+            drag: None,
+            history: History::default(),
+            renaming: None,
+            unsaved: false,
+            status: String::new(),
+        })
+    }
+
+    /// The stages as edited, and the one that was on screen.
+    pub fn finish(self) -> (Vec<Stage>, usize) {
+        (self.stages, self.selected)
+    }
+
+    fn stage(&self) -> &Stage {
+        &self.stages[self.selected]
+    }
+
+    fn snapshot(&self) -> Snapshot {
+        (self.stages.clone(), self.selected)
+    }
+
+    /// Remembers how things were for undo, then hands over the stage to change.
+    fn edit(&mut self) -> &mut Stage {
+        self.history.push(self.snapshot());
+        self.unsaved = true;
+        self.status.clear();
+        &mut self.stages[self.selected]
+    }
+
+    /// Tileset, backdrop and decoration, after changing stage or its tileset.
+    fn reload_looks(&mut self, ctx: &mut Context) {
+        let stage = &self.stages[self.selected];
+        match (TileSet::autotiled(ctx, &stage.tileset), stage.load_backdrop(ctx), stage.load_decor(ctx)) {
+            (Ok(tileset), Ok(backdrop), Ok(decor)) => {
+                self.tileset = tileset;
+                self.backdrop = backdrop;
+                self.decor = decor;
+            }
+            (Err(e), _, _) | (_, Err(e), _) | (_, _, Err(e)) => self.status = format!("Couldn't load its looks: {}", e),
+        }
+    }
+
+    fn restore(&mut self, ctx: &mut Context, (stages, selected): Snapshot) {
+        self.stages = stages;
+        self.selected = selected.min(self.stages.len() - 1);
+        self.unsaved = true;
+        self.reload_looks(ctx);
+    }
+
+    /// Column and row (top first) of the tile under the mouse.
+    fn hovered_tile(&self) -> Option<(usize, usize)> {
+        let (column, row) = ((self.mouse.x / TILE_SIZE).floor(), (self.mouse.y / TILE_SIZE).floor());
+        let inside = (0.0..MAP_WIDTH as f32).contains(&column) && (0.0..MAP_HEIGHT as f32).contains(&row);
+        inside.then_some((column as usize, row as usize))
+    }
+
+    fn paint(&mut self) {
+        let (Some(Drag::Paint { solid, changed }), Some((column, row))) = (self.drag, self.hovered_tile()) else { return };
+        if self.stage().tile(column, row) == solid {
+            return;
+        }
+        if changed {
+            self.stages[self.selected].set_tile(column, row, solid);
+        } else {
+            self.edit().set_tile(column, row, solid);
+            self.drag = Some(Drag::Paint { solid, changed: true });
+        }
+    }
+
+    /// Where the mouse is, in layout pixels.
+    pub fn mouse_motion(&mut self, pos: Vec2) {
+        self.mouse = pos;
+        self.paint();
+    }
+
+    pub fn mouse_down(&mut self, button: MouseButton) {
+        if self.renaming.is_some() || self.drag.is_some() {
+            return;
+        }
+        let (place, at) = (button == MouseButton::Left, to_world(self.mouse));
+        if button != MouseButton::Left && button != MouseButton::Right {
+            return;
+        }
+        match self.tool {
+            Tool::Ground => {
+                self.drag = Some(Drag::Paint { solid: place, changed: false });
+                self.paint();
+            }
+            Tool::Spawn if place => self.drag = Some(Drag::Zone(at.x)),
+            Tool::Spawn => {
+                if let Some(i) = self.stage().spawns.iter().position(|z| (z.left.min(z.right)..=z.left.max(z.right)).contains(&at.x)) {
+                    self.edit().spawns.remove(i);
+                }
+            }
+            // The cat stands on the floor, it can start above it but not under.
+            Tool::Start if place => self.edit().start = Some(vec2(at.x, at.y.max(FLOOR_Y))),
+            Tool::Start => {
+                if self.stage().start.is_some() {
+                    self.edit().start = None;
+                }
+            }
+            Tool::Hazard(kind) if place => self.edit().hazards.push(PlacedHazard { kind, at }),
+            Tool::Hazard(_) => {
+                let nearest = self.stage().hazards.iter().enumerate()
+                    .map(|(i, h)| (i, h.at.distance(at)))
+                    .filter(|&(_, d)| d < PICK_RADIUS)
+                    .min_by(|a, b| a.1.total_cmp(&b.1));
+                if let Some((i, _)) = nearest {
+                    self.edit().hazards.remove(i);
+                }
+            }
+        }
+    }
+
+    pub fn mouse_up(&mut self, _button: MouseButton) {
+        if let Some(Drag::Zone(from)) = self.drag {
+            let to = to_world(self.mouse).x.clamp(-SCREEN_SIZE.0 / 2.0, SCREEN_SIZE.0 / 2.0);
+            // A click without a drag is more likely a slip than a zone.
+            if (to - from).abs() >= TILE_SIZE / 4.0 {
+                self.edit().spawns.push(SpawnZone { left: from.min(to), right: from.max(to) });
+            }
+        }
+        self.drag = None;
+    }
+
+    pub fn text_input(&mut self, c: char) {
+        if self.renaming.is_some() && !c.is_control() {
+            self.stages[self.selected].name.push(c);
+        }
+    }
+
+    /// Returns true when it's time to close.
+    pub fn key_down(&mut self, ctx: &mut Context, keycode: KeyCode, mods: KeyMods, resource_dir: &Path) -> bool {
+        if let Some(old) = &self.renaming {
+            match keycode {
+                KeyCode::Back => {
+                    self.stages[self.selected].name.pop();
+                }
+                KeyCode::Return | KeyCode::NumpadEnter => {
+                    // Names are what progress, saves and replays find stages by.
+                    let name = &self.stages[self.selected].name;
+                    if name.trim().is_empty() {
+                        self.status = "A stage needs a name".to_string();
+                    } else if self.stages.iter().enumerate().any(|(i, s)| i != self.selected && s.name == *name) {
+                        self.status = format!("There's already a stage called {:?}", name);
+                    } else {
+                        self.status.clear();
+                        self.renaming = None;
+                    }
+                }
+                KeyCode::Escape => {
+                    self.stages[self.selected].name = old.clone();
+                    self.renaming = None;
+                    self.status.clear();
+                    self.history.undo.pop();
+                }
+                _ => (),
+            }
+            return false;
+        }
+
+        let ctrl = mods.contains(KeyMods::CTRL);
+        let shift = mods.contains(KeyMods::SHIFT);
+        match keycode {
+            KeyCode::F4 | KeyCode::Escape => return true,
+            KeyCode::Z if ctrl && shift => self.redo(ctx),
+            KeyCode::Z if ctrl => self.undo(ctx),
+            KeyCode::Y if ctrl => self.redo(ctx),
+            KeyCode::S if ctrl => {
+                self.status = match campaign::save_stages(resource_dir, &self.stages) {
+                    Ok(()) => {
+                        self.unsaved = false;
+                        format!("Saved to {}", campaign::STAGES_FILE)
+                    }
+                    Err(e) => format!("Couldn't save: {}", e),
+                };
+            }
+            KeyCode::L if ctrl => match campaign::load_stages(resource_dir) {
+                Ok(stages) => {
+                    self.history.push(self.snapshot());
+                    self.restore(ctx, (stages, self.selected));
+                    self.unsaved = false;
+                    self.status = format!("Loaded {}", campaign::STAGES_FILE);
+                }
+                Err(e) => self.status = format!("Couldn't load: {}", e),
+            },
+            KeyCode::N if ctrl => {
+                let mut stage = self.stage().clone();
+                stage.name = (self.stages.len() + 1..)
+                    .map(|n| format!("Stage {}", n))
+                    .find(|name| self.stages.iter().all(|s| s.name != *name))
+                    .unwrap_or_default();
+                stage.map = (0..MAP_HEIGHT)
+                    .map(|row| (if row + 2 >= MAP_HEIGHT { "#" } else { "." }).repeat(MAP_WIDTH))
+                    .collect();
+                stage.start = None;
+                stage.spawns.clear();
+                stage.hazards.clear();
+                stage.decor.clear();
+                self.history.push(self.snapshot());
+                self.stages.insert(self.selected + 1, stage);
+                self.selected += 1;
+                self.unsaved = true;
+                self.reload_looks(ctx);
+            }
+            KeyCode::PageUp | KeyCode::PageDown => {
+                let count = self.stages.len();
+                self.selected = if keycode == KeyCode::PageUp { (self.selected + count - 1) % count } else { (self.selected + 1) % count };
+                self.reload_looks(ctx);
+            }
+            KeyCode::Key1 | KeyCode::Key2 | KeyCode::Key3 | KeyCode::Key4 | KeyCode::Key5 | KeyCode::Key6 => {
+                self.tool = TOOLS[keycode as usize - KeyCode::Key1 as usize].0;
+            }
+            KeyCode::Return => {
+                self.renaming = Some(self.stage().name.clone());
+                self.status.clear();
+                self.edit();
+            }
+            KeyCode::G => {
+                let stage = self.edit();
+                stage.goal = match stage.goal {
+                    Goal::Candy(_) => Goal::Time(60.0),
+                    Goal::Time(_) => Goal::Candy(20),
+                };
+            }
+            KeyCode::Minus | KeyCode::Equals => {
+                let dir = if keycode == KeyCode::Minus { -1.0 } else { 1.0 };
+                let stage = self.edit();
+                stage.goal = match stage.goal {
+                    Goal::Candy(n) => Goal::Candy((n as i64 + dir as i64).max(1) as u32),
+                    Goal::Time(t) => Goal::Time((t + dir * 5.0).max(5.0)),
+                };
+            }
+            KeyCode::R => {
+                let stage = self.edit();
+                let at = RANKS.iter().position(|&r| r == stage.rank).unwrap_or(0);
+                let count = RANKS.len();
+                stage.rank = RANKS[if shift { (at + count - 1) % count } else { (at + 1) % count }];
+            }
+            KeyCode::T if !self.tilesets.is_empty() => {
+                let at = self.tilesets.iter().position(|t| *t == self.stage().tileset).map_or(0, |i| i + 1);
+                let next = self.tilesets[at % self.tilesets.len()].clone();
+                self.edit().tileset = next;
+                self.reload_looks(ctx);
+            }
+            _ => (),
+        }
+        false
+    }
+
+    fn undo(&mut self, ctx: &mut Context) {
+        match self.history.undo(self.snapshot()) {
+            Some(before) => self.restore(ctx, before),
+            None => self.status = "Nothing to undo".to_string(),
+        }
+    }
+
+    fn redo(&mut self, ctx: &mut Context) {
+        match self.history.redo(self.snapshot()) {
+            Some(after) => self.restore(ctx, after),
+            None => self.status = "Nothing to redo".to_string(),
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut Context, assets: &Assets, locale: &Locale) -> GameResult {
+        let (w, h) = SCREEN_SIZE;
+        let to_screen = |p: Vec2| world_to_screen_coords(w, h, p);
+        let stage = self.stage();
+        self.backdrop.draw(ctx, &Camera::default())?;
+
+        // What a left click would do, so the autotiling around it shows.
+        let mut preview = stage.clone();
+        if let (Tool::Ground, None, Some((column, row))) = (self.tool, self.drag, self.hovered_tile()) {
+            preview.set_tile(column, row, true);
+        }
+        draw_map(ctx, &preview.layout(), &self.tileset)?;
+        // Both under the grid and markers, so nothing's hidden.
+        self.decor.draw(ctx, false)?;
+        self.decor.draw(ctx, true)?;
+
+        let mut mb = MeshBuilder::new();
+        for x in 1..MAP_WIDTH {
+            mb.line(&[vec2(x as f32 * TILE_SIZE, 0.0), vec2(x as f32 * TILE_SIZE, h)], 1.0, GRID)?;
+        }
+        for y in 1..MAP_HEIGHT {
+            mb.line(&[vec2(0.0, y as f32 * TILE_SIZE), vec2(w, y as f32 * TILE_SIZE)], 1.0, GRID)?;
+        }
+        if let (Tool::Ground, Some((column, row))) = (self.tool, self.hovered_tile()) {
+            let tile = Rect::new(column as f32 * TILE_SIZE, row as f32 * TILE_SIZE, TILE_SIZE, TILE_SIZE);
+            mb.rectangle(DrawMode::stroke(2.0), tile, CURSOR)?;
+        }
+
+        let floor = to_screen(vec2(0.0, FLOOR_Y)).y;
+        mb.line(&[vec2(0.0, floor), vec2(w, floor)], 1.0, GUIDE)?;
+        let spawn_y = to_screen(vec2(0.0, SPAWN_HEIGHT)).y;
+        let mut zones = stage.spawn_zones();
+        if let Some(Drag::Zone(from)) = self.drag {
+            let to = to_world(self.mouse).x;
+            zones.push(SpawnZone { left: from.min(to), right: from.max(to) });
+        }
+        for zone in zones {
+            let (left, right) = (to_screen(vec2(zone.left, 0.0)).x, to_screen(vec2(zone.right, 0.0)).x);
+            mb.rectangle(DrawMode::fill(), Rect::new(left, 0.0, right - left, spawn_y), ZONE)?;
+            mb.line(&[vec2(left, spawn_y), vec2(right, spawn_y)], 1.0, GUIDE)?;
+        }
+        let mesh = mb.build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::new())?;
+
+        let cat = DrawParam::new().dest(to_screen(stage.start())).offset(vec2(0.5, 0.5));
+        assets.cat_sheet.draw_frame(ctx, 0, cat)?;
+        for hazard in &stage.hazards {
+            let mesh = match hazard.kind {
+                HazardKind::Bat => &assets.bat,
+                HazardKind::Ghost => &assets.ghost,
+                HazardKind::Pumpkin => &assets.pumpkin,
+            };
+            graphics::draw(ctx, mesh, (to_screen(hazard.at),))?;
+        }
+
+        self.draw_panel(ctx, locale)
+    }
+
+    fn draw_panel(&self, ctx: &mut Context, locale: &Locale) -> GameResult {
+        let stage = self.stage();
+        let goal = match stage.goal {
+            Goal::Candy(n) => format!("{} candy", n),
+            Goal::Time(t) => format!("{} seconds", t),
+        };
+        let name = if self.renaming.is_some() { format!("{}_", stage.name) } else { stage.name.clone() };
+        let tools = TOOLS.iter().enumerate()
+            .map(|(i, (tool, label))| if *tool == self.tool { format!("[{} {}]", i + 1, label) } else { format!(" {} {} ", i + 1, label) })
+            .collect::<String>();
+        let hint = if !self.status.is_empty() {
+            &self.status
+        } else if self.renaming.is_some() {
+            "Typing a name: Enter done, Esc cancel"
+        } else {
+            "Ctrl+Z/Y undo/redo  Ctrl+S save  Ctrl+L reload  Ctrl+N new stage  F4 play it"
+        };
+        let lines = [
+            format!("Stage {}/{}: {}{}  (PgUp/PgDn, Enter renames)",
+                self.selected + 1, self.stages.len(), name, if self.unsaved { " *" } else { "" }),
+            format!("Goal: {} (G, - =)  Rank: {} (R)  Ground: {} (T)", goal, stage.rank, stage.tileset),
+            tools,
+            hint.to_string(),
+        ];
+
+        let backing = graphics::Mesh::new_rectangle(
+            ctx, DrawMode::fill(), Rect::new(0.0, 0.0, SCREEN_SIZE.0, lines.len() as f32 * 14.0 + 8.0),
+            Color::new(0.0, 0.0, 0.0, 0.7))?;
+        graphics::draw(ctx, &backing, DrawParam::new())?;
+        for (i, line) in lines.iter().enumerate() {
+            let text = locale.text(line, 12.0);
+            graphics::draw(ctx, &text, (vec2(6.0, 4.0 + i as f32 * 14.0), 0.0, Color::WHITE))?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn snapshot(n: usize) -> Snapshot {
+        (Vec::new(), n)
+    }
+
+    #[test]
+    fn undo_and_redo_walk_the_history() {
+        let mut history = History::default();
+        history.push(snapshot(0));
+        history.push(snapshot(1));
+        assert_eq!(history.undo(snapshot(2)).map(|s| s.1), Some(1));
+        assert_eq!(history.undo(snapshot(1)).map(|s| s.1), Some(0));
+        assert!(history.undo(snapshot(0)).is_none());
+        assert_eq!(history.redo(snapshot(0)).map(|s| s.1), Some(1));
+        // A new edit drops what could have been redone.
+        history.push(snapshot(1));
+        assert!(history.redo(snapshot(3)).is_none());
+    }
+}
@@ -0,0 +1,233 @@
+//! Bats, ghosts and pumpkins: what they do each tick and what happens when
+//! they touch something. Spawning them is in `systems.rs` like everything else.
+
+use std::f32::consts::PI;
+
+use glam::*;
+use oorandom::Rand32;
+
+use crate::broadphase::Grid;
+use crate::ecs::{Entity, HazardKind, World};
+use crate::tuning::Tuning;
+use crate::{systems, FLOOR_Y, SCREEN_SIZE, SPAWN_HEIGHT, SPAWN_SPREAD};
+
+/// Hazards start and finish this far past the edge of the screen.
+const MARGIN: f32 = 24.0;
+
+/// Now and then adds a random hazard, once difficulty is past the grace period.
+pub fn maybe_spawn(world: &mut World, rng: &mut Rand32, tuning: &Tuning, difficulty: f32, dt: f32) {
+    if difficulty < tuning.hazard_grace || rng.rand_float() >= tuning.hazard_rate * dt {
+        return;
+    }
+    let (w, h) = SCREEN_SIZE;
+    let side = if rng.rand_float() < 0.5 { -1.0 } else { 1.0 };
+    let x = (rng.rand_float() - 0.5) * 2.0 * w * SPAWN_SPREAD;
+    match rng.rand_range(0..3) {
+        0 => systems::spawn_bat(world, vec2(side * (w / 2.0 + MARGIN), SPAWN_HEIGHT), tuning),
+        1 => systems::spawn_ghost(world, vec2(x, h / 2.0 + MARGIN), tuning),
+        _ => systems::spawn_pumpkin(world, vec2(x, SPAWN_HEIGHT), vec2(0.0, rng.rand_float() * 40.0), tuning),
+    };
+}
+
+/// Steers every hazard, and removes the ones that are done. Call before
+/// `systems::integrate`.
+pub fn think(world: &mut World, player_pos: Vec2, tuning: &Tuning, dt: f32) {
+    let (w, h) = SCREEN_SIZE;
+    let candy = world.catchables.iter()
+        .filter_map(|(e, _)| world.transforms.get(e).map(|t| t.pos))
+        .collect::<Vec<_>>();
+
+    let mut gone = Vec::new();
+    for (e, hazard) in world.hazards.iter_mut() {
+        hazard.age += dt;
+        let (Some(transform), Some(body)) = (world.transforms.get(e), world.bodies.get_mut(e)) else { continue };
+        let pos = transform.pos;
+        match hazard.kind {
+            HazardKind::Bat => {
+                // Half a sine wave across the screen, dipping to just above the floor.
+                let dir = -hazard.origin.x.signum();
+                let span = w + MARGIN * 2.0;
+                let progress = (pos.x - hazard.origin.x).abs() / span;
+                let depth = hazard.origin.y - (FLOOR_Y + 8.0);
+                let dip = depth * PI / span * tuning.bat_speed * (PI * progress).cos();
+                body.velocity = vec2(dir * tuning.bat_speed, -dip);
+                if progress >= 1.0 {
+                    gone.push(e);
+                }
+            }
+            HazardKind::Ghost => {
+                if hazard.carrying {
+                    body.velocity = vec2(0.0, tuning.ghost_speed * 3.0);
+                    if pos.y > h / 2.0 + MARGIN {
+                        gone.push(e);
+                    }
+                } else {
+                    // Goes for the nearest candy, or the cat if there isn't any.
+                    let target = candy.iter().copied()
+                        .min_by(|a, b| a.distance_squared(pos).total_cmp(&b.distance_squared(pos)))
+                        .unwrap_or(player_pos);
+                    body.velocity = (target - pos).normalize_or_zero() * tuning.ghost_speed;
+                    if hazard.age > tuning.ghost_time {
+                        gone.push(e);
+                    }
+                }
+            }
+            HazardKind::Pumpkin => {
+                // Smashes on the ground.
+                if pos.y < FLOOR_Y - 16.0 {
+                    gone.push(e);
+                }
+            }
+        }
+    }
+    for e in gone {
+        world.despawn(e);
+    }
+}
+
+/// What hazards did to the cat this tick.
+#[derive(Debug, Default)]
+pub struct Contacts {
+    pub hurt: bool,
+    /// Life lost to hits.
+    pub damage: i32,
+    /// Candy taken by ghosts.
+    pub stolen: usize,
+}
+
+/// Hurts the cat and messes with candy for everything touching a hazard.
+/// Call after `systems::integrate`, with a grid built after it.
+pub fn contact(world: &mut World, grid: &Grid, player: Entity, tuning: &Tuning) -> Contacts {
+    let mut contacts = Contacts::default();
+    let hazards = world.hazards.iter().map(|(e, h)| (e, *h)).collect::<Vec<_>>();
+    for (e, hazard) in hazards {
+        for (other, _) in grid.touching(world, e) {
+            if !world.is_alive(e) {
+                break;
+            }
+            if other == player {
+                let stun = if hazard.kind == HazardKind::Pumpkin { tuning.pumpkin_stun_time } else { 0.0 };
+                let Some(cat) = world.players.get_mut(player) else { continue };
+                if cat.take_hit(hazard.damage, stun, tuning) {
+                    contacts.hurt = true;
+                    contacts.damage += hazard.damage;
+                    // Bats keep going, the others are used up.
+                    if hazard.kind != HazardKind::Bat {
+                        world.despawn(e);
+                    }
+                }
+            } else if world.catchables.get(other).is_some() {
+                match hazard.kind {
+                    HazardKind::Bat => {
+                        let dir = -hazard.origin.x.signum();
+                        if let Some(body) = world.bodies.get_mut(other) {
+                            body.velocity = vec2(dir, 0.5) * tuning.bat_knock;
+                        }
+                    }
+                    HazardKind::Ghost => {
+                        let Some(ghost) = world.hazards.get_mut(e) else { continue };
+                        if !ghost.carrying {
+                            ghost.carrying = true;
+                            contacts.stolen += 1;
+                            world.despawn(other);
+                        }
+                    }
+                    HazardKind::Pumpkin => (),
+                }
+            }
+        }
+    }
+    contacts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The cat with a hazard of `kind` right on top of it.
+    fn cat_and(kind: HazardKind, tuning: &Tuning) -> (World, Entity, Entity) {
+        let mut world = World::default();
+        let cat = systems::spawn_player(&mut world, Vec2::ZERO, 5);
+        let hazard = match kind {
+            HazardKind::Bat => systems::spawn_bat(&mut world, Vec2::ZERO, tuning),
+            HazardKind::Ghost => systems::spawn_ghost(&mut world, Vec2::ZERO, tuning),
+            HazardKind::Pumpkin => systems::spawn_pumpkin(&mut world, Vec2::ZERO, Vec2::ZERO, tuning),
+        };
+        (world, cat, hazard)
+    }
+
+    fn touch(world: &mut World, cat: Entity, tuning: &Tuning) -> Contacts {
+        let grid = Grid::build(world);
+        contact(world, &grid, cat, tuning)
+    }
+
+    #[test]
+    fn only_bats_survive_hitting_the_cat() {
+        let tuning = Tuning::default();
+        let kinds = [
+            (HazardKind::Bat, tuning.bat_damage),
+            (HazardKind::Ghost, tuning.ghost_damage),
+            (HazardKind::Pumpkin, tuning.pumpkin_damage),
+        ];
+        for (kind, damage) in kinds {
+            let (mut world, cat, hazard) = cat_and(kind, &tuning);
+            let contacts = touch(&mut world, cat, &tuning);
+            assert!(contacts.hurt);
+            assert_eq!(contacts.damage, damage);
+            assert_eq!(world.players.get(cat).unwrap().life, 5 - damage);
+            assert_eq!(world.is_alive(hazard), kind == HazardKind::Bat, "{:?}", kind);
+        }
+    }
+
+    #[test]
+    fn no_hits_while_still_hurt() {
+        let tuning = Tuning::default();
+        let (mut world, cat, _) = cat_and(HazardKind::Pumpkin, &tuning);
+        touch(&mut world, cat, &tuning);
+        let ghost = systems::spawn_ghost(&mut world, Vec2::ZERO, &tuning);
+        let contacts = touch(&mut world, cat, &tuning);
+        assert!(!contacts.hurt);
+        assert_eq!(contacts.damage, 0);
+        assert_eq!(world.players.get(cat).unwrap().life, 5 - tuning.pumpkin_damage);
+        assert!(world.is_alive(ghost), "a ghost that didn't hurt isn't used up");
+
+        let player = world.players.get_mut(cat).unwrap();
+        player.hurt = 0.0;
+        assert!(player.take_hit(1, 0.0, &tuning));
+        assert!(!player.take_hit(1, 0.0, &tuning));
+        assert_eq!(player.life, 5 - tuning.pumpkin_damage - 1);
+    }
+
+    #[test]
+    fn a_ghost_steals_one_candy() {
+        let tuning = Tuning::default();
+        let mut world = World::default();
+        let cat = systems::spawn_player(&mut world, vec2(200.0, 0.0), 5);
+        let ghost = systems::spawn_ghost(&mut world, Vec2::ZERO, &tuning);
+        let candy = [
+            systems::spawn_candy(&mut world, Vec2::ZERO, Vec2::ZERO),
+            systems::spawn_candy(&mut world, vec2(4.0, 0.0), Vec2::ZERO),
+        ];
+        let contacts = touch(&mut world, cat, &tuning);
+        assert_eq!(contacts.stolen, 1);
+        assert!(!contacts.hurt);
+        assert!(world.hazards.get(ghost).unwrap().carrying);
+        assert_eq!(candy.iter().filter(|&&c| world.is_alive(c)).count(), 1);
+    }
+
+    #[test]
+    fn a_ghost_on_the_cat_and_a_candy_does_the_same_every_time() {
+        let tuning = Tuning::default();
+        // Each grid hashes differently, so a few goes would catch hash order leaking through.
+        for _ in 0..20 {
+            let (mut world, cat, ghost) = cat_and(HazardKind::Ghost, &tuning);
+            let candy = systems::spawn_candy(&mut world, vec2(4.0, 0.0), Vec2::ZERO);
+            let contacts = touch(&mut world, cat, &tuning);
+            // The cat comes first, so the ghost is used up on it before it gets to the candy.
+            assert!(contacts.hurt);
+            assert_eq!(contacts.stolen, 0);
+            assert!(!world.is_alive(ghost));
+            assert!(world.is_alive(candy));
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! The in-game HUD, laid out from a toml file in resources (`hud.toml` unless
+//! the stage picks another), so it can be moved around and reskinned without
+//! touching code.
+
+use std::collections::HashMap;
+use std::io::Read;
+
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Image, Rect};
+use ggez::{Context, GameError, GameResult};
+use glam::*;
+use serde::Deserialize;
+
+use crate::locale::Locale;
+use crate::rank::Rank;
+use crate::settings::Accessibility;
+use crate::SCREEN_SIZE;
+
+pub const DEFAULT_HUD: &str = "/hud.toml";
+
+/// Counters close this fraction of the gap to the real value per second.
+const TICK_RATE: f32 = 8.0;
+
+/// Point on the screen a widget's `pos` is measured from. Widgets anchored
+/// right or bottom grow back towards the middle.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Anchor {
+    #[default]
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl Anchor {
+    /// Where on the screen, and how much of the widget's size to pull back by.
+    fn place(self, pos: Vec2, size: Vec2) -> Vec2 {
+        let (w, h) = SCREEN_SIZE;
+        let (point, pull) = match self {
+            Anchor::TopLeft => (vec2(0.0, 0.0), vec2(0.0, 0.0)),
+            Anchor::Top => (vec2(w / 2.0, 0.0), vec2(0.5, 0.0)),
+            Anchor::TopRight => (vec2(w, 0.0), vec2(1.0, 0.0)),
+            Anchor::Left => (vec2(0.0, h / 2.0), vec2(0.0, 0.5)),
+            Anchor::Center => (vec2(w / 2.0, h / 2.0), vec2(0.5, 0.5)),
+            Anchor::Right => (vec2(w, h / 2.0), vec2(1.0, 0.5)),
+            Anchor::BottomLeft => (vec2(0.0, h), vec2(0.0, 1.0)),
+            Anchor::Bottom => (vec2(w / 2.0, h), vec2(0.5, 1.0)),
+            Anchor::BottomRight => (vec2(w, h), vec2(1.0, 1.0)),
+        };
+        point + pos - size * pull
+    }
+}
+
+/// Numbers the game hands the HUD every frame.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stat {
+    Score,
+    Life,
+    MaxLife,
+    Combo,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct HudValues {
+    pub rank: Rank,
+    pub score: u32,
+    pub life: i32,
+    pub max_life: i32,
+    pub combo: u32,
+    /// How the stage goal is going, e.g. "Candy: 3/20".
+    pub goal: String,
+    pub caught: u32,
+    /// Whole seconds left, for modes with a time limit.
+    pub time_left: u32,
+    /// How far the game has sped up, from 1.
+    pub level: u32,
+}
+
+impl HudValues {
+    fn stat(&self, stat: Stat) -> f32 {
+        match stat {
+            Stat::Score => self.score as f32,
+            Stat::Life => self.life as f32,
+            Stat::MaxLife => self.max_life as f32,
+            Stat::Combo => self.combo as f32,
+        }
+    }
+
+    /// Fills in `{rank}`, `{score}`, `{life}`, `{combo}`, `{goal}`, `{caught}`,
+    /// `{time_left}` and `{level}`.
+    fn fill(&self, template: &str) -> String {
+        template
+            .replace("{rank}", self.rank.as_str())
+            .replace("{score}", &self.score.to_string())
+            .replace("{life}", &self.life.to_string())
+            .replace("{combo}", &self.combo.to_string())
+            .replace("{goal}", &self.goal)
+            .replace("{caught}", &self.caught.to_string())
+            .replace("{time_left}", &self.time_left.to_string())
+            .replace("{level}", &self.level.to_string())
+    }
+}
+
+fn default_size() -> f32 {
+    32.0
+}
+
+fn default_badge_radius() -> f32 {
+    16.0
+}
+
+fn white() -> [f32; 4] {
+    [1.0, 1.0, 1.0, 1.0]
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Widget {
+    /// Text, or a key in the language files, with `{stat}` bits filled in.
+    Label {
+        text: String,
+        #[serde(default = "default_size")]
+        size: f32,
+    },
+    /// A number that counts up to its value instead of jumping.
+    Counter {
+        /// Text or a language key, like `text` on labels.
+        #[serde(default)]
+        prefix: String,
+        value: Stat,
+        #[serde(default = "default_size")]
+        size: f32,
+    },
+    /// One `empty` image per point of `max`, with `fill` over the ones up to `value`.
+    Bar {
+        value: Stat,
+        max: Stat,
+        fill: String,
+        empty: String,
+        spacing: f32,
+    },
+    /// `icon` repeated `value` times, up to `max`.
+    Icons {
+        value: Stat,
+        max: u32,
+        icon: String,
+        spacing: f32,
+    },
+    /// The rank's badge, `radius` pixels round.
+    Rank {
+        #[serde(default = "default_badge_radius")]
+        radius: f32,
+    },
+    /// A box behind other widgets.
+    Panel {
+        size: [f32; 2],
+        /// Only drawn with the high contrast setting on.
+        #[serde(default)]
+        high_contrast_only: bool,
+    },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Placed {
+    #[serde(flatten)]
+    pub widget: Widget,
+    #[serde(default)]
+    pub anchor: Anchor,
+    #[serde(default)]
+    pub pos: [f32; 2],
+    #[serde(default = "white")]
+    pub color: [f32; 4],
+}
+
+#[derive(Deserialize)]
+struct HudFile {
+    widget: Vec<Placed>,
+}
+
+pub struct Hud {
+    widgets: Vec<Placed>,
+    images: HashMap<String, Image>,
+    /// What each counter is showing right now, by widget index.
+    shown: Vec<f32>,
+}
+
+impl Hud {
+    /// `path` is in the resources folder, like images.
+    pub fn load(ctx: &mut Context, path: &str) -> GameResult<Hud> {
+        let mut text = String::new();
+        ggez::filesystem::open(ctx, path)?.read_to_string(&mut text)?;
+        let file: HudFile = toml::from_str(&text)
+            .map_err(|e| GameError::ConfigError(format!("{}: {}", path, e)))?;
+
+        let mut images = HashMap::new();
+        for placed in &file.widget {
+            let paths = match &placed.widget {
+                Widget::Bar { fill, empty, .. } => vec![fill, empty],
+                Widget::Icons { icon, .. } => vec![icon],
+                _ => vec![],
+            };
+            for path in paths {
+                if !images.contains_key(path) {
+                    images.insert(path.clone(), Image::new(ctx, path)?);
+                }
+            }
+        }
+        let shown = vec![0.0; file.widget.len()];
+        Ok(Hud { widgets: file.widget, images, shown })
+    }
+
+    /// Moves counters along towards their values.
+    pub fn update(&mut self, values: &HudValues, dt: f32) {
+        for (placed, shown) in self.widgets.iter().zip(&mut self.shown) {
+            if let Widget::Counter { value, .. } = placed.widget {
+                let target = values.stat(value);
+                *shown += (target - *shown) * (1.0 - (-TICK_RATE * dt).exp());
+                if (target - *shown).abs() < 0.5 || target < *shown {
+                    *shown = target;
+                }
+            }
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut Context, locale: &Locale, values: &HudValues, accessibility: &Accessibility) -> GameResult {
+        let text_scale = if accessibility.large_text { 1.25 } else { 1.0 };
+        for (placed, &shown) in self.widgets.iter().zip(&self.shown) {
+            let pos = Vec2::from(placed.pos);
+            let [r, g, b, a] = placed.color;
+            let color = Color::new(r, g, b, a);
+            match &placed.widget {
+                Widget::Label { text, size } => {
+                    let text = locale.text(values.fill(locale.t(text)), size * text_scale);
+                    let at = placed.anchor.place(pos, vec2(text.width(ctx), text.height(ctx)));
+                    graphics::draw(ctx, &text, (at, 0.0, color))?;
+                }
+                Widget::Counter { prefix, size, .. } => {
+                    let text = format!("{}{}", locale.t(prefix), shown.round() as i64);
+                    let text = locale.text(text, size * text_scale);
+                    let at = placed.anchor.place(pos, vec2(text.width(ctx), text.height(ctx)));
+                    graphics::draw(ctx, &text, (at, 0.0, color))?;
+                }
+                Widget::Bar { value, max, fill, empty, spacing } => {
+                    let (value, max) = (values.stat(*value) as i32, values.stat(*max) as i32);
+                    let (fill, empty) = (&self.images[fill], &self.images[empty]);
+                    let size = vec2(max as f32 * spacing, empty.height() as f32);
+                    let at = placed.anchor.place(pos, size);
+                    for i in 0..max {
+                        let params = DrawParam::new().dest(at + vec2(i as f32 * spacing, 0.0)).color(color);
+                        graphics::draw(ctx, empty, params)?;
+                        if i < value {
+                            graphics::draw(ctx, fill, params)?;
+                        }
+                    }
+                }
+                Widget::Icons { value, max, icon, spacing } => {
+                    let count = (values.stat(*value) as u32).min(*max);
+                    let icon = &self.images[icon];
+                    let size = vec2(count as f32 * spacing, icon.height() as f32);
+                    let at = placed.anchor.place(pos, size);
+                    for i in 0..count {
+                        let params = DrawParam::new().dest(at + vec2(i as f32 * spacing, 0.0)).color(color);
+                        graphics::draw(ctx, icon, params)?;
+                    }
+                }
+                Widget::Rank { radius } => {
+                    let at = placed.anchor.place(pos, Vec2::splat(radius * 2.0));
+                    values.rank.draw_badge(ctx, locale, at + Vec2::splat(*radius), *radius)?;
+                }
+                Widget::Panel { size, high_contrast_only } => {
+                    if *high_contrast_only && !accessibility.high_contrast {
+                        continue;
+                    }
+                    let size = Vec2::from(*size);
+                    let at = placed.anchor.place(pos, size);
+                    let panel = graphics::Mesh::new_rectangle(
+                        ctx, DrawMode::fill(), Rect::new(at.x, at.y, size.x, size.y), color)?;
+                    graphics::draw(ctx, &panel, DrawParam::new())?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,235 @@
+//! How the cat jumps. Kept away from `Player` and its sprite so the feel can
+//! be tested without a window.
+
+use serde::{Deserialize, Serialize};
+
+use crate::tuning::Tuning;
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct JumpState {
+    /// Seconds since the cat was last on the ground.
+    airtime: f32,
+    /// Seconds left for a jump press that hasn't been used yet.
+    buffered: f32,
+    /// Jump was held last tick, to tell presses from holds.
+    was_held: bool,
+    /// Jump went down this tick.
+    pressed: bool,
+    /// In the air from a jump, not from walking off something. Stops coyote
+    /// time giving a second jump.
+    jumped: bool,
+    /// Still going up from a jump that's being held, so letting go can cut it.
+    holding: bool,
+}
+
+impl JumpState {
+    /// Call once per tick before moving. Returns true if the cat jumped, in
+    /// which case `velocity_y` is now the jump speed.
+    pub fn update(&mut self, tuning: &Tuning, held: bool, grounded: bool, velocity_y: &mut f32, dt: f32) -> bool {
+        let pressed = held && !self.was_held;
+        self.was_held = held;
+        self.pressed = pressed;
+
+        if grounded {
+            self.airtime = 0.0;
+            self.jumped = false;
+        } else {
+            self.airtime += dt;
+        }
+
+        if pressed {
+            self.buffered = tuning.jump_buffer_time;
+        }
+
+        let can_jump = grounded || (!self.jumped && self.airtime <= tuning.coyote_time);
+        if self.buffered > 0.0 && can_jump {
+            *velocity_y = tuning.jump_velocity;
+            self.buffered = 0.0;
+            self.jumped = true;
+            self.holding = true;
+            return true;
+        }
+        self.buffered = (self.buffered - dt).max(0.0);
+
+        // Letting go on the way up ends the jump early.
+        if self.holding && !held && *velocity_y > 0.0 {
+            *velocity_y *= tuning.jump_release_cut;
+        }
+        if !held || *velocity_y <= 0.0 {
+            self.holding = false;
+        }
+        false
+    }
+
+    /// Jumps again in the air if jump was pressed this tick. The caller
+    /// decides whether the cat has a jump to spend.
+    pub fn air_jump(&mut self, tuning: &Tuning, velocity_y: &mut f32) -> bool {
+        if !self.pressed {
+            return false;
+        }
+        *velocity_y = tuning.double_jump_velocity;
+        self.buffered = 0.0;
+        self.jumped = true;
+        self.holding = true;
+        true
+    }
+
+    /// Vertical speed after a tick of gravity. Falling is heavier than rising.
+    pub fn apply_gravity(&self, tuning: &Tuning, velocity_y: f32, dt: f32) -> f32 {
+        let scale = if velocity_y > 0.0 {
+            tuning.rise_gravity_scale
+        } else {
+            tuning.fall_gravity_scale
+        };
+        velocity_y - tuning.gravity * scale * dt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1.0 / 60.0;
+
+    /// Just the vertical part of the cat, on a floor at 0.
+    struct Body {
+        y: f32,
+        vy: f32,
+        grounded: bool,
+        jump: JumpState,
+    }
+
+    impl Body {
+        fn on_ground() -> Body {
+            Body { y: 0.0, vy: 0.0, grounded: true, jump: JumpState::default() }
+        }
+
+        /// Falling for long enough that coyote time is over.
+        fn in_air(y: f32) -> Body {
+            let jump = JumpState { airtime: 1.0, ..JumpState::default() };
+            Body { y, vy: 0.0, grounded: false, jump }
+        }
+
+        /// Same order as the game: input, then move, then land.
+        fn tick(&mut self, tuning: &Tuning, held: bool) -> bool {
+            let jumped = self.jump.update(tuning, held, self.grounded, &mut self.vy, DT);
+            if jumped {
+                self.grounded = false;
+            }
+            self.y += self.vy * DT;
+            if !self.grounded {
+                self.vy = self.jump.apply_gravity(tuning, self.vy, DT);
+            }
+            if self.y <= 0.0 {
+                self.y = 0.0;
+                self.vy = 0.0;
+                self.grounded = true;
+            } else {
+                self.grounded = false;
+            }
+            jumped
+        }
+
+        /// Holds jump for `hold` ticks then lets go, and reports the peak.
+        fn apex(&mut self, tuning: &Tuning, hold: usize) -> f32 {
+            let mut apex = 0.0f32;
+            for i in 0..600 {
+                self.tick(tuning, i < hold);
+                apex = apex.max(self.y);
+                if i > 0 && self.grounded {
+                    break;
+                }
+            }
+            apex
+        }
+    }
+
+    #[test]
+    fn holding_jumps_higher_than_tapping() {
+        let tuning = Tuning::default();
+        let tapped = Body::on_ground().apex(&tuning, 1);
+        let held = Body::on_ground().apex(&tuning, 600);
+        assert!(tapped > 0.0);
+        assert!(held > tapped * 1.5, "held {} tapped {}", held, tapped);
+    }
+
+    #[test]
+    fn falls_faster_than_it_rises() {
+        let tuning = Tuning::default();
+        let mut body = Body::on_ground();
+        let (mut rising, mut falling) = (0, 0);
+        for i in 0..600 {
+            body.tick(&tuning, true);
+            if body.grounded && i > 0 {
+                break;
+            }
+            if body.vy > 0.0 { rising += 1 } else { falling += 1 }
+        }
+        assert!(falling < rising, "rising {} falling {}", rising, falling);
+    }
+
+    #[test]
+    fn coyote_time_allows_a_late_jump() {
+        let tuning = Tuning::default();
+        let mut body = Body::on_ground();
+        body.tick(&tuning, false);
+        // Walked off a ledge a moment ago.
+        body.y = 100.0;
+        body.grounded = false;
+        body.tick(&tuning, false);
+        assert!(body.tick(&tuning, true));
+    }
+
+    #[test]
+    fn coyote_time_runs_out() {
+        let tuning = Tuning::default();
+        let mut body = Body::on_ground();
+        body.tick(&tuning, false);
+        body.y = 1000.0;
+        body.grounded = false;
+        let ticks = (tuning.coyote_time / DT).ceil() as usize + 1;
+        for _ in 0..ticks {
+            body.tick(&tuning, false);
+        }
+        assert!(!body.tick(&tuning, true));
+    }
+
+    #[test]
+    fn coyote_time_gives_no_second_jump() {
+        let tuning = Tuning::default();
+        let mut body = Body::on_ground();
+        assert!(body.tick(&tuning, true));
+        body.tick(&tuning, false);
+        assert!(!body.tick(&tuning, true));
+    }
+
+    #[test]
+    fn press_just_before_landing_is_buffered() {
+        let tuning = Tuning::default();
+        let mut body = Body::in_air(1.0);
+        body.vy = -120.0;
+        // Pressed while still falling, then held until the landing.
+        assert!(!body.tick(&tuning, true));
+        let mut jumped = false;
+        for _ in 0..10 {
+            jumped |= body.tick(&tuning, true);
+        }
+        assert!(jumped);
+    }
+
+    #[test]
+    fn press_long_before_landing_is_dropped() {
+        let tuning = Tuning::default();
+        let mut body = Body::in_air(500.0);
+        body.tick(&tuning, true);
+        let mut jumped = false;
+        for _ in 0..600 {
+            jumped |= body.tick(&tuning, false);
+            if body.grounded {
+                break;
+            }
+        }
+        assert!(body.grounded);
+        assert!(!jumped);
+    }
+}
@@ -0,0 +1,79 @@
+#![allow(clippy::unusual_byte_groupings)]
+//! Everything but the window: the game runs from `main.rs`, and the balance
+//! simulator in `bin/balance.rs` plays the same stages without one.
+
+use glam::*;
+
+pub mod assets;
+pub mod sprite;
+pub mod tileset;
+pub mod decor;
+pub mod ecs;
+pub mod systems;
+pub mod broadphase;
+pub mod hazards;
+pub mod player;
+pub mod rank;
+pub mod results;
+pub mod save;
+pub mod settings;
+pub mod settings_menu;
+pub mod campaign;
+pub mod camera;
+pub mod backdrop;
+pub mod lighting;
+pub mod locale;
+pub mod hud;
+pub mod menu;
+pub mod modes;
+pub mod screens;
+pub mod stage_select;
+pub mod scores;
+pub mod stats;
+pub mod toast;
+pub mod capture;
+pub mod replay;
+pub mod score_screen;
+pub mod sfx;
+pub mod sim;
+pub mod trajectory;
+pub mod terrain;
+pub mod arena;
+pub mod markers;
+pub mod bot;
+pub mod storage;
+pub mod tuning;
+pub mod tuning_overlay;
+pub mod jump;
+pub mod moves;
+
+/// The size everything is laid out in, whatever size the window really is.
+pub const SCREEN_SIZE: (f32, f32) = (640.0, 480.0);
+
+/// Height the cat stands at on the ground.
+pub const FLOOR_Y: f32 = -162.0;
+
+/// Candy appears at this height...
+pub const SPAWN_HEIGHT: f32 = 150.0;
+/// ...anywhere across this fraction of the screen either side of the middle.
+pub const SPAWN_SPREAD: f32 = 0.45;
+
+/// Where the cat starts each stage.
+pub fn player_start() -> Vec2 {
+    Vec2::new(0.0, -SCREEN_SIZE.1 / 2.0 + 32.0 + 16.0)
+}
+
+pub fn world_to_screen_coords(screen_width: f32, screen_height: f32, point: Vec2) -> Vec2 {
+    let x = point.x + screen_width / 2.0;
+    let y = screen_height - (point.y + screen_height / 2.0);
+    Vec2::new(x, y)
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct ControllerState {
+    pub left: bool,
+    pub right: bool,
+    pub up: bool,
+    pub down: bool,
+    pub dash: bool,
+}
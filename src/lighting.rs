@@ -0,0 +1,202 @@
+//! The night-time look: the scene is darkened, then lit back up around the
+//! cat, candy and jack-o'-lanterns, with a vignette on top and, if shaders
+//! work, a colour grade. Everything here is optional, see `LightingMode`.
+
+use ggez::conf::NumSamples;
+use ggez::graphics::{self, BlendMode, Canvas, Color, DrawParam, Drawable, EmptyConst, Image, Shader};
+use ggez::{Context, GameResult};
+use glam::*;
+
+use crate::ecs::{HazardKind, World};
+use crate::settings::LightingMode;
+use crate::{world_to_screen_coords, SCREEN_SIZE};
+
+/// What unlit things are multiplied by.
+const AMBIENT: Color = Color::new(0.35, 0.3, 0.5, 1.0);
+
+const CAT_LIGHT: (f32, Color) = (130.0, Color::new(0.9, 0.8, 0.7, 1.0));
+const CANDY_LIGHT: (f32, Color) = (40.0, Color::new(1.0, 0.6, 0.8, 0.8));
+const LANTERN_LIGHT: (f32, Color) = (70.0, Color::new(1.0, 0.55, 0.1, 1.0));
+const GHOST_LIGHT: (f32, Color) = (36.0, Color::new(0.6, 0.8, 1.0, 0.5));
+const SPARKLE_LIGHT: (f32, Color) = (30.0, Color::new(1.0, 1.0, 0.8, 0.8));
+
+/// Pixels across the generated light and vignette images. They get scaled up.
+const LIGHT_SIZE: u16 = 64;
+const VIGNETTE_SIZE: (u16, u16) = (64, 48);
+
+/// A white spot fading out to nothing at the edge.
+fn light_image(ctx: &mut Context) -> GameResult<Image> {
+    let n = LIGHT_SIZE as usize;
+    let mut rgba = Vec::with_capacity(n * n * 4);
+    for y in 0..n {
+        for x in 0..n {
+            let d = (vec2(x as f32, y as f32) + 0.5) / n as f32 * 2.0 - 1.0;
+            let falloff = (1.0 - d.length()).max(0.0);
+            rgba.extend_from_slice(&[255, 255, 255, (falloff * falloff * 255.0) as u8]);
+        }
+    }
+    let mut image = Image::from_rgba8(ctx, LIGHT_SIZE, LIGHT_SIZE, &rgba)?;
+    image.set_blend_mode(Some(BlendMode::Add));
+    Ok(image)
+}
+
+/// Clear in the middle, darker towards the corners.
+fn vignette_image(ctx: &mut Context) -> GameResult<Image> {
+    let (w, h) = (VIGNETTE_SIZE.0 as usize, VIGNETTE_SIZE.1 as usize);
+    let mut rgba = Vec::with_capacity(w * h * 4);
+    for y in 0..h {
+        for x in 0..w {
+            let d = vec2((x as f32 + 0.5) / w as f32, (y as f32 + 0.5) / h as f32) * 2.0 - 1.0;
+            let t = ((d.length() - 0.6) / 0.8).clamp(0.0, 1.0);
+            rgba.extend_from_slice(&[0, 0, 0, (t * t * 0.7 * 255.0) as u8]);
+        }
+    }
+    Image::from_rgba8(ctx, VIGNETTE_SIZE.0, VIGNETTE_SIZE.1, &rgba)
+}
+
+fn screen_canvas(ctx: &mut Context) -> GameResult<Canvas> {
+    let format = graphics::get_window_color_format(ctx);
+    Canvas::new(ctx, SCREEN_SIZE.0 as u16, SCREEN_SIZE.1 as u16, NumSamples::One, format)
+}
+
+pub struct Lighting {
+    /// What the settings asked for.
+    wanted: LightingMode,
+    /// What's actually running.
+    mode: LightingMode,
+    light: Option<Image>,
+    vignette: Option<Image>,
+    /// Darkness with the lights added in, multiplied over the scene.
+    light_map: Option<Canvas>,
+    /// In `Full`, the scene is drawn here first so the grade can go over all of it.
+    scene: Option<(Canvas, Shader<EmptyConst>)>,
+}
+
+impl Lighting {
+    /// Never fails: anything that doesn't work drops it to a lower mode.
+    pub fn new(ctx: &mut Context, wanted: LightingMode) -> Lighting {
+        let mut lighting = Lighting {
+            wanted,
+            mode: LightingMode::Off,
+            light: None,
+            vignette: None,
+            light_map: None,
+            scene: None,
+        };
+        if wanted == LightingMode::Off {
+            return lighting;
+        }
+
+        let basic = (|| -> GameResult<(Image, Image, Canvas)> {
+            let mut light_map = screen_canvas(ctx)?;
+            light_map.set_blend_mode(Some(BlendMode::Multiply));
+            Ok((light_image(ctx)?, vignette_image(ctx)?, light_map))
+        })();
+        match basic {
+            Ok((light, vignette, light_map)) => {
+                lighting.light = Some(light);
+                lighting.vignette = Some(vignette);
+                lighting.light_map = Some(light_map);
+                lighting.mode = LightingMode::Basic;
+            }
+            Err(e) => {
+                println!("Lighting is off, couldn't set it up: {}", e);
+                return lighting;
+            }
+        }
+        if wanted == LightingMode::Basic {
+            return lighting;
+        }
+
+        let full = (|| -> GameResult<(Canvas, Shader<EmptyConst>)> {
+            let shader = Shader::new(ctx, "/shaders/basic.glslv", "/shaders/grade.glslf", EmptyConst, "Empty", None)?;
+            Ok((screen_canvas(ctx)?, shader))
+        })();
+        match full {
+            Ok(scene) => {
+                lighting.scene = Some(scene);
+                lighting.mode = LightingMode::Full;
+            }
+            Err(e) => println!("No colour grading, using basic lighting: {}", e),
+        }
+        lighting
+    }
+
+    pub fn wanted(&self) -> LightingMode {
+        self.wanted
+    }
+
+    /// Call before drawing anything that should be lit.
+    pub fn begin(&self, ctx: &mut Context) {
+        if let Some((scene, _)) = &self.scene {
+            graphics::set_canvas(ctx, Some(scene));
+            graphics::clear(ctx, Color::BLACK);
+        }
+    }
+
+    /// Lights what was drawn since `begin`. Anything drawn after, like the
+    /// HUD, is left alone.
+    pub fn finish(&self, ctx: &mut Context, world: &World, flicker: bool) -> GameResult {
+        if self.mode == LightingMode::Off {
+            return Ok(());
+        }
+        let (Some(light), Some(vignette), Some(light_map)) = (&self.light, &self.vignette, &self.light_map) else {
+            return Ok(());
+        };
+
+        graphics::set_canvas(ctx, Some(light_map));
+        graphics::clear(ctx, AMBIENT);
+        let to_screen = |p: Vec2| world_to_screen_coords(SCREEN_SIZE.0, SCREEN_SIZE.1, p);
+        let draw_light = |ctx: &mut Context, pos: Vec2, (radius, color): (f32, Color), brightness: f32| {
+            let params = DrawParam::new()
+                .dest(to_screen(pos))
+                .offset(vec2(0.5, 0.5))
+                .scale(Vec2::splat(radius * 2.0 / LIGHT_SIZE as f32))
+                .color(Color::new(color.r, color.g, color.b, color.a * brightness));
+            light.draw(ctx, params)
+        };
+        for (e, _) in world.players.iter() {
+            if let Some(t) = world.transforms.get(e) {
+                draw_light(ctx, t.pos, CAT_LIGHT, 1.0)?;
+            }
+        }
+        for (e, _) in world.catchables.iter() {
+            if let Some(t) = world.transforms.get(e) {
+                draw_light(ctx, t.pos, CANDY_LIGHT, 1.0)?;
+            }
+        }
+        for (e, hazard) in world.hazards.iter() {
+            let Some(t) = world.transforms.get(e) else { continue };
+            match hazard.kind {
+                HazardKind::Pumpkin => {
+                    // Two waves out of step look more like a candle than one.
+                    let phase = hazard.origin.x;
+                    let wobble = ((hazard.age * 11.0 + phase).sin() + (hazard.age * 17.3).sin()) * 0.5;
+                    let brightness = if flicker { 0.8 + 0.2 * wobble } else { 1.0 };
+                    draw_light(ctx, t.pos, LANTERN_LIGHT, brightness)?;
+                }
+                HazardKind::Ghost => draw_light(ctx, t.pos, GHOST_LIGHT, 1.0)?,
+                HazardKind::Bat => (),
+            }
+        }
+        for (e, _) in world.animators.iter() {
+            if let Some(t) = world.transforms.get(e) {
+                draw_light(ctx, t.pos, SPARKLE_LIGHT, 1.0)?;
+            }
+        }
+
+        graphics::set_canvas(ctx, self.scene.as_ref().map(|(scene, _)| scene));
+        graphics::draw(ctx, light_map, DrawParam::new())?;
+        let vignette_scale = vec2(
+            SCREEN_SIZE.0 / VIGNETTE_SIZE.0 as f32,
+            SCREEN_SIZE.1 / VIGNETTE_SIZE.1 as f32);
+        graphics::draw(ctx, vignette, DrawParam::new().scale(vignette_scale))?;
+
+        if let Some((scene, shader)) = &self.scene {
+            graphics::set_canvas(ctx, None);
+            let _lock = graphics::use_shader(ctx, shader);
+            graphics::draw(ctx, scene, DrawParam::new())?;
+        }
+        Ok(())
+    }
+}
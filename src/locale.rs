@@ -0,0 +1,179 @@
+//! Player-facing text, from `resources/lang/<code>.toml`, and the fonts to
+//! draw it with. Keys missing from a language fall back to English, then to
+//! the key itself, so a half-done translation still plays.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::io::Read;
+
+use ab_glyph::Font as _;
+use ggez::graphics::{Font, PxScale, Text, TextFragment};
+use ggez::{Context, GameError, GameResult};
+
+pub const DEFAULT_LANGUAGE: &str = "en";
+const LANG_DIR: &str = "/lang";
+
+/// Tried in order for each character, after any fonts the language asks for.
+/// ggez's own font comes last for anything none of them have.
+const FONTS: &[&str] = &["/Minecraftia.ttf"];
+
+/// A language's strings, flattened to `section.key`.
+struct LangFile {
+    name: String,
+    fonts: Vec<String>,
+    strings: HashMap<String, String>,
+}
+
+fn flatten(prefix: &str, table: toml::value::Table, out: &mut HashMap<String, String>) {
+    for (key, value) in table {
+        let key = if prefix.is_empty() { key } else { format!("{}.{}", prefix, key) };
+        match value {
+            toml::Value::String(s) => { out.insert(key, s); }
+            toml::Value::Table(t) => flatten(&key, t, out),
+            _ => println!("Ignoring {} in a language file, it isn't text", key),
+        }
+    }
+}
+
+fn read_lang(ctx: &mut Context, code: &str) -> GameResult<LangFile> {
+    let path = format!("{}/{}.toml", LANG_DIR, code);
+    let mut text = String::new();
+    ggez::filesystem::open(ctx, &path)?.read_to_string(&mut text)?;
+    let mut table: toml::value::Table = toml::from_str(&text)
+        .map_err(|e| GameError::ConfigError(format!("{}: {}", path, e)))?;
+
+    let name = match table.remove("name") {
+        Some(toml::Value::String(name)) => name,
+        _ => code.to_string(),
+    };
+    let fonts = match table.remove("fonts") {
+        Some(fonts) => fonts.try_into().map_err(|e| GameError::ConfigError(format!("{}: {}", path, e)))?,
+        None => Vec::new(),
+    };
+    let mut strings = HashMap::new();
+    flatten("", table, &mut strings);
+    Ok(LangFile { name, fonts, strings })
+}
+
+/// Every language in the folder as (code, name), English first.
+pub fn available(ctx: &mut Context) -> Vec<(String, String)> {
+    let mut codes = match ggez::filesystem::read_dir(ctx, LANG_DIR) {
+        Ok(paths) => paths
+            .filter(|p| p.extension().is_some_and(|e| e == "toml"))
+            .filter_map(|p| p.file_stem().map(|s| s.to_string_lossy().into_owned()))
+            .collect::<Vec<_>>(),
+        Err(e) => {
+            println!("Couldn't list languages: {}", e);
+            Vec::new()
+        }
+    };
+    codes.sort_by_key(|code| (code != DEFAULT_LANGUAGE, code.clone()));
+    codes.dedup();
+    codes.into_iter()
+        .filter_map(|code| match read_lang(ctx, &code) {
+            Ok(lang) => Some((code, lang.name)),
+            Err(e) => {
+                println!("Skipping language {}: {}", code, e);
+                None
+            }
+        })
+        .collect()
+}
+
+pub struct Locale {
+    strings: HashMap<String, String>,
+    /// English, for anything the language hasn't got yet.
+    fallback: HashMap<String, String>,
+    /// Each font with a copy we can ask which characters it has.
+    fonts: Vec<(Font, ab_glyph::FontArc)>,
+}
+
+fn load_font(ctx: &mut Context, path: &str) -> GameResult<(Font, ab_glyph::FontArc)> {
+    let mut bytes = Vec::new();
+    ggez::filesystem::open(ctx, path)?.read_to_end(&mut bytes)?;
+    let font = Font::new_glyph_font_bytes(ctx, &bytes)?;
+    let glyphs = ab_glyph::FontArc::try_from_vec(bytes)
+        .map_err(|e| GameError::FontError(format!("{}: {}", path, e)))?;
+    Ok((font, glyphs))
+}
+
+impl Locale {
+    /// Never fails: a broken or missing language is English instead, and
+    /// fonts that won't load are left out of the chain.
+    pub fn load(ctx: &mut Context, code: &str) -> Locale {
+        let fallback = match read_lang(ctx, DEFAULT_LANGUAGE) {
+            Ok(lang) => lang.strings,
+            Err(e) => {
+                println!("Couldn't read the English text, showing keys: {}", e);
+                HashMap::new()
+            }
+        };
+        let lang = match read_lang(ctx, code) {
+            Ok(lang) => lang,
+            Err(e) => {
+                println!("Couldn't read language {}, using English: {}", code, e);
+                LangFile { name: String::new(), fonts: Vec::new(), strings: fallback.clone() }
+            }
+        };
+
+        let paths = lang.fonts.iter().map(|s| s.as_str()).chain(FONTS.iter().copied());
+        let fonts = paths
+            .filter_map(|path| match load_font(ctx, path) {
+                Ok(font) => Some(font),
+                Err(e) => {
+                    println!("Couldn't load font {}: {}", path, e);
+                    None
+                }
+            })
+            .collect();
+        Locale { strings: lang.strings, fallback, fonts }
+    }
+
+    /// The text for `key`.
+    pub fn t<'a>(&'a self, key: &'a str) -> &'a str {
+        self.strings.get(key)
+            .or_else(|| self.fallback.get(key))
+            .map_or(key, |s| s.as_str())
+    }
+
+    /// The text for `key` with `{name}` bits filled in from `args`.
+    pub fn fmt(&self, key: &str, args: &[(&str, &dyn Display)]) -> String {
+        let mut text = self.t(key).to_string();
+        for (name, value) in args {
+            text = text.replace(&format!("{{{}}}", name), &value.to_string());
+        }
+        text
+    }
+
+    /// Which font in the chain draws `c`.
+    fn font_for(&self, c: char) -> Font {
+        self.fonts.iter()
+            .find(|(_, glyphs)| glyphs.glyph_id(c).0 != 0)
+            .map_or(Font::default(), |(font, _)| *font)
+    }
+
+    /// Like `Text::new((text, font, size))`, but each run of characters gets
+    /// the first font that has them.
+    pub fn text(&self, text: impl AsRef<str>, size: f32) -> Text {
+        let text = text.as_ref();
+        let fragment = |s: &str, font| TextFragment::new(s).font(font).scale(PxScale::from(size));
+        let mut out = Text::default();
+        let mut start = 0;
+        let mut run_font = None;
+        for (i, c) in text.char_indices() {
+            // Spaces are in every font, so they don't need to start a new run.
+            let font = match run_font {
+                Some(font) if c.is_whitespace() => font,
+                _ => self.font_for(c),
+            };
+            if let Some(prev) = run_font.filter(|&f| f != font) {
+                out.add(fragment(&text[start..i], prev));
+                start = i;
+            }
+            run_font = Some(font);
+        }
+        let font = run_font.unwrap_or_else(|| self.font_for(' '));
+        out.add(fragment(&text[start..], font));
+        out
+    }
+}
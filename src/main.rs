@@ -1,584 +1,894 @@
 #![allow(clippy::unusual_byte_groupings)]
 use ggez::conf;
-use ggez::event::{self, EventHandler, KeyCode, KeyMods};
-use ggez::graphics::{self, Color};
-use ggez::{Context, ContextBuilder, GameResult};
-use ggez::audio::Source;
+use ggez::event::{self, Axis, Button, EventHandler, GamepadId, KeyCode, KeyMods};
+#[cfg(debug_assertions)]
+use ggez::event::MouseButton;
+use ggez::graphics;
+use ggez::{Context, ContextBuilder, GameError, GameResult};
+use ggez::audio::{Source, SoundSource};
 use glam::*;
 use oorandom::Rand32;
-use tileset::TileSet;
+use pogin::assets::{self, Assets, Sprite};
+use pogin::backdrop::Backdrop;
+use pogin::camera::Camera;
+use pogin::campaign::{self, Goal, Progress, Stage};
+use pogin::capture::{self, FrameDump, Recorder};
+use pogin::decor::Decor;
+use pogin::hud::{Hud, HudValues};
+use pogin::lighting::Lighting;
+use pogin::locale::{self, Locale};
+use pogin::menu::MenuInput;
+use pogin::modes::{self, Mode, Rules};
+use pogin::rank::{self, Grade, Grading, Policy};
+use pogin::replay::Replay;
+use pogin::results::Results;
+use pogin::save::{self, RunSave};
+use pogin::score_screen::ScoreScreen;
+use pogin::scores::{Entry, Leaderboards};
+use pogin::screens::{After, Data, Go, Screen};
+use pogin::settings::{self, Action, Settings};
+use pogin::settings_menu::{MenuResult, SettingsMenu};
+use pogin::sfx::Sfx;
+use pogin::sim::{End, Events, Run, TICKS_PER_SECOND};
+use pogin::stage_select::StageSelect;
+use pogin::stats::{self, Achievement, Stats};
+use pogin::terrain::Terrain;
+use pogin::tileset::TileSet;
+use pogin::toast::Toasts;
+use pogin::trajectory::Ground;
+use pogin::tuning::Tuning;
+use pogin::tuning_overlay::TuningOverlay;
+use pogin::{arena, markers, sprite, systems, ControllerState, SCREEN_SIZE};
+#[cfg(debug_assertions)]
+use pogin::{world_to_screen_coords, FLOOR_Y, SPAWN_HEIGHT};
 
 use std::env;
 use std::path;
+use std::sync::Arc;
 
-mod sprite;
-mod tileset;
-mod physical;
-
-use lazy_static;
+#[cfg(debug_assertions)]
+mod debug_draw;
+#[cfg(debug_assertions)]
+mod editor;
 
 lazy_static::lazy_static! {
 
 }
 
+/// How far a stick has to lean to count as pressed.
+const STICK_DEADZONE: f32 = 0.5;
 
-#[derive(Debug, Clone, Copy)]
-enum LR {
-    Left,
-    Right
-}
-
-impl LR {
-    fn to_f32(self) -> f32 {
-        match self {
-            LR::Left => -1.0,
-            LR::Right => 1.0
-        }
+/// -1, 0 or 1 for which way a stick axis is leaning, if far enough.
+fn stick_dir(value: f32) -> i32 {
+    if value > STICK_DEADZONE {
+        1
+    } else if value < -STICK_DEADZONE {
+        -1
+    } else {
+        0
     }
 }
 
-trait Draw {
-    fn draw(&self, assets: &mut Assets, ctx: &mut Context, world_coords: (f32, f32)) -> GameResult;
-}
 
-struct Player {
-    sprite: sprite::Sprite,
-    pos: Vec2,
-    velocity: Vec2,
-    last_velocity: Vec2,
-    facing: LR,
-    animation_frame: f32,
-    bbox_size: f32,
-    life: i32,
-    grounded: bool
+/// Playing a replay back with `--render`: a tick every frame however long
+/// frames take, and every one of them dumped.
+struct Render {
+    input: Vec<ControllerState>,
+    /// Index into `input` of the next tick.
+    tick: usize,
+    dump: Option<FrameDump>,
+    /// The stage ended or the input ran out, so the last frame's been dumped.
+    done: bool,
 }
 
-impl Draw for Player {
-    fn draw(&self, assets: &mut Assets, ctx: &mut Context, world_coords: (f32, f32)) -> GameResult {
-        // let (screen_w, screen_h) = world_coords;
-        // let pos = world_to_screen_coords(screen_w, screen_h, self.pos);
-        // let frame = if self.velocity.x == 0.0 { 0 } else { self.animation_frame.floor() as usize };
-        // let image = &assets.player.frames[frame];
-        // let drawparams = graphics::DrawParam::new()
-        //     .dest(pos)
-        //     .offset(Vec2::new(0.5, 0.5))
-        //     .scale([-self.facing.to_f32()*1.0, 1.0]);
-        // graphics::draw(ctx, image, drawparams)?;
-        
-        self.sprite.draw_frame(ctx, self.animation_frame.floor() as usize)
-    }
-}
+/// Difficulty per level shown on the HUD.
+const LEVEL_DIFFICULTY: f32 = 30.0;
 
-#[derive(Debug, Clone, Copy)]
-struct Candy {
-    pos: Vec2,
-    velocity: Vec2,
-    animation_frame: f32,
-    bbox_size: f32,
-    is_collected: bool,
+struct MainState {
+    /// The stage being played.
+    run: Run,
+    assets: Assets,
+    input: ControllerState,
+    map: (Vec<Vec<usize>>, TileSet),
+    /// Where candy comes down on `map`.
+    ground: Ground,
+    settings: Settings,
+    /// Open over the game and any screen, which are paused while it is.
+    settings_menu: Option<SettingsMenu>,
+    /// Title, pause, stage select or scores. The game is paused under it.
+    screen: Option<Screen>,
+    /// High scores for each mode.
+    boards: Leaderboards,
+    stats: Stats,
+    achievements: Vec<Achievement>,
+    toasts: Toasts,
+    /// Taken in `draw`, once there's something drawn to take.
+    screenshot_wanted: bool,
+    recorder: Recorder,
+    /// This stage so far, to save when it ends. Not kept for runs loaded from
+    /// a save, which don't start anywhere a replay can.
+    replay: Option<Replay>,
+    render: Option<Render>,
+    /// Left stick, to turn it into presses.
+    stick: Vec2,
+    tuning: Tuning,
+    /// Where the tuning file gets saved back to.
+    resource_dir: path::PathBuf,
+    /// Unlike settings, the game keeps running under this one.
+    tuning_overlay: Option<TuningOverlay>,
+    #[cfg(debug_assertions)]
+    show_debug_draw: bool,
+    /// Open over the game, which waits under it.
+    #[cfg(debug_assertions)]
+    editor: Option<editor::Editor>,
+    stages: Vec<Stage>,
+    progress: Progress,
+    /// Index into `stages` of the one being played.
+    stage: usize,
+    modes: Vec<Mode>,
+    /// Index into `modes` of the one being played.
+    mode: usize,
+    grading: Grading,
+    backdrop: Backdrop,
+    decor: Decor,
+    camera: Camera,
+    lighting: Lighting,
+    hud: Hud,
+    /// Player-facing text and the fonts to draw it.
+    locale: Locale,
+    /// (code, name) of each language there's a file for.
+    languages: Vec<(String, String)>,
 }
 
-impl Draw for Candy {
-    fn draw(&self, assets: &mut Assets, ctx: &mut Context, world_coords: (f32, f32)) -> GameResult {
-        let (screen_w, screen_h) = world_coords;
-        let pos = world_to_screen_coords(screen_w, screen_h, self.pos);
-        let frame = self.animation_frame.floor() as usize;
-        let image = &assets.candy;//.frames[frame];
-        let scale = if self.velocity.y > 0.0 {
-            1.0 - self.velocity.y.max(0.0).min(80.0) / 80.0
-        } else {
-            1.0
-        };
-        let drawparams = graphics::DrawParam::new()
-            .dest(pos)
-            .offset(Vec2::new(0.5, 0.5))
-            .scale([scale, scale])
-            ;
-        graphics::draw(ctx, image, drawparams)
-    }
+/// What the screens need, borrowed field by field so `screen` and `boards`
+/// can still be borrowed mutably alongside it.
+macro_rules! screen_data {
+    ($state:expr) => {
+        Data {
+            stages: &$state.stages,
+            modes: &$state.modes,
+            progress: &$state.progress,
+            mode: $state.mode,
+            stats: &$state.stats,
+            achievements: &$state.achievements,
+            locale: &$state.locale,
+        }
+    };
 }
 
-type SpriteFrame = graphics::Image;
-
-fn get_rank(score: u32, health: i32) -> std::string::String {
-    (if score == 0 && health == PLAYER_LIFE {
-        "-"
-    }
-    else {
-        let ratio = health as f32 / PLAYER_LIFE as f32;
-        let bonus = score as f32  * ratio / 10.0;
-        let rankscore = ratio*0.5 + bonus*0.5;
-        if rankscore < 0.5 {
-            "F"
-        } else if rankscore < 0.75 {
-            "D"
-        } else if rankscore < 0.9 {
-            "C"
-        } else if rankscore < 1.0 {
-            "B"
-        } else if rankscore < 1.2 {
-            "A"
-        } else if rankscore < 1.4 {
-            "S"
-        } else if rankscore < 1.5 {
-            "SS"
-        } else {
-            "SS+"
+impl MainState {
+    fn new(
+        ctx: &mut Context,
+        assets: Assets,
+        settings: Settings,
+        resource_dir: path::PathBuf,
+        stages: Vec<Stage>,
+    ) -> GameResult<MainState> {
+        println!("Game resource path: {:?}", ctx.filesystem);
+
+        let locale = Locale::load(ctx, &settings.language);
+        let languages = locale::available(ctx);
+        print_instructions(&locale);
+
+        // Seed our RNG
+        let seed = 0;
+        let rng = Rand32::new(seed);
+
+        let tuning = Tuning::load(&resource_dir);
+        let achievements = stats::load_achievements(&resource_dir)?;
+        let grading = Grading::load(&resource_dir)?;
+        let modes = modes::load_modes(&resource_dir)?;
+        let wanted = stages.iter().map(|s| (&s.name, Some(&s.grading)))
+            .chain(modes.iter().map(|m| (&m.id, m.rules.grading.as_ref())));
+        for (name, policy) in wanted {
+            if let Some(policy) = policy.filter(|p| !grading.has(p)) {
+                println!("{} wants grading policy {:?}, which isn't in {}, using the default",
+                    name, policy, rank::GRADING_FILE);
+            }
         }
-    }).to_string()
-    
-}
 
-use std::sync::Arc;
+        let run = Run::new(tuning.player_life, rng, &stages[0]);
 
-#[derive(Clone)]
-struct Sprite {
-    frames: Arc<Vec<SpriteFrame>>
-}
+        let progress = Progress::load();
+        let map = stages[0].load_map(ctx)?;
+        let ground = Ground::from_layout(&map.0);
+        let backdrop = stages[0].load_backdrop(ctx)?;
+        let decor = stages[0].load_decor(ctx)?;
+        let lighting = Lighting::new(ctx, settings.display.lighting);
+        let hud = stages[0].load_hud(ctx)?;
 
-#[derive(Clone)]
-struct Particle {
-    sprite: Sprite,
-    pos: Vec2,
-    vel: Vec2,
-    animation_frame: f32,
-}
+        let s = MainState {
+            run,
+            assets,
+            input: ControllerState::default(),
+            map,
+            ground,
+            settings,
+            settings_menu: None,
+            screen: Some(Screen::title(&locale, save::exists())),
+            boards: Leaderboards::load(&modes),
+            stats: Stats::load(),
+            achievements,
+            toasts: Toasts::default(),
+            screenshot_wanted: false,
+            recorder: Recorder::default(),
+            replay: None,
+            render: None,
+            stick: Vec2::ZERO,
+            tuning,
+            resource_dir,
+            tuning_overlay: None,
+            #[cfg(debug_assertions)]
+            show_debug_draw: false,
+            #[cfg(debug_assertions)]
+            editor: None,
+            stages,
+            progress,
+            stage: 0,
+            modes,
+            mode: 0,
+            grading,
+            backdrop,
+            decor,
+            camera: Camera::default(),
+            lighting,
+            hud,
+            locale,
+            languages,
+        };
 
-impl Particle {
-    fn update(&mut self, dt: f32) -> bool {
-        self.animation_frame += dt * 30.0;
-        self.pos += self.vel * dt;
-        self.vel.y -= GRAVITY * dt;
-        // keep if:
-        (self.animation_frame.floor() as usize) < self.sprite.frames.len()
+        Ok(s)
     }
-}
 
-impl Draw for Particle {
-    fn draw(&self, _assets: &mut Assets, ctx: &mut Context, world_coords: (f32, f32)) -> GameResult {
-        let (screen_w, screen_h) = world_coords;
-        let pos = world_to_screen_coords(screen_w, screen_h, self.pos);
-        let frame = self.animation_frame.floor() as usize;
-        let image = &self.sprite.frames[frame];//.frames[frame];
-        let drawparams = graphics::DrawParam::new()
-            .dest(pos)
-            .offset(Vec2::new(0.5, 0.5))
-            ;
-        graphics::draw(ctx, image, drawparams)
+    fn start_stage(&mut self, ctx: &mut Context, mode: usize, index: usize) -> GameResult {
+        // The dice carry on from the last stage rather than starting over.
+        let mut rng = self.run.rng;
+        let replay = Replay::new(&self.modes[mode].id, &self.stages[index].name, &rng, &self.tuning);
+        let random_map = self.modes[mode].rules.random_map;
+        let stage = if random_map {
+            arena::arena(&self.stages[index], &mut rng, &self.tuning)
+        } else {
+            self.stages[index].clone()
+        };
+        self.map = stage.load_map(ctx)?;
+        self.ground = Ground::from_layout(&self.map.0);
+        self.backdrop = stage.load_backdrop(ctx)?;
+        self.decor = stage.load_decor(ctx)?;
+        self.hud = match &self.modes[mode].rules.hud {
+            Some(path) => Hud::load(ctx, path)?,
+            None => stage.load_hud(ctx)?,
+        };
+        self.mode = mode;
+        self.camera = Camera::default();
+        self.run = Run::new(self.start_life(), rng, &stage);
+        if random_map {
+            self.run.terrain = Terrain::from_layout(&self.map.0);
+        }
+        self.input = ControllerState::default();
+        self.stage = index;
+        self.replay = Some(replay);
+        self.screen = None;
+        Ok(())
     }
-}
 
+    /// Everything about the run in progress, to save.
+    fn save_run(&self) -> RunSave {
+        RunSave::new(&self.run, &self.modes[self.mode].id, &self.stages[self.stage].name, &self.map.0, self.camera)
+    }
 
-const PLAYER_LIFE: i32 = 12;
-
+    /// Starts the run's stage, then puts everything back how it was. Opens
+    /// paused, so nobody gets dropped straight back in.
+    fn load_run(&mut self, ctx: &mut Context, mut run: RunSave) -> GameResult {
+        let mode = self.modes.iter().position(|m| m.id == run.mode)
+            .ok_or_else(|| GameError::ConfigError(format!("saved run is in mode {:?}, which isn't in {}", run.mode, modes::MODES_FILE)))?;
+        let stage = self.stages.iter().position(|s| s.name == run.stage)
+            .ok_or_else(|| GameError::ConfigError(format!("saved run is on {:?}, which isn't in {}", run.stage, campaign::STAGES_FILE)))?;
+        if run.world.players.get(run.player).is_none() {
+            return Err(GameError::ConfigError("saved run has no cat".to_string()));
+        }
+        self.start_stage(ctx, mode, stage)?;
+        self.map.0 = std::mem::take(&mut run.map);
+        self.ground = Ground::from_layout(&self.map.0);
+        if self.rules().random_map {
+            self.run.terrain = Terrain::from_layout(&self.map.0);
+        }
+        self.camera = run.camera;
+        run.restore(&mut self.run);
+        self.replay = None;
+        self.screen = Some(Screen::pause(&screen_data!(self), self.stage, self.mode));
+        Ok(())
+    }
 
-/// Acceleration in pixels per second.
-const PLAYER_THRUST: f32 = 600.0;
-const PLAYER_BREAK_THRUST: f32 = PLAYER_THRUST * 3.0;
-const GRAVITY: f32 = 200.0;
+    /// Sets up `replay` to be played back a tick a frame, with every frame
+    /// going to `dump`, then quits. The tuning and dice are the replay's.
+    fn start_render(&mut self, ctx: &mut Context, replay: Replay, dump: FrameDump) -> GameResult {
+        let mode = self.modes.iter().position(|m| m.id == replay.mode)
+            .ok_or_else(|| GameError::ConfigError(format!("replay is in mode {:?}, which isn't in {}", replay.mode, modes::MODES_FILE)))?;
+        let stage = self.stages.iter().position(|s| s.name == replay.stage)
+            .ok_or_else(|| GameError::ConfigError(format!("replay is on {:?}, which isn't in {}", replay.stage, campaign::STAGES_FILE)))?;
+        self.tuning = replay.tuning.clone();
+        self.run.rng = replay.rng();
+        self.start_stage(ctx, mode, stage)?;
+        self.replay = None;
+        self.render = Some(Render { input: replay.ticks().collect(), tick: 0, dump: Some(dump), done: false });
+        Ok(())
+    }
 
-const PLAYER_VEL: f32 = 400.0;
+    /// One tick of the stage, and what follows it along on screen.
+    fn step(&mut self) -> Events {
+        let seconds = 1.0 / (TICKS_PER_SECOND as f32);
+        if self.replay.as_ref().is_some_and(|r| r.tuning != self.tuning) {
+            println!("Tuning changed partway through the stage, so there won't be a replay of it");
+            self.replay = None;
+        }
+        if let Some(replay) = &mut self.replay {
+            replay.push(&self.input);
+        }
+        let effects = !self.settings.accessibility.reduce_motion;
+        let events = self.run.tick(&self.input, &self.tuning, &self.modes[self.mode].rules, &self.stages[self.stage], effects);
+        self.camera.follow(events.player_pos, seconds);
+        self.backdrop.update(seconds);
+        self.decor.update(seconds);
+        let values = self.hud_values();
+        self.hud.update(&values, seconds);
+        events
+    }
 
-fn player_handle_input(actor: &mut Player, input: &ControllerState, dt: f32) {
-    let (facing, target_vel) = if input.left { 
-        (LR::Left, -1.0 )
-    } else if input.right { (LR::Right, 1.0) } else { (actor.facing, 0.0) };
+    fn save_replay(&mut self) {
+        let Some(replay) = self.replay.take() else { return };
+        if !self.settings.capture.save_replays || replay.is_empty() {
+            return;
+        }
+        match replay.save() {
+            Ok(path) => println!("Replay saved to {}", path.display()),
+            Err(e) => println!("Couldn't save the replay: {}", e),
+        }
+    }
 
-    if actor.velocity.x != 0.0 {
-        if actor.last_velocity.x == 0.0 {
-            actor.animation_frame += 0.99;
+    /// The render's update: the next tick, or once the last frame's out,
+    /// closing the dump and quitting.
+    fn render_update(&mut self, ctx: &mut Context) -> GameResult {
+        // Only so the clock doesn't pile up ticks, they're not what drives it.
+        while ggez::timer::check_update_time(ctx, TICKS_PER_SECOND) {}
+        let Some(render) = &mut self.render else { return Ok(()) };
+        if render.done {
+            if let Some(dump) = render.dump.take() {
+                let frames = dump.finish()?;
+                println!("Rendered {} frames at {} a second", frames, TICKS_PER_SECOND);
+                event::quit(ctx);
+            }
+            return Ok(());
+        }
+        let Some(&input) = render.input.get(render.tick) else {
+            render.done = true;
+            return Ok(());
+        };
+        render.tick += 1;
+        let last = render.tick == render.input.len();
+        self.input = input;
+        let ended = self.step().end.is_some();
+        if let Some(render) = &mut self.render {
+            render.done = last || ended;
         }
-        actor.animation_frame = (actor.animation_frame + 0.2 * actor.velocity.x.abs() / PLAYER_VEL) % 4.0;
+        Ok(())
     }
-    else {
-        actor.animation_frame = 0.0;
+
+    /// Mid-stage, or paused in one, rather than in the menus or on results.
+    fn in_run(&self) -> bool {
+        matches!(self.screen, None | Some(Screen::Pause(..)))
     }
 
-    actor.facing = facing;
-    actor.sprite.scale.x = -facing.to_f32();
-    let target_vel = target_vel * PLAYER_VEL;
-    let thrust_sign =
-        if actor.velocity.x < target_vel {
-            1.0
-        } else if actor.velocity.x > target_vel {
-            -1.0 
-        } else {
-            0.0
-        };
-    
-    let thrust =
-        if (target_vel == 0.0 && actor.grounded) || actor.velocity.x.signum() != target_vel.signum(){
-            PLAYER_BREAK_THRUST * dt * thrust_sign
-        } else{
-            PLAYER_THRUST * dt * thrust_sign
-        };
+    fn rules(&self) -> &Rules {
+        &self.modes[self.mode].rules
+    }
 
-    
-    actor.last_velocity = actor.velocity;
-    actor.velocity.x = if (actor.velocity.x - target_vel).abs() <= thrust {
-        target_vel
-    } else {
-        actor.velocity.x + thrust
-    };
+    fn start_life(&self) -> i32 {
+        self.rules().start_life(&self.tuning)
+    }
 
-    if input.up && actor.grounded {
-        actor.velocity.y = 400.0;
-        actor.grounded = false;
+    fn hud_values(&self) -> HudValues {
+        let goal = match self.stages[self.stage].goal {
+            _ if self.rules().time_limit.is_some() && !self.rules().stage_goal => {
+                self.locale.fmt("goal.time_left", &[("seconds", &self.run.time_left(self.rules()))])
+            }
+            _ if !self.rules().stage_goal => self.locale.fmt("goal.caught", &[("caught", &self.run.caught)]),
+            Goal::Candy(n) => self.locale.fmt("goal.candy_progress", &[("caught", &self.run.caught), ("count", &n)]),
+            Goal::Time(t) => {
+                let time = self.run.stage_time.min(t).floor();
+                self.locale.fmt("goal.time_progress", &[("time", &time), ("seconds", &t.round())])
+            }
+        };
+        HudValues {
+            rank: self.grade().rank,
+            score: self.run.score,
+            life: self.run.player().life,
+            max_life: self.run.max_life,
+            combo: self.run.combo,
+            goal,
+            caught: self.run.caught,
+            time_left: self.run.time_left(self.rules()),
+            level: 1 + (self.run.difficulty / LEVEL_DIFFICULTY) as u32,
+        }
     }
 
-    actor.sprite.pos.x = actor.pos.x + 320.0;
-    actor.sprite.pos.y = -actor.pos.y + 240.0;
-}
+    /// The mode's grading policy if it has one, otherwise the stage's.
+    fn policy(&self) -> &Policy {
+        self.grading.get(self.rules().policy_name(&self.stages[self.stage]))
+    }
 
-fn update_player_position(actor: &mut Player, dt: f32) {
-    let dv = actor.velocity * dt;
-    actor.pos += dv;
-    if !actor.grounded {
-        actor.velocity.y -= GRAVITY * dt * 5.0;
-    } else {
-        actor.velocity.y = 0.0;
+    /// The rank so far.
+    fn grade(&self) -> Grade {
+        self.policy().grade(&self.run.performance())
     }
-    if actor.pos.y <= -162.0 {
-        actor.pos.y = -162.0;
-        actor.grounded = true;
-        actor.velocity.x += f32::min(actor.velocity.y.abs(), actor.velocity.x.abs()) * actor.velocity.x.signum() * 0.5;
-        actor.velocity.y = 0.0;
-    } else {
-        actor.grounded = false;
+
+
+    /// Records how the stage went and shows the results, then the high
+    /// scores if it made the table, then stage select. Only modes that play
+    /// for progress can clear a stage.
+    fn end_stage(&mut self, out_of_life: bool) {
+        self.save_replay();
+        let grade = self.grade();
+        let rank = grade.rank;
+        let progress = self.rules().progress;
+        let stage = &self.stages[self.stage];
+        let cleared = progress && !out_of_life && rank >= stage.rank;
+        self.stats.finish_stage(&stage.name, rank, cleared);
+        self.check_achievements();
+        self.stats.save();
+
+        let stage = &self.stages[self.stage];
+        let mode = self.modes[self.mode].name_key();
+        let mode = self.locale.t(&mode);
+        let mut next = self.stage;
+        let message = if out_of_life {
+            self.locale.fmt("game.out_of_lives", &[("stage", &stage.name)])
+        } else if !progress {
+            self.locale.fmt("game.finished", &[("mode", &mode), ("stage", &stage.name), ("score", &self.run.score)])
+        } else if self.progress.finish(&self.stages, self.stage, rank) {
+            self.progress.save();
+            if self.progress.is_unlocked(self.stage + 1) && self.stage + 1 < self.stages.len() {
+                next = self.stage + 1;
+            }
+            self.locale.fmt("game.cleared", &[("stage", &stage.name), ("rank", &rank)])
+        } else {
+            self.locale.fmt("game.not_cleared", &[("rank", &rank), ("stage", &stage.name), ("needed", &stage.rank)])
+        };
+        println!("{}", message);
+        self.input = ControllerState::default();
+        let entry = self.boards.get(self.mode).qualifies(self.run.score).then(|| Entry {
+            name: String::new(),
+            stage: stage.name.clone(),
+            score: self.run.score,
+            rank,
+        });
+        let results = Results::new(grade, self.run.performance(), self.policy().clone(), message.clone(), &self.locale);
+        let after = After { mode: self.mode, stage: next, message };
+        self.screen = Some(Screen::Results(results, entry, after));
     }
-    
-}
 
-fn world_to_screen_coords(screen_width: f32, screen_height: f32, point: Vec2) -> Vec2 {
-    let x = point.x + screen_width / 2.0;
-    let y = screen_height - (point.y + screen_height / 2.0);
-    Vec2::new(x, y)
-}
+    /// Toasts anything the stats have just earned. Saves if there was any,
+    /// so it's not lost to a crash before the stage ends.
+    fn check_achievements(&mut self) {
+        let earned = self.stats.check(&self.achievements);
+        for a in &earned {
+            self.toasts.push(self.locale.t("achievement.unlocked"), self.locale.t(&a.name_key()));
+        }
+        if !earned.is_empty() {
+            self.stats.save();
+        }
+    }
 
+    fn go(&mut self, ctx: &mut Context, go: Go) {
+        match go {
+            Go::Stay => (),
+            Go::Title => self.screen = Some(Screen::title(&self.locale, save::exists())),
+            Go::ModeSelect { selected } => self.screen = Some(Screen::mode_select(&self.modes, selected, &self.locale)),
+            Go::StageSelect { mode, selected, message } => {
+                self.screen = Some(Screen::StageSelect(StageSelect::new(&screen_data!(self), mode, selected, message)));
+            }
+            Go::Scores => self.screen = Some(Screen::Scores(ScoreScreen::view(&self.boards, &self.modes, self.mode, &self.locale), None)),
+            Go::Stats => self.screen = Some(Screen::stats(&self.stats, &self.achievements, &self.locale)),
+            Go::Settings => self.settings_menu = Some(SettingsMenu::new(&self.settings, self.languages.clone(), &self.locale)),
+            Go::Start { mode, stage } => {
+                if let Err(e) = self.start_stage(ctx, mode, stage) {
+                    println!("Couldn't start {}: {}", self.stages[stage].name, e);
+                }
+            }
+            Go::Continue => {
+                match save::take() {
+                    Ok(Some(run)) => {
+                        if let Err(e) = self.load_run(ctx, run) {
+                            println!("Couldn't continue the saved run: {}", e);
+                            self.go(ctx, Go::Title);
+                        }
+                    }
+                    Ok(None) => self.go(ctx, Go::Title),
+                    Err(e) => {
+                        println!("Couldn't read the saved run: {}", e);
+                        self.go(ctx, Go::Title);
+                    }
+                }
+            }
+            Go::Resume => self.screen = None,
+            Go::SaveRun => {
+                self.stats.save();
+                match save::save(&self.save_run()) {
+                    Ok(()) => self.go(ctx, Go::Title),
+                    Err(e) => println!("Couldn't save the run: {}", e),
+                }
+            }
+            Go::GiveUp if self.rules().is_open_ended() => self.end_stage(false),
+            Go::GiveUp => {
+                // Doesn't go through end_stage, which would save these.
+                self.stats.save();
+                self.save_replay();
+                let message = self.locale.t("game.gave_up").to_string();
+                self.go(ctx, Go::StageSelect { mode: self.mode, selected: self.stage, message });
+            }
+            Go::Quit => {
+                self.stats.save();
+                event::quit(ctx);
+            }
+        }
+    }
 
+    /// Goes to the settings menu if it's open, otherwise whatever screen is up.
+    fn menu_input(&mut self, ctx: &mut Context, input: MenuInput) {
+        if let Some(menu) = &mut self.settings_menu {
+            let result = menu.input(&mut self.settings, &self.locale, input);
+            self.settings_changed(ctx, result);
+            return;
+        }
+        let Some(screen) = &mut self.screen else { return };
+        let go = screen.input(input, &screen_data!(self), &mut self.boards);
+        if let Some(sound) = screen.take_sound() {
+            self.assets.sfx.play(ctx, sound, self.settings.audio.effects_volume());
+        }
+        self.go(ctx, go);
+    }
 
-struct Assets {
-    player: Sprite,
-    bg: SpriteFrame,
-    candy: SpriteFrame,
-    font: graphics::Font,
-    collect_animation: Sprite,
-    // bgm: Source,
-    lifebar: SpriteFrame,
-    lifebar_bg: SpriteFrame,
-    collect_sheet: sprite::SpriteSheet,
-}
+    fn settings_changed(&mut self, ctx: &mut Context, result: MenuResult) {
+        if let Some(sound) = self.settings_menu.as_mut().and_then(|menu| menu.take_sound()) {
+            self.assets.sfx.play(ctx, sound, self.settings.audio.effects_volume());
+        }
+        match result {
+            MenuResult::Stay => (),
+            MenuResult::DisplayChanged => {
+                if let Err(e) = apply_display(ctx, &self.settings) {
+                    println!("Couldn't change the display mode: {}", e);
+                }
+            }
+            MenuResult::LanguageChanged => {
+                self.locale = Locale::load(ctx, &self.settings.language);
+                if let Some(menu) = &mut self.settings_menu {
+                    menu.relabel(&self.settings, &self.locale);
+                }
+                if let Some(screen) = &mut self.screen {
+                    screen.relabel(&screen_data!(self), &self.boards);
+                }
+            }
+            MenuResult::Close => self.close_settings(),
+        }
+        if let Some(bgm) = &mut self.assets.bgm {
+            bgm.set_volume(self.settings.audio.music_volume());
+        }
+        if self.lighting.wanted() != self.settings.display.lighting {
+            self.lighting = Lighting::new(ctx, self.settings.display.lighting);
+        }
+    }
 
-#[derive(Debug, Default)]
-struct ControllerState {
-    left: bool,
-    right: bool,
-    up: bool,
-    down: bool
-}
+    /// A gameplay action, from a key or a pad.
+    fn action_down(&mut self, action: Action, repeat: bool) {
+        match action {
+            Action::Left => self.input.left = true,
+            Action::Right => self.input.right = true,
+            Action::Jump => self.input.up = true,
+            Action::Down => self.input.down = true,
+            Action::Dash => self.input.dash = true,
+            Action::Screenshot if !repeat => self.screenshot_wanted = true,
+            Action::Clip if !repeat => self.save_clip(),
+            Action::Settings if !repeat => {
+                // Keys held going in would otherwise stay held.
+                self.input = ControllerState::default();
+                self.settings_menu = Some(SettingsMenu::new(&self.settings, self.languages.clone(), &self.locale));
+            }
+            Action::Quit if !repeat => {
+                self.input = ControllerState::default();
+                self.screen = Some(Screen::pause(&screen_data!(self), self.stage, self.mode));
+            }
+            _ => (),
+        }
+    }
 
-struct MainState {
-    player: Player,
-    candies: Vec<Candy>,
-    score: u32,
-    combo: u32,
-    assets: Assets,
-    screen_width: f32,
-    screen_height: f32,
-    input: ControllerState,
-    rng: Rand32,
-    particles: Vec<Particle>,
-    difficulty: f32,
-    is_first_frame: bool,
-    map: (Vec<Vec<usize>>, TileSet)
-}
+    fn action_up(&mut self, action: Action) {
+        match action {
+            Action::Left => self.input.left = false,
+            Action::Right => self.input.right = false,
+            Action::Jump => self.input.up = false,
+            Action::Down => self.input.down = false,
+            Action::Dash => self.input.dash = false,
+            _ => (),
+        }
+    }
 
-impl MainState {
-    fn new(ctx: &mut Context, assets: Assets, mut map: (Vec<Vec<usize>>, TileSet)) -> GameResult<MainState> {
-        println!("Game resource path: {:?}", ctx.filesystem);
 
-        print_instructions();
+    fn save_clip(&mut self) {
+        if self.recorder.is_saving() {
+            return;
+        }
+        if self.recorder.is_empty() {
+            self.toasts.push(self.locale.t("capture.no_clip"), self.locale.t("capture.no_clip_why"));
+            return;
+        }
+        match self.recorder.save(&self.settings.capture) {
+            Ok(()) => self.toasts.push(self.locale.t("capture.saving_clip"), ""),
+            Err(e) => self.capture_failed(e.to_string()),
+        }
+    }
 
-        let (width, height) = graphics::drawable_size(ctx);
+    /// The name's enough on screen, the whole path goes to the console.
+    fn capture_saved(&mut self, heading: &str, path: &path::Path) {
+        println!("Saved {}", path.display());
+        let name = path.file_name().map_or(String::new(), |n| n.to_string_lossy().into_owned());
+        self.toasts.push(self.locale.t(heading), name);
+    }
 
-        // Seed our RNG
-        let seed = 0;
-        let rng = Rand32::new(seed);
+    fn capture_failed(&mut self, message: String) {
+        println!("Capture failed: {}", message);
+        self.toasts.push(self.locale.t("capture.failed"), message);
+    }
 
-        let cat_sheet = sprite::SpriteSheet::new(
-            graphics::Image::new(ctx, "/cat.png")?, 4, 1
-        );
-
-        // let assets = Assets::new(ctx)?;
-        let player = Player {
-            sprite: cat_sheet.sprite(Vec2::new(0.0, 0.0)),
-            pos: Vec2::new(0.0, -height/ 2.0 + 32.0 + 16.0),
-            velocity: Vec2::new(0.0, 0.0),
-            last_velocity: Vec2::new(0.0, 0.0),
-            facing: LR::Right,
-            animation_frame: 0.0,
-            bbox_size: 10.0,
-            life: PLAYER_LIFE,
-            grounded: true
-        };
-        let candies = Vec::new();
-        
-        let s = MainState {
-            player,
-            candies,
-            score: 0,
-            combo: 0,
-            assets,
-            screen_width: width,
-            screen_height: height,
-            input: ControllerState::default(),
-            rng,
-            particles: Vec::new(),
-            difficulty: 0.0,
-            is_first_frame: true,
-            map
-        };
+    #[cfg(debug_assertions)]
+    fn open_editor(&mut self, ctx: &mut Context) {
+        self.input = ControllerState::default();
+        match editor::Editor::new(ctx, self.stages.clone(), self.stage) {
+            Ok(editor) => self.editor = Some(editor),
+            Err(e) => println!("Couldn't open the editor: {}", e),
+        }
+    }
 
-        Ok(s)
+    /// Takes the edited stages, saved or not, and plays the one the editor was on.
+    #[cfg(debug_assertions)]
+    fn close_editor(&mut self, ctx: &mut Context) {
+        let Some(editor) = self.editor.take() else { return };
+        let (stages, selected) = editor.finish();
+        self.stages = stages;
+        if let Err(e) = self.start_stage(ctx, self.mode, selected) {
+            println!("Couldn't start {}: {}", self.stages[selected].name, e);
+        }
     }
 
+    fn close_settings(&mut self) {
+        self.settings_menu = None;
+        self.settings.save();
+    }
+}
 
-   
+fn apply_display(ctx: &mut Context, settings: &Settings) -> GameResult {
+    graphics::set_mode(ctx, settings.display.window_mode())?;
+    // The game is laid out for 640x480, bigger windows just scale it up.
+    graphics::set_screen_coordinates(ctx, graphics::Rect::new(0.0, 0.0, SCREEN_SIZE.0, SCREEN_SIZE.1))
 }
 
-/// **********************************************************************
-/// A couple of utility functions.
-/// **********************************************************************
+// **********************************************************************
+// A couple of utility functions.
+// **********************************************************************
 
-fn print_instructions() {
+fn print_instructions(locale: &Locale) {
     println!();
-    println!("Welcome to Pogin!");
+    println!("{}", locale.t("intro.welcome"));
     println!();
-    println!("How to play:");
-    println!("L/R arrow keys to move");
-    println!("Catch candy to appease the Pogin.");
+    for key in ["intro.how", "intro.move", "intro.jump", "intro.air", "intro.goal", "intro.menus", "intro.hazards"] {
+        println!("{}", locale.t(key));
+    }
     println!();
 }
 
-const DIFFICULTY_RATE: f32 = 1.15;
-
-impl EventHandler<ggez::GameError> for MainState {
-    fn update(&mut self, ctx: &mut Context) -> GameResult {
-        const DESIRED_FPS: u32 = 60;
-
-        while ggez::timer::check_update_time(ctx, DESIRED_FPS) {
-            let seconds = 1.0 / (DESIRED_FPS as f32);
-
-            self.difficulty += seconds * DIFFICULTY_RATE;
-            let rate = (seconds + 1.0) * 0.005;
-            if self.rng.rand_float() < rate || self.is_first_frame {
-                self.is_first_frame = false;
+/// Ground tiles, each picked by what's around it. `layout` is bottom row first.
+fn draw_map(ctx: &mut Context, layout: &[Vec<usize>], tileset: &TileSet) -> GameResult {
+    let sample_tile = |m: &[Vec<usize>], x: i32, y: i32| {
+        if !(0..20).contains(&x) || !(0..15).contains(&y) {
+            0usize
+        } else {
+            m[14-(y as usize)][x as usize]
+        }
+    };
 
-                let pos = Vec2::new( ((self.rng.rand_float() - 0.5)*2.0) * self.screen_width * 0.45, 150.0 );
-                let mut velx = ((self.rng.rand_float() - 0.5)*2.0)*40.0;
-                let vely = self.rng.rand_float()*60.0+20.0;
+    for x in 0i32..20 {
+        for y in 0i32..15 {
+            let tile = sample_tile(layout, x, y);
+            if tile != 0 {
+                for sub_x in 0..2 {
+                    for sub_y in 0..2 {
+                        let rightfill = sub_x == 0 || sample_tile(layout, x+1, y) != 0;
+                        let leftfill = sub_x == 1 || sample_tile(layout, x-1, y) != 0;
+                        let up_fill = sub_y == 1 || sample_tile(layout, x, y-1) != 0;
+                        let down_fill = sub_y == 0 || sample_tile(layout, x, y+1) != 0;
+
+                        let up_up_fill = sample_tile(layout, x, y-1) != 0;
+
+                        let mut tile_id = 7.0;
+                        
+                        if !rightfill && !up_fill {
+                            tile_id = 2.0;
+                        }
+                        if !leftfill && !up_fill {
+                            tile_id = 1.0;
+                        }
+                        if leftfill && rightfill && up_fill {
+                            tile_id = 9.0;
+                        }
+                        if up_up_fill {
+                            tile_id = 12.0;
+                        }
+                        if !leftfill && rightfill && up_fill {
+                            tile_id = 5.0;
+                        }
+                        if leftfill && !rightfill && up_fill {
+                            tile_id = 6.0;
+                        }
+                        if !leftfill && rightfill && up_fill && up_up_fill {
+                            tile_id = 10.0;
+                        }
+                        if leftfill && !rightfill && up_fill && up_up_fill {
+                            tile_id = 11.0;
+                        }
+                        if !down_fill {
+                            tile_id = 8.0;
+                        }
+                        if !down_fill && !leftfill {
+                            tile_id = 3.0;
+                        }
+                        if !down_fill && !rightfill {
+                            tile_id = 4.0;
+                        }
 
-                if self.screen_width / 2.0 < (pos.x + velx * 2.0).abs() {
-                    velx *= -1.0;
+                        let params = graphics::DrawParam::new()
+                            // .offset(vec2(0.5, 0.5))
+                            .dest(vec2((2*x+sub_x) as f32 * 16.0, (2*y+sub_y) as f32 * 16.0))
+                            .src(tileset.src(tile_id as usize, sub_x, sub_y));
+                        graphics::draw(ctx, &tileset.img(), params)?;
+                    }
                 }
-
-                // let time_until_ground = 
-
-                self.candies.push(Candy {
-                    pos,
-                    velocity: Vec2::new(velx, vely ),
-                    animation_frame: 0.0,
-                    bbox_size: 10.0,
-                    is_collected: false,
-                });
             }
+        }
+    }
+    Ok(())
+}
 
-            
-            player_handle_input(&mut self.player, &self.input, seconds);
+impl EventHandler<ggez::GameError> for MainState {
+    fn update(&mut self, ctx: &mut Context) -> GameResult {
+        if self.render.is_some() {
+            return self.render_update(ctx);
+        }
+        let dt = ggez::timer::delta(ctx).as_secs_f32();
+        if let Some(menu) = &mut self.settings_menu {
+            menu.update(dt);
+        }
+        if let Some(screen) = &mut self.screen {
+            screen.update(dt);
+        }
+        self.toasts.update(dt);
+        match self.recorder.finished() {
+            Some(Ok(path)) => self.capture_saved("capture.clip", &path),
+            Some(Err(message)) => self.capture_failed(message),
+            None => (),
+        }
+        #[cfg(debug_assertions)]
+        let editing = self.editor.is_some();
+        #[cfg(not(debug_assertions))]
+        let editing = false;
+        if self.settings_menu.is_some() || self.screen.is_some() || editing {
+            // Eat the ticks so the game doesn't race to catch up after.
+            while ggez::timer::check_update_time(ctx, TICKS_PER_SECOND) {}
+            return Ok(());
+        }
 
+        while ggez::timer::check_update_time(ctx, TICKS_PER_SECOND) {
+            let seconds = 1.0 / (TICKS_PER_SECOND as f32);
+            let events = self.step();
 
-            update_player_position(&mut self.player, seconds);
-            if self.player.pos.x.abs() > self.screen_width / 2.0 {
-                self.player.pos.x = self.screen_width / 2.0 * self.player.pos.x.signum();
-                self.player.velocity.x = -self.player.velocity.x * 0.9;
-                if self.player.grounded {
-                    self.player.velocity.x *= 0.4;
-                }
+            self.stats.play_time += seconds as f64;
+            if events.jumped {
+                self.stats.jumps += 1;
             }
-
-
-            for candy in &mut self.candies {
-                candy.pos += candy.velocity * seconds;
-                candy.velocity.y -= GRAVITY * seconds;
+            self.stats.candy_caught += events.caught as u64;
+            self.stats.longest_combo = self.stats.longest_combo.max(self.run.max_combo);
+            if events.hurt {
+                self.stats.hits_taken += 1;
             }
+            self.stats.candy_missed += events.missed as u64;
+            if events.double_jump_unlocked {
+                println!("{}", self.locale.t("game.double_jump"));
+            }
+            self.check_achievements();
 
-            for candy in &mut self.candies {
-                let pdistance = candy.pos - self.player.pos;
-                if pdistance.length() < (self.player.bbox_size + candy.bbox_size) {
-                    self.combo += 1;
-                    self.score += self.combo;
-                    candy.is_collected = true;
-                    self.particles.push(Particle {
-                        pos: Vec2::new(self.player.pos.x, self.player.pos.y+16.0),
-                        // velocity: Vec2::new(0.0, 0.0),
-                        animation_frame: 0.0,
-                        sprite: self.assets.collect_animation.clone(),
-                        vel: self.player.velocity
-                    });
+            match events.end {
+                Some(End::OutOfLife) => {
+                    println!("{}", self.locale.t("game.over"));
+                    self.end_stage(true);
+                    break;
                 }
-                else if candy.pos.y < self.screen_height * -0.5 {
-                    self.player.life -= 1;
-                    candy.is_collected = true;
-                    self.combo = 0;
+                Some(End::Finished) => {
+                    self.end_stage(false);
+                    break;
                 }
-            }
-
-            self.particles = self.particles.iter_mut().filter_map(
-                |p| {
-                    if p.update(seconds) {
-                        Some(p.clone())
-                    }
-                    else {
-                        None
-                    }
-                }).collect();
-
-            self.candies.retain(|candy| !candy.is_collected);
-
-            if self.player.life <= 0 {
-                println!("Game over!");
-                event::quit(ctx);
+                None => (),
             }
         }
 
-        
-
         Ok(())
     }
 
     fn draw(&mut self, ctx: &mut Context) -> GameResult {
+        #[cfg(debug_assertions)]
+        if let Some(editor) = &self.editor {
+            graphics::clear(ctx, graphics::Color::BLACK);
+            editor.draw(ctx, &self.assets, &self.locale)?;
+            self.toasts.draw(ctx, &self.locale, &self.settings.accessibility)?;
+            return graphics::present(ctx);
+        }
 
         // graphics::clear(ctx, Color::from_rgb(180, 100, 200));
 
         {
             
             let assets = &mut self.assets;
-            let coords = (self.screen_width, self.screen_height);
 
             // let bottom_left = vec2(-coords.0 / 2.0, -coords.1 / 2.0);
 
-            graphics::draw(
-                ctx, &assets.bg,
-                graphics::DrawParam::new().dest(vec2(0.0, 0.0))
-            )?;
+            self.lighting.begin(ctx);
+            self.backdrop.draw(ctx, &self.camera)?;
 
-            let sample_tile = |m: &Vec<Vec<_>>, x: i32, y: i32| {
-                if !(0..20).contains(&x) || !(0..15).contains(&y) {
-                    0usize
-                } else {
-                    m[14-(y as usize)][x as usize]
-                }
-            };
-
-            for x in 0i32..20 {
-                for y in 0i32..15 {
-                    let tile = sample_tile(&self.map.0, x, y);
-                    if tile != 0 {
-                        for sub_x in 0..2 {
-                            for sub_y in 0..2 {
-                                let rightfill = sub_x == 0 || sample_tile(&self.map.0, x+1, y) != 0;
-                                let leftfill = sub_x == 1 || sample_tile(&self.map.0, x-1, y) != 0;
-                                let up_fill = sub_y == 1 || sample_tile(&self.map.0, x, y-1) != 0;
-                                let down_fill = sub_y == 0 || sample_tile(&self.map.0, x, y+1) != 0;
-
-                                let up_up_fill = sample_tile(&self.map.0, x, y-1) != 0;
-
-                                let mut tile_id = 7.0;
-                                
-                                if !rightfill && !up_fill {
-                                    tile_id = 2.0;
-                                }
-                                if !leftfill && !up_fill {
-                                    tile_id = 1.0;
-                                }
-                                if leftfill && rightfill && up_fill {
-                                    tile_id = 9.0;
-                                }
-                                if up_up_fill {
-                                    tile_id = 12.0;
-                                }
-                                if !leftfill && rightfill && up_fill {
-                                    tile_id = 5.0;
-                                }
-                                if leftfill && !rightfill && up_fill {
-                                    tile_id = 6.0;
-                                }
-                                if !leftfill && rightfill && up_fill && up_up_fill {
-                                    tile_id = 10.0;
-                                }
-                                if leftfill && !rightfill && up_fill && up_up_fill {
-                                    tile_id = 11.0;
-                                }
-                                if !down_fill {
-                                    tile_id = 8.0;
-                                }
-                                if !down_fill && !leftfill {
-                                    tile_id = 3.0;
-                                }
-                                if !down_fill && !rightfill {
-                                    tile_id = 4.0;
-                                }
-
-                                let params = graphics::DrawParam::new()
-                                    // .offset(vec2(0.5, 0.5))
-                                    .dest(vec2((2*x+sub_x) as f32 * 16.0, (2*y+sub_y) as f32 * 16.0))
-                                    .src(graphics::Rect { x: tile_id / 15.0, y: 0.0, w: 1.0 / 15.0, h: 1.0 });
-                                graphics::draw(ctx, &self.map.1.img(), params)?;
-                            }
-                        }
-                    }
-                }
-            }
+            draw_map(ctx, &self.map.0, &self.map.1)?;
+            self.decor.draw(ctx, false)?;
 
             // self.stage.draw(assets, ctx, coords)?;
 
-            for candy in self.candies.iter() {
-                candy.draw(assets, ctx, coords)?;
-            }
+            systems::render(&self.run.world, assets, ctx)?;
+            self.decor.draw(ctx, true)?;
+            self.lighting.finish(ctx, &self.run.world, !self.settings.accessibility.reduce_motion)?;
+            markers::draw(ctx, &self.run.world, &self.tuning, &self.ground, self.settings.accessibility.landing_markers)?;
 
-            for particle in self.particles.iter() {
-                particle.draw(assets, ctx, coords)?;
-            }
+        }
 
-            //let p = &self.player as &dyn Draw;
+        self.hud.draw(ctx, &self.locale, &self.hud_values(), &self.settings.accessibility)?;
 
-            // self.player.draw(assets, ctx, coords)?;
-            self.player.sprite.draw_frame(ctx, self.player.animation_frame.floor() as usize)?;
+        #[cfg(debug_assertions)]
+        if self.show_debug_draw {
+            debug_draw::draw(ctx, self)?;
+        }
 
+        if let Some(overlay) = &self.tuning_overlay {
+            overlay.draw(ctx, &self.locale, &self.tuning)?;
         }
 
-        let level_str = format!("Rank: {}", get_rank(self.score, self.player.life));
-        let score_str = format!("Score: {}", self.score);
-        let life_str = format!("{}", self.player.life);
-        let level_display = graphics::Text::new((level_str, self.assets.font, 32.0));
-        let score_display = graphics::Text::new((score_str, self.assets.font, 32.0));
-        let life_display = graphics::Text::new((life_str, self.assets.font, 32.0));
-        graphics::draw(ctx, &level_display, (Vec2::new(10.0, 10.0), 0.0, Color::WHITE))?;
-        graphics::draw(ctx, &score_display, (Vec2::new(200.0, 10.0), 0.0, Color::WHITE))?;
-        graphics::draw(ctx, &life_display, (Vec2::new(10.0, 40.0), 0.0, Color::WHITE))?;
+        if let Some(screen) = &self.screen {
+            screen.draw(ctx, &self.locale, &self.settings.accessibility)?;
+        }
 
-        for i in 0..PLAYER_LIFE {
-            let x = (i as f32) * (5.0) + 44.0;
+        if let Some(menu) = &self.settings_menu {
+            menu.draw(ctx, &self.locale, &self.settings)?;
+        }
 
-            let drawparams = graphics::DrawParam::new()
-                .dest(Vec2::new(x, 42.0));
-            graphics::draw(ctx, &self.assets.lifebar_bg, drawparams)?;
-            if i < self.player.life {
-                graphics::draw(ctx, &self.assets.lifebar, drawparams)?;
+        if let Some(dump) = self.render.as_mut().and_then(|r| r.dump.as_mut()) {
+            dump.write(ctx)?;
+        }
+
+        // Before the toasts, so "saved" notices don't end up in the next one.
+        let dt = ggez::timer::delta(ctx).as_secs_f32();
+        if let Err(e) = self.recorder.capture(ctx, &self.settings.capture, dt) {
+            self.capture_failed(e.to_string());
+        }
+        if self.screenshot_wanted {
+            self.screenshot_wanted = false;
+            match capture::screenshot(ctx) {
+                Ok(path) => self.capture_saved("capture.screenshot", &path),
+                Err(e) => self.capture_failed(e.to_string()),
             }
         }
-            
-        
+
+        self.toasts.draw(ctx, &self.locale, &self.settings.accessibility)?;
 
         graphics::present(ctx)?;
 
@@ -591,42 +901,167 @@ impl EventHandler<ggez::GameError> for MainState {
         ctx: &mut Context,
         keycode: KeyCode,
         _keymod: KeyMods,
-        _repeat: bool,
+        repeat: bool,
     ) {
-        match keycode {
-            KeyCode::Left => {
-                self.input.left = true;
+        // Nothing but closing the window while rendering.
+        if self.render.is_some() {
+            return;
+        }
+        #[cfg(debug_assertions)]
+        if let Some(editor) = &mut self.editor {
+            if editor.key_down(ctx, keycode, _keymod, &self.resource_dir) {
+                self.close_editor(ctx);
             }
-            KeyCode::Right => {
-                self.input.right = true;
+            return;
+        }
+        #[cfg(debug_assertions)]
+        if keycode == KeyCode::F4 && !repeat && self.settings_menu.is_none() {
+            self.open_editor(ctx);
+            return;
+        }
+        if let Some(menu) = &mut self.settings_menu {
+            let result = menu.key_down(&mut self.settings, &self.locale, keycode);
+            self.settings_changed(ctx, result);
+            return;
+        }
+
+        #[cfg(debug_assertions)]
+        if keycode == KeyCode::F3 && !repeat {
+            self.show_debug_draw = !self.show_debug_draw;
+            return;
+        }
+        if keycode == KeyCode::F2 && !repeat {
+            self.tuning_overlay = match self.tuning_overlay {
+                Some(_) => None,
+                None => Some(TuningOverlay::default()),
+            };
+            return;
+        }
+        if let Some(overlay) = &mut self.tuning_overlay {
+            let resource_dir = &self.resource_dir;
+            if overlay.key_down(&mut self.tuning, keycode, |tuning| tuning.save(resource_dir)) {
+                return;
             }
-            KeyCode::Up => self.input.up = true,
-            KeyCode::Down => self.input.down = true,
-            KeyCode::P => {
-                let img = graphics::screenshot(ctx).expect("Could not take screenshot");
-                img.encode(ctx, graphics::ImageFormat::Png, "/screenshot.png")
-                    .expect("Could not save screenshot");
+        }
+
+        let action = self.settings.controls.action_for(keycode);
+        if let Some(screen) = &self.screen {
+            let typing = screen.is_typing();
+            if !typing && action == Some(Action::Settings) && !repeat {
+                self.settings_menu = Some(SettingsMenu::new(&self.settings, self.languages.clone(), &self.locale));
+            } else if let Some(input) = MenuInput::from_key(&self.settings.controls, keycode, typing) {
+                self.menu_input(ctx, input);
             }
-            KeyCode::Escape => event::quit(ctx),
-            _ => (), 
+            return;
+        }
+        if let Some(action) = action {
+            self.action_down(action, repeat);
         }
     }
 
     fn key_up_event(&mut self, _ctx: &mut Context, keycode: KeyCode, _keymod: KeyMods) {
-        match keycode {
-            KeyCode::Left => {
-                self.input.left = false;
+        if let Some(action) = self.settings.controls.action_for(keycode) {
+            self.action_up(action);
+        }
+    }
+
+    fn text_input_event(&mut self, ctx: &mut Context, character: char) {
+        #[cfg(debug_assertions)]
+        if let Some(editor) = &mut self.editor {
+            editor.text_input(character);
+            return;
+        }
+        if self.settings_menu.is_none() && self.screen.as_ref().is_some_and(Screen::is_typing) {
+            self.menu_input(ctx, MenuInput::Char(character));
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    fn mouse_button_down_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        if let Some(editor) = &mut self.editor {
+            editor.mouse_motion(editor::mouse_pos(ctx, x, y));
+            editor.mouse_down(button);
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    fn mouse_button_up_event(&mut self, ctx: &mut Context, button: MouseButton, x: f32, y: f32) {
+        if let Some(editor) = &mut self.editor {
+            editor.mouse_motion(editor::mouse_pos(ctx, x, y));
+            editor.mouse_up(button);
+        }
+    }
+
+    #[cfg(debug_assertions)]
+    fn mouse_motion_event(&mut self, ctx: &mut Context, x: f32, y: f32, _dx: f32, _dy: f32) {
+        if let Some(editor) = &mut self.editor {
+            editor.mouse_motion(editor::mouse_pos(ctx, x, y));
+        }
+    }
+
+    fn gamepad_button_down_event(&mut self, ctx: &mut Context, btn: Button, _id: GamepadId) {
+        if self.render.is_some() {
+            return;
+        }
+        if self.settings_menu.is_some() || self.screen.is_some() {
+            if let Some(input) = MenuInput::from_button(btn) {
+                self.menu_input(ctx, input);
             }
-            KeyCode::Right => {
-                self.input.right = false;
+            return;
+        }
+        if let Some(action) = settings::button_action(btn) {
+            self.action_down(action, false);
+        }
+    }
+
+    fn gamepad_button_up_event(&mut self, _ctx: &mut Context, btn: Button, _id: GamepadId) {
+        if let Some(action) = settings::button_action(btn) {
+            self.action_up(action);
+        }
+    }
+
+    /// The left stick moves, and steps through menus a press at a time.
+    fn gamepad_axis_event(&mut self, ctx: &mut Context, axis: Axis, value: f32, _id: GamepadId) {
+        let (was, now) = match axis {
+            Axis::LeftStickX => (stick_dir(std::mem::replace(&mut self.stick.x, value)), stick_dir(value)),
+            Axis::LeftStickY => (stick_dir(std::mem::replace(&mut self.stick.y, value)), stick_dir(value)),
+            _ => return,
+        };
+        if was == now {
+            return;
+        }
+        if self.settings_menu.is_some() || self.screen.is_some() {
+            let input = match (axis, now) {
+                (Axis::LeftStickX, -1) => MenuInput::Left,
+                (Axis::LeftStickX, 1) => MenuInput::Right,
+                (Axis::LeftStickY, 1) => MenuInput::Up,
+                (Axis::LeftStickY, -1) => MenuInput::Down,
+                _ => return,
+            };
+            self.menu_input(ctx, input);
+            return;
+        }
+        match axis {
+            Axis::LeftStickX => {
+                self.input.left = now < 0;
+                self.input.right = now > 0;
             }
-            KeyCode::Up => self.input.up = false,
-            KeyCode::Down => self.input.down = false,
-            _ => (), 
+            _ => self.input.down = now < 0,
         }
     }
-}
 
+    /// Closing the window mid-stage would otherwise lose its stats, and the
+    /// run itself, which is saved to continue.
+    fn quit_event(&mut self, _ctx: &mut Context) -> bool {
+        self.stats.save();
+        if self.in_run() && self.render.is_none() {
+            if let Err(e) = save::save(&self.save_run()) {
+                println!("Couldn't save the run: {}", e);
+            }
+        }
+        false
+    }
+}
 
 pub fn main() -> GameResult {
 
@@ -639,50 +1074,40 @@ pub fn main() -> GameResult {
         path::PathBuf::from("./resources")
     };
 
-    let cb = ContextBuilder::new("pogin", "dunkyl")
-        .window_setup(conf::WindowSetup::default().title("Pogin!"))
-        .window_mode(conf::WindowMode::default().dimensions(640.0, 480.0))
-        .add_resource_path(resource_dir);
-
-    let (mut ctx, events_loop) = cb.build()?;
+    let args = env::args().collect::<Vec<_>>();
+    // The value after `--name`, if it's there.
+    let arg = |name: &str| -> GameResult<Option<&String>> {
+        match args.iter().position(|a| a == name) {
+            Some(at) => args.get(at + 1).map(Some).ok_or_else(|| GameError::ConfigError(format!("{} needs a value", name))),
+            None => Ok(None),
+        }
+    };
 
-    const UP:    IVec2 = ivec2( 0,  1);
-    const DOWN:  IVec2 = ivec2( 0, -1);
-    const LEFT:  IVec2 = ivec2(-1,  0);
-    const RIGHT: IVec2 = ivec2( 1,  0);
-
-    let grass_tiles = tileset::TileSet::new(
-        &mut ctx,
-        "/ground2.png",
-        vec![
-            vec![ivec2(0, 0)],
-            vec![UP, LEFT],
-            vec![UP, RIGHT],
-            vec![DOWN, LEFT],
-            vec![DOWN, RIGHT],
-            vec![UP+UP, LEFT],
-            vec![UP+UP, RIGHT],
-            vec![UP],
-            vec![DOWN],
-            vec![UP+UP],
-            vec![LEFT],
-            vec![RIGHT],
-            vec![],
-            vec![UP+RIGHT],
-            vec![UP+LEFT],
-        ]
-    );
+    let mut settings = Settings::load();
+    let render = arg("--render")?;
+    if render.is_some() {
+        // Any size works, the game scales to it, but not 4:3 stretches.
+        if let Some(size) = arg("--size")? {
+            let (w, h) = size.split_once('x')
+                .and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)))
+                .ok_or_else(|| GameError::ConfigError(format!("--size is like 1280x960, not {:?}", size)))?;
+            settings.display.width = w;
+            settings.display.height = h;
+        }
+        settings.display.fullscreen = false;
+        // Frames go as fast as they're drawn, not at the monitor's pace.
+        settings.display.vsync = false;
+    }
 
-    let mut test_map =
-        (0..15).map(|_| (0..20).map(|_| 0usize).collect::<Vec<_>>())
-        .collect::<Vec<_>>();
+    let cb = ContextBuilder::new("pogin", "dunkyl")
+        .window_setup(conf::WindowSetup::default().title("Pogin!").vsync(settings.display.vsync))
+        .window_mode(settings.display.window_mode())
+        .add_resource_path(resource_dir.clone());
 
-    for i in 0..20 {
-        test_map[0][i] = 1;
-        test_map[1][i] = 1;
-    }
+    let (mut ctx, events_loop) = cb.build()?;
+    apply_display(&mut ctx, &settings)?;
 
-    
+    let stages = campaign::load_stages(&resource_dir)?;
 
     let collect_sheet = sprite::SpriteSheet::new(
         graphics::Image::new(&mut ctx, "/collect.png")?, 6, 1
@@ -692,9 +1117,7 @@ pub fn main() -> GameResult {
         frames: Arc::new(vec![
             graphics::Image::new(&mut ctx, "/cat1.png")?,
             graphics::Image::new(&mut ctx, "/cat2.png")?])};
-    let bg = graphics::Image::new(&mut ctx, "/bg2.png")?;
     let candy = graphics::Image::new(&mut ctx, "/candy_a.png")?;
-    let font = graphics::Font::new(&mut ctx, "/Minecraftia.ttf")?;
     
     let collect_animation = Sprite {
         frames: Arc::new(vec![
@@ -705,25 +1128,58 @@ pub fn main() -> GameResult {
             graphics::Image::new(&mut ctx, "/collect5.png")?,
         ])
     };
-    // let bgm = Source::new(&mut ctx, "/c.mp3")?;
+    let bgm = match Source::new(&mut ctx, "/halloween.mp3") {
+        Ok(mut bgm) => {
+            bgm.set_repeat(true);
+            bgm.set_volume(settings.audio.music_volume());
+            Some(bgm)
+        }
+        Err(e) => {
+            println!("No music: {}", e);
+            None
+        }
+    };
 
 
-    let lifebar = graphics::Image::new(&mut ctx, "/lifebar.png")?;
-    let lifebar_bg = graphics::Image::new(&mut ctx, "/lifebar_bg.png")?;
 
-    let assets: Assets = Assets {
+    let cat_sheet = sprite::SpriteSheet::new(
+        graphics::Image::new(&mut ctx, "/cat.png")?, 4, 1
+    );
+
+    let (bat, ghost, pumpkin) = assets::hazard_meshes(&mut ctx)?;
+
+    let mut assets: Assets = Assets {
         player,
-        bg,
         candy,
-        font,
         collect_animation,
-        // bgm,
-        lifebar,
-        lifebar_bg,
-        collect_sheet
+        bgm,
+        collect_sheet,
+        cat_sheet,
+        bat,
+        ghost,
+        pumpkin,
+        sfx: Sfx::load(),
     };
 
-    // let _ = assets.bgm.play(&mut ctx)?;
-    let game = MainState::new(&mut ctx, assets, (test_map, grass_tiles))?;
+    if let Some(bgm) = &mut assets.bgm {
+        if render.is_none() {
+            bgm.play(&ctx)?;
+        }
+    }
+    let mut game = MainState::new(&mut ctx, assets, settings, resource_dir, stages)?;
+    // `--load <file>` opens straight into a saved run, e.g. one sent in with a bug.
+    if let Some(path) = arg("--load")? {
+        game.load_run(&mut ctx, save::read(path::Path::new(path))?)?;
+    }
+    // `--render <replay>` plays it back and writes every frame to `--out <folder>`
+    // (`frames`) as PNGs, or as raw RGBA into `--pipe <command>`.
+    if let Some(path) = render {
+        let replay = Replay::read(path::Path::new(path))?;
+        let dump = match (arg("--pipe")?, arg("--out")?) {
+            (Some(command), _) => FrameDump::pipe(command)?,
+            (None, out) => FrameDump::folder(path::Path::new(out.map_or("frames", |o| o.as_str())))?,
+        };
+        game.start_render(&mut ctx, replay, dump)?;
+    }
     event::run(ctx, events_loop, game)
 }
\ No newline at end of file
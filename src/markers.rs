@@ -0,0 +1,58 @@
+//! The landing assist: a mark on the floor where each candy will come down,
+//! or a shadow that grows and darkens as it gets close. Off unless it's
+//! turned on in the accessibility settings.
+
+use ggez::graphics::{self, Color, DrawMode, DrawParam, MeshBuilder};
+use ggez::{Context, GameResult};
+use glam::*;
+
+use crate::ecs::World;
+use crate::settings::LandingMarkers;
+use crate::trajectory::{self, Ground};
+use crate::tuning::Tuning;
+use crate::{world_to_screen_coords, SCREEN_SIZE};
+
+const MARKER: Color = Color::new(1.0, 0.65, 0.2, 0.9);
+/// Shadows start showing this many seconds before the candy lands...
+const SHADOW_LEAD: f32 = 1.5;
+/// ...this wide, and grow to `SHADOW_WIDTH` as it comes down.
+const SHADOW_START: f32 = 3.0;
+const SHADOW_WIDTH: f32 = 12.0;
+
+pub fn draw(ctx: &mut Context, world: &World, tuning: &Tuning, ground: &Ground, style: LandingMarkers) -> GameResult {
+    if style == LandingMarkers::Off {
+        return Ok(());
+    }
+    let (w, h) = SCREEN_SIZE;
+    let mut mb = MeshBuilder::new();
+    let mut any = false;
+    for (e, _) in world.catchables.iter() {
+        let (Some(transform), Some(body)) = (world.transforms.get(e), world.bodies.get(e)) else { continue };
+        let gravity = tuning.gravity * body.gravity_scale;
+        let Some(landing) = trajectory::landing(transform.pos, body.velocity, gravity, ground) else { continue };
+        let mut at = world_to_screen_coords(w, h, landing.point);
+        at.x = at.x.clamp(0.0, w);
+        match style {
+            LandingMarkers::Off => (),
+            LandingMarkers::Markers => {
+                mb.ellipse(DrawMode::stroke(2.0), at, 8.0, 3.0, 0.5, MARKER)?;
+                mb.line(&[at - vec2(0.0, 10.0), at - vec2(0.0, 4.0)], 2.0, MARKER)?;
+            }
+            LandingMarkers::Shadows => {
+                let near = 1.0 - (landing.time / SHADOW_LEAD).min(1.0);
+                if near <= 0.0 {
+                    continue;
+                }
+                let width = SHADOW_START + (SHADOW_WIDTH - SHADOW_START) * near;
+                mb.ellipse(DrawMode::fill(), at, width, width * 0.3, 0.5, Color::new(0.0, 0.0, 0.0, 0.6 * near))?;
+            }
+        }
+        any = true;
+    }
+    // Building an empty mesh is an error.
+    if any {
+        let mesh = mb.build(ctx)?;
+        graphics::draw(ctx, &mesh, DrawParam::new())?;
+    }
+    Ok(())
+}
@@ -0,0 +1,397 @@
+//! The widgets every screen is built from: a titled list of buttons, toggles,
+//! sliders, choices and text boxes, driven by `MenuInput` so keys and pads
+//! work the same. Screens own a `Menu` and react to the `MenuEvent`s it hands back.
+
+use ggez::event::{Button, KeyCode};
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Rect};
+use ggez::{Context, GameResult};
+use glam::*;
+
+use crate::locale::Locale;
+use crate::settings::{Accessibility, Action, Controls};
+use crate::SCREEN_SIZE;
+
+/// Seconds a menu takes to fade and slide in.
+const OPEN_TIME: f32 = 0.2;
+/// How far it slides in from, in pixels.
+const SLIDE: f32 = 24.0;
+/// Rows on screen at once; longer menus scroll.
+const VISIBLE: usize = 16;
+const ROW_HEIGHT: f32 = 22.0;
+/// Where the value column starts.
+const VALUE_X: f32 = 330.0;
+const SLIDER_WIDTH: f32 = 120.0;
+
+const SELECTED: Color = Color::new(1.0, 0.8, 0.2, 1.0);
+const DISABLED: Color = Color::new(0.5, 0.5, 0.5, 1.0);
+
+/// Everything a menu responds to. Keys and gamepad buttons are turned into these.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MenuInput {
+    Up,
+    Down,
+    Left,
+    Right,
+    Confirm,
+    Back,
+    /// Typed into a text box.
+    Char(char),
+    Erase,
+}
+
+impl MenuInput {
+    /// Menus go by the gameplay bindings, with jump as up.
+    pub fn from_action(action: Action) -> Option<MenuInput> {
+        match action {
+            Action::Left => Some(MenuInput::Left),
+            Action::Right => Some(MenuInput::Right),
+            Action::Jump => Some(MenuInput::Up),
+            Action::Down => Some(MenuInput::Down),
+            Action::Confirm => Some(MenuInput::Confirm),
+            Action::Quit => Some(MenuInput::Back),
+            _ => None,
+        }
+    }
+
+    /// While typing, letters go to the text box instead, so only a few keys count.
+    pub fn from_key(controls: &Controls, keycode: KeyCode, typing: bool) -> Option<MenuInput> {
+        if typing {
+            return match keycode {
+                KeyCode::Up => Some(MenuInput::Up),
+                KeyCode::Down => Some(MenuInput::Down),
+                KeyCode::Return => Some(MenuInput::Confirm),
+                KeyCode::Escape => Some(MenuInput::Back),
+                KeyCode::Back => Some(MenuInput::Erase),
+                _ => None,
+            };
+        }
+        controls.action_for(keycode).and_then(MenuInput::from_action)
+    }
+
+    /// Pads aren't rebindable, they use the usual layout.
+    pub fn from_button(button: Button) -> Option<MenuInput> {
+        match button {
+            Button::DPadUp => Some(MenuInput::Up),
+            Button::DPadDown => Some(MenuInput::Down),
+            Button::DPadLeft => Some(MenuInput::Left),
+            Button::DPadRight => Some(MenuInput::Right),
+            Button::South | Button::Start => Some(MenuInput::Confirm),
+            Button::East => Some(MenuInput::Back),
+            Button::West => Some(MenuInput::Erase),
+            _ => None,
+        }
+    }
+}
+
+/// For the game to play, if it has a sound for it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuSound {
+    Move,
+    Change,
+    Confirm,
+    Back,
+    /// Pressed something that's disabled.
+    Denied,
+}
+
+#[derive(Debug, Clone)]
+pub enum Control {
+    /// Just text, can't be selected.
+    Info,
+    Button,
+    Toggle(bool),
+    Slider { value: f32, min: f32, max: f32, step: f32 },
+    Choice { options: Vec<String>, index: usize },
+    Text { value: String, max_len: usize },
+}
+
+#[derive(Debug, Clone)]
+pub struct Item<T> {
+    pub id: T,
+    pub label: String,
+    /// Shown in the value column, for buttons and info.
+    pub detail: String,
+    pub control: Control,
+    pub enabled: bool,
+}
+
+impl<T> Item<T> {
+    fn new(id: T, label: impl Into<String>, control: Control) -> Item<T> {
+        Item { id, label: label.into(), detail: String::new(), control, enabled: true }
+    }
+
+    pub fn info(id: T, label: impl Into<String>) -> Item<T> {
+        Item::new(id, label, Control::Info)
+    }
+
+    pub fn button(id: T, label: impl Into<String>) -> Item<T> {
+        Item::new(id, label, Control::Button)
+    }
+
+    pub fn toggle(id: T, label: impl Into<String>, on: bool) -> Item<T> {
+        Item::new(id, label, Control::Toggle(on))
+    }
+
+    pub fn slider(id: T, label: impl Into<String>, value: f32, min: f32, max: f32, step: f32) -> Item<T> {
+        Item::new(id, label, Control::Slider { value, min, max, step })
+    }
+
+    pub fn choice(id: T, label: impl Into<String>, options: Vec<String>, index: usize) -> Item<T> {
+        Item::new(id, label, Control::Choice { options, index })
+    }
+
+    pub fn text(id: T, label: impl Into<String>, value: impl Into<String>, max_len: usize) -> Item<T> {
+        Item::new(id, label, Control::Text { value: value.into(), max_len })
+    }
+
+    pub fn detail(mut self, detail: impl Into<String>) -> Item<T> {
+        self.detail = detail.into();
+        self
+    }
+
+    pub fn enabled(mut self, enabled: bool) -> Item<T> {
+        self.enabled = enabled;
+        self
+    }
+
+    /// What's typed in a text box, empty for anything else.
+    pub fn text_value(&self) -> &str {
+        match &self.control {
+            Control::Text { value, .. } => value,
+            _ => "",
+        }
+    }
+
+    fn focusable(&self) -> bool {
+        !matches!(self.control, Control::Info)
+    }
+
+    /// What's in the value column, apart from sliders which are drawn.
+    fn value_text(&self, selected: bool, blink: bool, locale: &Locale) -> String {
+        match &self.control {
+            Control::Info | Control::Button | Control::Slider { .. } => self.detail.clone(),
+            Control::Toggle(on) => locale.t(if *on { "menu.on" } else { "menu.off" }).to_string(),
+            Control::Choice { options, index } => format!("< {} >", options.get(*index).map_or("", |s| s.as_str())),
+            Control::Text { value, .. } if selected && blink => format!("{}_", value),
+            Control::Text { value, .. } => value.clone(),
+        }
+    }
+}
+
+/// What happened, for the screen to act on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuEvent<T> {
+    None,
+    /// A button, or enter in a text box.
+    Pressed(T),
+    /// A toggle, slider, choice or text box has a new value.
+    Changed(T),
+    Back,
+}
+
+pub struct Menu<T> {
+    title: String,
+    items: Vec<Item<T>>,
+    selected: usize,
+    /// Shown under the items, e.g. how the last stage went.
+    pub message: String,
+    /// Empty for the usual one.
+    hint: String,
+    /// Seconds since it opened, for the transition.
+    age: f32,
+    sound: Option<MenuSound>,
+}
+
+impl<T: Copy + PartialEq> Menu<T> {
+    pub fn new(title: impl Into<String>, items: Vec<Item<T>>) -> Menu<T> {
+        let mut menu = Menu {
+            title: title.into(),
+            items: Vec::new(),
+            selected: 0,
+            message: String::new(),
+            hint: String::new(),
+            age: 0.0,
+            sound: None,
+        };
+        menu.set_items(items);
+        menu
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Menu<T> {
+        self.hint = hint.into();
+        self
+    }
+
+    /// Swaps in new items, e.g. with updated values, keeping the same row selected.
+    pub fn set_items(&mut self, items: Vec<Item<T>>) {
+        self.items = items;
+        self.selected = self.selected.min(self.items.len().saturating_sub(1));
+        if !self.items.get(self.selected).is_some_and(Item::focusable) {
+            self.step(1);
+        }
+    }
+
+    pub fn select(&mut self, id: T) {
+        if let Some(i) = self.items.iter().position(|item| item.id == id && item.focusable()) {
+            self.selected = i;
+        }
+    }
+
+    pub fn selected(&self) -> Option<T> {
+        self.items.get(self.selected).map(|item| item.id)
+    }
+
+    pub fn item(&self, id: T) -> Option<&Item<T>> {
+        self.items.iter().find(|item| item.id == id)
+    }
+
+    /// Letters should go to the text box rather than be read as controls.
+    pub fn is_typing(&self) -> bool {
+        matches!(self.items.get(self.selected).map(|item| &item.control), Some(Control::Text { .. }))
+    }
+
+    /// The sound for the last input, if there should be one.
+    pub fn take_sound(&mut self) -> Option<MenuSound> {
+        self.sound.take()
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.age += dt;
+    }
+
+    /// Moves to the next focusable item that way, wrapping round.
+    fn step(&mut self, dir: i32) {
+        let n = self.items.len() as i32;
+        for k in 1..=n {
+            let i = (self.selected as i32 + dir * k).rem_euclid(n) as usize;
+            if self.items[i].focusable() {
+                self.selected = i;
+                return;
+            }
+        }
+    }
+
+    pub fn input(&mut self, input: MenuInput) -> MenuEvent<T> {
+        if input == MenuInput::Back {
+            self.sound = Some(MenuSound::Back);
+            return MenuEvent::Back;
+        }
+        if matches!(input, MenuInput::Up | MenuInput::Down) {
+            let before = self.selected;
+            self.step(if input == MenuInput::Up { -1 } else { 1 });
+            if self.selected != before {
+                self.sound = Some(MenuSound::Move);
+            }
+            return MenuEvent::None;
+        }
+
+        let Some(item) = self.items.get_mut(self.selected) else { return MenuEvent::None };
+        if !item.focusable() {
+            return MenuEvent::None;
+        }
+        let id = item.id;
+        if !item.enabled {
+            if input == MenuInput::Confirm {
+                self.sound = Some(MenuSound::Denied);
+            }
+            return MenuEvent::None;
+        }
+        let (event, sound) = match (&mut item.control, input) {
+            (Control::Button, MenuInput::Confirm) => (MenuEvent::Pressed(id), MenuSound::Confirm),
+            (Control::Toggle(on), MenuInput::Confirm | MenuInput::Left | MenuInput::Right) => {
+                *on = !*on;
+                (MenuEvent::Changed(id), MenuSound::Change)
+            }
+            (Control::Slider { value, min, max, step }, MenuInput::Left | MenuInput::Right) => {
+                let dir = if input == MenuInput::Left { -1.0 } else { 1.0 };
+                // Snapped to the step so repeated nudges don't drift.
+                let next = (((*value + dir * *step) / *step).round() * *step).clamp(*min, *max);
+                if next == *value {
+                    return MenuEvent::None;
+                }
+                *value = next;
+                (MenuEvent::Changed(id), MenuSound::Change)
+            }
+            (Control::Choice { options, index }, MenuInput::Confirm | MenuInput::Left | MenuInput::Right) => {
+                let dir = if input == MenuInput::Left { -1 } else { 1 };
+                *index = (*index as i32 + dir).rem_euclid(options.len().max(1) as i32) as usize;
+                (MenuEvent::Changed(id), MenuSound::Change)
+            }
+            (Control::Text { value, max_len }, MenuInput::Char(c)) => {
+                if value.chars().count() >= *max_len || c.is_control() {
+                    return MenuEvent::None;
+                }
+                value.push(c);
+                (MenuEvent::Changed(id), MenuSound::Change)
+            }
+            (Control::Text { value, .. }, MenuInput::Erase) => {
+                if value.pop().is_none() {
+                    return MenuEvent::None;
+                }
+                (MenuEvent::Changed(id), MenuSound::Change)
+            }
+            (Control::Text { .. }, MenuInput::Confirm) => (MenuEvent::Pressed(id), MenuSound::Confirm),
+            _ => return MenuEvent::None,
+        };
+        self.sound = Some(sound);
+        event
+    }
+
+    pub fn draw(&self, ctx: &mut Context, locale: &Locale, accessibility: &Accessibility) -> GameResult {
+        let (w, h) = SCREEN_SIZE;
+        let t = (self.age / OPEN_TIME).clamp(0.0, 1.0);
+        let t = t * t * (3.0 - 2.0 * t);
+        let slide = if accessibility.reduce_motion { 0.0 } else { (1.0 - t) * SLIDE };
+        let fade = |c: Color| Color::new(c.r, c.g, c.b, c.a * t);
+
+        let backing_alpha = if accessibility.high_contrast { 0.95 } else { 0.8 };
+        let backing = graphics::Mesh::new_rectangle(
+            ctx, DrawMode::fill(), Rect::new(0.0, 0.0, w, h), Color::new(0.0, 0.0, 0.0, backing_alpha * t))?;
+        graphics::draw(ctx, &backing, DrawParam::new())?;
+
+        let title = locale.text(&self.title, 32.0);
+        graphics::draw(ctx, &title, (vec2(20.0 + slide, 10.0), 0.0, fade(Color::WHITE)))?;
+
+        let first = self.selected.saturating_sub(VISIBLE - 1);
+        let blink = self.age.fract() < 0.5;
+        for (row, (i, item)) in self.items.iter().enumerate().skip(first).take(VISIBLE).enumerate() {
+            let selected = i == self.selected;
+            let color = if selected {
+                SELECTED
+            } else if !item.enabled {
+                DISABLED
+            } else {
+                Color::WHITE
+            };
+            let y = 56.0 + row as f32 * ROW_HEIGHT;
+            let label = locale.text(&item.label, 16.0);
+            graphics::draw(ctx, &label, (vec2(30.0 + slide, y), 0.0, fade(color)))?;
+
+            if let Control::Slider { value, min, max, .. } = item.control {
+                let filled = ((value - min) / (max - min)).clamp(0.0, 1.0);
+                let bar = Rect::new(VALUE_X + slide, y + 5.0, SLIDER_WIDTH, 10.0);
+                let mesh = graphics::MeshBuilder::new()
+                    .rectangle(DrawMode::fill(), Rect { w: bar.w * filled, ..bar }, fade(color))?
+                    .rectangle(DrawMode::stroke(1.0), bar, fade(color))?
+                    .build(ctx)?;
+                graphics::draw(ctx, &mesh, DrawParam::new())?;
+            }
+            let value = item.value_text(selected, blink, locale);
+            if !value.is_empty() {
+                let x = match item.control {
+                    Control::Slider { .. } => VALUE_X + SLIDER_WIDTH + 10.0,
+                    _ => VALUE_X,
+                };
+                let value = locale.text(value, 16.0);
+                graphics::draw(ctx, &value, (vec2(x + slide, y), 0.0, fade(color)))?;
+            }
+        }
+
+        let message = locale.text(&self.message, 16.0);
+        graphics::draw(ctx, &message, (vec2(20.0 + slide, h - 56.0), 0.0, fade(Color::WHITE)))?;
+        let hint = if self.hint.is_empty() { locale.t("menu.hint") } else { &self.hint };
+        let hint = locale.text(hint, 12.0);
+        graphics::draw(ctx, &hint, (vec2(20.0 + slide, h - 24.0), 0.0, fade(Color::WHITE)))?;
+        Ok(())
+    }
+}
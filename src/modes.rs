@@ -0,0 +1,116 @@
+//! Ways to play a stage, from `resources/modes.toml`. They all run the same
+//! game, just with different rules.
+
+use std::path::Path;
+
+use ggez::GameResult;
+use serde::Deserialize;
+
+use crate::campaign::Stage;
+use crate::storage;
+use crate::tuning::Tuning;
+
+pub const MODES_FILE: &str = "modes.toml";
+/// The stages in order, with their goals and unlocks. Also what you get if
+/// there's no modes file.
+pub const CAMPAIGN: &str = "campaign";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Rules {
+    /// Finish on the stage's own goal.
+    pub stage_goal: bool,
+    /// Finish after this many seconds, if there's no stage goal.
+    pub time_limit: Option<f32>,
+    /// Life to start with, 0 for the tuning's.
+    pub life: i32,
+    /// Missed candy costs a life.
+    pub misses_hurt: bool,
+    pub hazards: bool,
+    /// Times the usual candy spawn rate.
+    pub spawn_rate: f32,
+    /// Extra spawn rate per point of difficulty, so it keeps getting busier.
+    pub spawn_ramp: f32,
+    /// Times the usual rate the game speeds up.
+    pub difficulty_rate: f32,
+    /// Clearing a stage counts towards unlocking the next.
+    pub progress: bool,
+    /// Grading policy and HUD layout, instead of the stage's.
+    pub grading: Option<String>,
+    pub hud: Option<String>,
+    /// Play on a random layout the cat really stands on, see `arena`, instead
+    /// of the stage's own.
+    pub random_map: bool,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Rules {
+            stage_goal: false,
+            time_limit: None,
+            life: 0,
+            misses_hurt: true,
+            hazards: true,
+            spawn_rate: 1.0,
+            spawn_ramp: 0.0,
+            difficulty_rate: 1.0,
+            progress: false,
+            grading: None,
+            hud: None,
+            random_map: false,
+        }
+    }
+}
+
+impl Rules {
+    /// Nothing ends it but running out of life, or giving up.
+    pub fn is_open_ended(&self) -> bool {
+        !self.stage_goal && self.time_limit.is_none()
+    }
+
+    pub fn start_life(&self, tuning: &Tuning) -> i32 {
+        match self.life {
+            0 => tuning.player_life,
+            life => life,
+        }
+    }
+
+    /// The grading policy to use on `stage`: the mode's, otherwise the stage's.
+    pub fn policy_name<'a>(&'a self, stage: &'a Stage) -> &'a str {
+        self.grading.as_deref().unwrap_or(&stage.grading)
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Mode {
+    /// Also where its text is: `modes.<id>.name` and `.description`.
+    pub id: String,
+    #[serde(flatten)]
+    pub rules: Rules,
+}
+
+impl Mode {
+    pub fn campaign() -> Mode {
+        Mode { id: CAMPAIGN.to_string(), rules: Rules { stage_goal: true, progress: true, ..Rules::default() } }
+    }
+
+    pub fn name_key(&self) -> String {
+        format!("modes.{}.name", self.id)
+    }
+
+    pub fn description_key(&self) -> String {
+        format!("modes.{}.description", self.id)
+    }
+}
+
+#[derive(Deserialize)]
+struct ModesFile {
+    mode: Vec<Mode>,
+}
+
+/// No file, or one without any, is just the campaign.
+pub fn load_modes(resource_dir: &Path) -> GameResult<Vec<Mode>> {
+    let path = resource_dir.join(MODES_FILE);
+    let modes = storage::read_toml::<ModesFile>(&path)?.map_or(Vec::new(), |file| file.mode);
+    Ok(if modes.is_empty() { vec![Mode::campaign()] } else { modes })
+}
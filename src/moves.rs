@@ -0,0 +1,185 @@
+//! The cat's extra moves: air dash, double jump and ground pound. Like
+//! `jump`, this only knows about velocities so it doesn't need a window.
+
+use glam::*;
+use serde::{Deserialize, Serialize};
+
+use crate::tuning::Tuning;
+
+/// What the cat is doing, for picking an animation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Pose {
+    Idle,
+    Run,
+    Jump,
+    Fall,
+    Dash,
+    Pound,
+}
+
+/// Which move buttons are down this tick.
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct MoveButtons {
+    pub dash: bool,
+    pub down: bool,
+}
+
+#[derive(Debug, Default, Clone, Copy, Serialize, Deserialize)]
+pub struct Moves {
+    /// Seconds left of the current dash.
+    dashing: f32,
+    /// Seconds until dashing is allowed again.
+    dash_cooldown: f32,
+    /// Only one dash per time in the air.
+    air_dash_used: bool,
+    /// Earned by scoring, see `Tuning::double_jump_unlock_score`.
+    pub double_jump_unlocked: bool,
+    double_jump_used: bool,
+    /// Diving at the ground. Landing like this makes a shockwave.
+    pounding: bool,
+    was_dash_held: bool,
+    was_down_held: bool,
+}
+
+impl Moves {
+    pub fn is_dashing(&self) -> bool {
+        self.dashing > 0.0
+    }
+
+    /// Gravity and steering are off during a dash or a pound.
+    pub fn overrides_movement(&self) -> bool {
+        self.is_dashing() || self.pounding
+    }
+
+    pub fn can_double_jump(&self) -> bool {
+        self.double_jump_unlocked && !self.double_jump_used && !self.overrides_movement()
+    }
+
+    pub fn used_double_jump(&mut self) {
+        self.double_jump_used = true;
+    }
+
+    /// Call once per tick with the held buttons. `facing` is -1 or 1.
+    pub fn update(&mut self, tuning: &Tuning, held: MoveButtons, grounded: bool, facing: f32, velocity: &mut Vec2, dt: f32) {
+        let dash_pressed = held.dash && !self.was_dash_held;
+        let down_pressed = held.down && !self.was_down_held;
+        self.was_dash_held = held.dash;
+        self.was_down_held = held.down;
+
+        self.dash_cooldown = (self.dash_cooldown - dt).max(0.0);
+        if self.dashing > 0.0 {
+            self.dashing = (self.dashing - dt).max(0.0);
+            if self.dashing == 0.0 {
+                // Come out of it at running speed rather than dash speed.
+                velocity.x = velocity.x.signum() * velocity.x.abs().min(tuning.player_vel);
+            }
+        }
+
+        if grounded {
+            self.air_dash_used = false;
+            self.double_jump_used = false;
+            return;
+        }
+
+        if dash_pressed && !self.pounding && !self.air_dash_used && self.dash_cooldown == 0.0 {
+            self.dashing = tuning.dash_time;
+            self.dash_cooldown = tuning.dash_cooldown;
+            self.air_dash_used = true;
+            *velocity = vec2(facing * tuning.dash_speed, 0.0);
+        }
+
+        if down_pressed && !self.pounding {
+            self.pounding = true;
+            self.dashing = 0.0;
+            *velocity = vec2(0.0, -tuning.pound_speed);
+        }
+    }
+
+    /// Call when the cat touches down. True if it was a ground pound, which
+    /// should send out a shockwave.
+    pub fn land(&mut self) -> bool {
+        let pounded = self.pounding;
+        self.pounding = false;
+        pounded
+    }
+
+    pub fn pose(&self, grounded: bool, velocity: Vec2) -> Pose {
+        if self.pounding {
+            Pose::Pound
+        } else if self.is_dashing() {
+            Pose::Dash
+        } else if !grounded && velocity.y > 0.0 {
+            Pose::Jump
+        } else if !grounded {
+            Pose::Fall
+        } else if velocity.x != 0.0 {
+            Pose::Run
+        } else {
+            Pose::Idle
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DASH: MoveButtons = MoveButtons { dash: true, down: false };
+    const DOWN: MoveButtons = MoveButtons { dash: false, down: true };
+    const NONE: MoveButtons = MoveButtons { dash: false, down: false };
+
+    /// Lets go of everything for long enough that any dash and its cooldown are over.
+    fn wait(moves: &mut Moves, tuning: &Tuning, grounded: bool) {
+        let mut velocity = Vec2::ZERO;
+        moves.update(tuning, NONE, grounded, 1.0, &mut velocity, tuning.dash_time + tuning.dash_cooldown + 1.0);
+    }
+
+    #[test]
+    fn one_dash_per_airtime() {
+        let tuning = Tuning::default();
+        let mut moves = Moves::default();
+        let mut velocity = Vec2::ZERO;
+        moves.update(&tuning, DASH, false, 1.0, &mut velocity, 0.0);
+        assert!(moves.is_dashing());
+        assert_eq!(velocity, vec2(tuning.dash_speed, 0.0));
+
+        wait(&mut moves, &tuning, false);
+        assert!(!moves.is_dashing());
+        moves.update(&tuning, DASH, false, 1.0, &mut velocity, 0.0);
+        assert!(!moves.is_dashing(), "second dash before landing");
+
+        wait(&mut moves, &tuning, true);
+        moves.update(&tuning, DASH, false, -1.0, &mut velocity, 0.0);
+        assert!(moves.is_dashing(), "landing gives the dash back");
+        assert_eq!(velocity, vec2(-tuning.dash_speed, 0.0));
+    }
+
+    #[test]
+    fn double_jump_needs_unlocking_and_comes_back_on_landing() {
+        let tuning = Tuning::default();
+        let mut moves = Moves::default();
+        assert!(!moves.can_double_jump());
+
+        moves.double_jump_unlocked = true;
+        assert!(moves.can_double_jump());
+        moves.used_double_jump();
+        assert!(!moves.can_double_jump());
+
+        wait(&mut moves, &tuning, true);
+        assert!(moves.can_double_jump());
+    }
+
+    #[test]
+    fn only_a_pound_lands_with_a_shockwave() {
+        let tuning = Tuning::default();
+        let mut moves = Moves::default();
+        assert!(!moves.land());
+
+        let mut velocity = vec2(100.0, 50.0);
+        moves.update(&tuning, DOWN, false, 1.0, &mut velocity, 0.0);
+        assert_eq!(velocity, vec2(0.0, -tuning.pound_speed));
+        assert_eq!(moves.pose(false, velocity), Pose::Pound);
+        assert!(moves.land());
+        assert!(!moves.land());
+    }
+}
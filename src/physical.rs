@@ -1,8 +0,0 @@
-use glam::*;
-
-use super::sprite;
-
-pub struct Physical {
-    sprite: sprite::Sprite,
-    vel: Vec2
-}
\ No newline at end of file
@@ -0,0 +1,210 @@
+use glam::*;
+use serde::{Deserialize, Serialize};
+
+use crate::ecs::{Body, SpriteRef, Transform};
+use crate::moves::{self, Pose};
+use crate::sprite::Clip;
+use crate::terrain::Terrain;
+use crate::tuning::Tuning;
+use crate::{jump, ControllerState};
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub enum LR {
+    Left,
+    Right
+}
+
+impl LR {
+    pub fn to_f32(self) -> f32 {
+        match self {
+            LR::Left => -1.0,
+            LR::Right => 1.0
+        }
+    }
+}
+
+/// The cat. Its position, velocity, collider and sprite are the usual components.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct Player {
+    pub last_velocity: Vec2,
+    pub facing: LR,
+    pub animation_frame: f32,
+    pub life: i32,
+    pub grounded: bool,
+    pub jump: jump::JumpState,
+    pub moves: moves::Moves,
+    pub pose: Pose,
+    /// Seconds spent in `pose`, for timing its animation.
+    pub pose_time: f32,
+    /// Seconds left that hazards can't hurt the cat.
+    pub hurt: f32,
+    /// Seconds left that the cat ignores the controls.
+    pub stunned: f32,
+}
+
+impl Player {
+    pub fn new(life: i32) -> Player {
+        Player {
+            last_velocity: Vec2::new(0.0, 0.0),
+            facing: LR::Right,
+            animation_frame: 0.0,
+            life,
+            grounded: true,
+            jump: jump::JumpState::default(),
+            moves: moves::Moves::default(),
+            pose: Pose::Idle,
+            pose_time: 0.0,
+            hurt: 0.0,
+            stunned: 0.0,
+        }
+    }
+
+    /// Takes `damage` and is stunned for `stun` seconds, unless it was hurt
+    /// too recently. Returns whether it was.
+    pub fn take_hit(&mut self, damage: i32, stun: f32, tuning: &Tuning) -> bool {
+        if self.hurt > 0.0 {
+            return false;
+        }
+        self.life -= damage;
+        self.hurt = tuning.hurt_time;
+        self.stunned = self.stunned.max(stun);
+        true
+    }
+
+    fn frame(&self) -> usize {
+        match self.pose {
+            Pose::Run => self.animation_frame.floor() as usize,
+            pose => cat_clip(pose).frame(self.pose_time),
+        }
+    }
+}
+
+/// Frames from cat.png for each pose. Running steps with speed instead, see
+/// `player_handle_input`.
+fn cat_clip(pose: Pose) -> &'static Clip {
+    const IDLE: Clip = Clip { frames: &[0], fps: 0.0, scale: Vec2::new(1.0, 1.0) };
+    const JUMP: Clip = Clip { frames: &[1], fps: 0.0, scale: Vec2::new(0.9, 1.1) };
+    const FALL: Clip = Clip { frames: &[3], fps: 0.0, scale: Vec2::new(1.0, 1.0) };
+    const DASH: Clip = Clip { frames: &[1, 2], fps: 20.0, scale: Vec2::new(1.3, 0.8) };
+    const POUND: Clip = Clip { frames: &[0, 2], fps: 15.0, scale: Vec2::new(0.8, 1.25) };
+    match pose {
+        Pose::Idle | Pose::Run => &IDLE,
+        Pose::Jump => &JUMP,
+        Pose::Fall => &FALL,
+        Pose::Dash => &DASH,
+        Pose::Pound => &POUND,
+    }
+}
+
+/// Returns true if the cat jumped this tick, off the ground or in the air.
+pub fn player_handle_input(actor: &mut Player, body: &mut Body, input: &ControllerState, tuning: &Tuning, dt: f32) -> bool {
+    actor.hurt = (actor.hurt - dt).max(0.0);
+    actor.stunned = (actor.stunned - dt).max(0.0);
+    let no_input = ControllerState::default();
+    let input = if actor.stunned > 0.0 { &no_input } else { input };
+
+    let (facing, target_vel) = if input.left {
+        (LR::Left, -1.0 )
+    } else if input.right { (LR::Right, 1.0) } else { (actor.facing, 0.0) };
+
+    if body.velocity.x != 0.0 {
+        if actor.last_velocity.x == 0.0 {
+            actor.animation_frame += 0.99;
+        }
+        actor.animation_frame = (actor.animation_frame + 0.2 * body.velocity.x.abs() / tuning.player_vel) % 4.0;
+    }
+    else {
+        actor.animation_frame = 0.0;
+    }
+
+    actor.facing = facing;
+    let target_vel = target_vel * tuning.player_vel;
+    let thrust_sign =
+        if body.velocity.x < target_vel {
+            1.0
+        } else if body.velocity.x > target_vel {
+            -1.0
+        } else {
+            0.0
+        };
+
+    let thrust =
+        if (target_vel == 0.0 && actor.grounded) || body.velocity.x.signum() != target_vel.signum(){
+            tuning.player_break_thrust * dt * thrust_sign
+        } else{
+            tuning.player_thrust * dt * thrust_sign
+        };
+
+
+    actor.last_velocity = body.velocity;
+    if !actor.moves.overrides_movement() {
+        body.velocity.x = if (body.velocity.x - target_vel).abs() <= thrust {
+            target_vel
+        } else {
+            body.velocity.x + thrust
+        };
+    }
+
+    let mut jumped = false;
+    if actor.jump.update(tuning, input.up, actor.grounded, &mut body.velocity.y, dt) {
+        actor.grounded = false;
+        jumped = true;
+    } else if !actor.grounded && actor.moves.can_double_jump()
+        && actor.jump.air_jump(tuning, &mut body.velocity.y) {
+        actor.moves.used_double_jump();
+        jumped = true;
+    }
+
+    let held = moves::MoveButtons { dash: input.dash, down: input.down };
+    actor.moves.update(tuning, held, actor.grounded, facing.to_f32(), &mut body.velocity, dt);
+
+    let pose = actor.moves.pose(actor.grounded, body.velocity);
+    if pose != actor.pose {
+        actor.pose = pose;
+        actor.pose_time = 0.0;
+    }
+    actor.pose_time += dt;
+    jumped
+}
+
+/// Half the cat's width, for walking into the side of the ground.
+const HALF_WIDTH: f32 = 10.0;
+
+/// Returns true if the cat just landed a ground pound.
+pub fn update_player_position(actor: &mut Player, transform: &mut Transform, body: &mut Body, terrain: &Terrain, tuning: &Tuning, dt: f32) -> bool {
+    let before = transform.pos;
+    let dv = body.velocity * dt;
+    transform.pos += dv;
+    if dv.x != 0.0 && terrain.blocks(transform.pos.x + HALF_WIDTH * dv.x.signum(), before.y) {
+        transform.pos.x = before.x;
+        body.velocity.x = 0.0;
+    }
+    let mut pounded = false;
+    if actor.moves.overrides_movement() {
+        // Dashes go straight, pounds go straight down.
+    } else if !actor.grounded {
+        body.velocity.y = actor.jump.apply_gravity(tuning, body.velocity.y, dt);
+    } else {
+        body.velocity.y = 0.0;
+    }
+    let floor = terrain.floor_below(transform.pos.x, before.y);
+    if transform.pos.y <= floor {
+        if !actor.grounded {
+            pounded = actor.moves.land();
+        }
+        transform.pos.y = floor;
+        actor.grounded = true;
+        body.velocity.x += f32::min(body.velocity.y.abs(), body.velocity.x.abs()) * body.velocity.x.signum() * 0.5;
+        body.velocity.y = 0.0;
+    } else {
+        actor.grounded = false;
+    }
+    pounded
+}
+
+/// Points the cat the way it's going and picks its frame.
+pub fn update_player_sprite(actor: &Player, sprite: &mut SpriteRef) {
+    let scale = cat_clip(actor.pose).scale;
+    sprite.scale = vec2(-actor.facing.to_f32() * scale.x, scale.y);
+    sprite.frame = actor.frame();
+}
@@ -0,0 +1,350 @@
+//! Ranks, and the grading policies that turn how a stage went into one.
+//! Policies are in `resources/grading.toml`; each stage says which it uses.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use ggez::graphics::{self, Color, DrawMode};
+use ggez::{Context, GameResult};
+use glam::*;
+use serde::de::IntoDeserializer;
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::locale::Locale;
+use crate::storage;
+
+pub const GRADING_FILE: &str = "grading.toml";
+pub const DEFAULT_POLICY: &str = "default";
+
+/// Worst first, so they compare the way you'd expect. `None` is for a stage
+/// where nothing has happened yet.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Rank {
+    #[default]
+    #[serde(rename = "-")]
+    None,
+    F,
+    D,
+    C,
+    B,
+    A,
+    S,
+    SS,
+    #[serde(rename = "SS+")]
+    SSPlus,
+}
+
+impl Rank {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Rank::None => "-",
+            Rank::F => "F",
+            Rank::D => "D",
+            Rank::C => "C",
+            Rank::B => "B",
+            Rank::A => "A",
+            Rank::S => "S",
+            Rank::SS => "SS",
+            Rank::SSPlus => "SS+",
+        }
+    }
+
+    pub fn colour(self) -> Color {
+        match self {
+            Rank::None => Color::new(0.6, 0.6, 0.6, 1.0),
+            Rank::F => Color::new(0.6, 0.2, 0.2, 1.0),
+            Rank::D => Color::new(0.8, 0.4, 0.2, 1.0),
+            Rank::C => Color::new(0.9, 0.7, 0.3, 1.0),
+            Rank::B => Color::new(0.5, 0.8, 0.3, 1.0),
+            Rank::A => Color::new(0.3, 0.7, 1.0, 1.0),
+            Rank::S => Color::new(0.7, 0.4, 1.0, 1.0),
+            Rank::SS => Color::new(1.0, 0.6, 0.1, 1.0),
+            Rank::SSPlus => Color::new(1.0, 0.9, 0.3, 1.0),
+        }
+    }
+
+    /// Its icon: a disc in its colour with the letters on, `radius` around
+    /// `centre` in screen pixels.
+    pub fn draw_badge(self, ctx: &mut Context, locale: &Locale, centre: Vec2, radius: f32) -> GameResult {
+        let colour = self.colour();
+        let dark = Color::new(colour.r * 0.3, colour.g * 0.3, colour.b * 0.3, 0.9);
+        let badge = graphics::MeshBuilder::new()
+            .circle(DrawMode::fill(), centre, radius, 0.5, dark)?
+            .circle(DrawMode::stroke(radius * 0.15), centre, radius, 0.5, colour)?
+            .build(ctx)?;
+        graphics::draw(ctx, &badge, graphics::DrawParam::new())?;
+        let size = if self.as_str().len() > 1 { radius * 0.8 } else { radius * 1.2 };
+        let text = locale.text(self.as_str(), size);
+        let at = centre - vec2(text.width(ctx), text.height(ctx)) / 2.0;
+        graphics::draw(ctx, &text, (at, 0.0, colour))
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// The things a policy can weigh up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Criterion {
+    Accuracy,
+    Combo,
+    Time,
+    Damage,
+}
+
+impl Criterion {
+    pub const ALL: [Criterion; 4] = [Criterion::Accuracy, Criterion::Combo, Criterion::Time, Criterion::Damage];
+
+    pub fn text_key(self) -> &'static str {
+        match self {
+            Criterion::Accuracy => "results.accuracy",
+            Criterion::Combo => "results.combo",
+            Criterion::Time => "results.time",
+            Criterion::Damage => "results.damage",
+        }
+    }
+}
+
+/// How a stage went, so far or at the end.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Performance {
+    pub caught: u32,
+    pub missed: u32,
+    pub max_combo: u32,
+    /// Seconds survived.
+    pub time: f32,
+    /// Life lost to hazards.
+    pub damage: i32,
+    pub max_life: i32,
+}
+
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(default)]
+pub struct Weights {
+    pub accuracy: f32,
+    pub combo: f32,
+    pub time: f32,
+    pub damage: f32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Policy {
+    pub weights: Weights,
+    /// Combo that counts as full marks.
+    pub combo_target: u32,
+    /// Seconds survived that count as full marks.
+    pub time_target: f32,
+    /// Points out of 100 needed for each rank. F is always the floor.
+    #[serde(deserialize_with = "rank_keys")]
+    pub ranks: BTreeMap<Rank, f32>,
+}
+
+/// toml only has string keys, so they're read as strings and then as ranks.
+fn rank_keys<'de, D: Deserializer<'de>>(deserializer: D) -> Result<BTreeMap<Rank, f32>, D::Error> {
+    BTreeMap::<String, f32>::deserialize(deserializer)?
+        .into_iter()
+        .map(|(key, points)| {
+            let rank = Rank::deserialize(key.as_str().into_deserializer())?;
+            Ok((rank, points))
+        })
+        .collect()
+}
+
+impl Default for Policy {
+    fn default() -> Self {
+        Policy {
+            weights: Weights { accuracy: 3.0, combo: 2.0, time: 0.0, damage: 2.0 },
+            combo_target: 20,
+            time_target: 60.0,
+            ranks: [(Rank::D, 35.0), (Rank::C, 50.0), (Rank::B, 62.0), (Rank::A, 74.0),
+                    (Rank::S, 85.0), (Rank::SS, 93.0), (Rank::SSPlus, 98.0)].into_iter().collect(),
+        }
+    }
+}
+
+/// One criterion's share of a grade.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Part {
+    pub criterion: Criterion,
+    /// How well it went, 0 to 1.
+    pub value: f32,
+    pub weight: f32,
+    /// What it added to the total out of 100.
+    pub points: f32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Grade {
+    pub rank: Rank,
+    /// Out of 100.
+    pub points: f32,
+    /// Only the criteria the policy weighs.
+    pub parts: Vec<Part>,
+    /// The next rank up and the points it needs, if there is one.
+    pub next: Option<(Rank, f32)>,
+}
+
+impl Policy {
+    fn weight(&self, criterion: Criterion) -> f32 {
+        let w = &self.weights;
+        match criterion {
+            Criterion::Accuracy => w.accuracy,
+            Criterion::Combo => w.combo,
+            Criterion::Time => w.time,
+            Criterion::Damage => w.damage,
+        }
+    }
+
+    /// How well `criterion` went, 0 to 1.
+    fn value(&self, criterion: Criterion, perf: &Performance) -> f32 {
+        let ratio = |n: f32, d: f32| if d > 0.0 { (n / d).clamp(0.0, 1.0) } else { 1.0 };
+        match criterion {
+            Criterion::Accuracy => ratio(perf.caught as f32, (perf.caught + perf.missed) as f32),
+            Criterion::Combo => ratio(perf.max_combo as f32, self.combo_target as f32),
+            Criterion::Time => ratio(perf.time, self.time_target),
+            Criterion::Damage => 1.0 - ratio(perf.damage as f32, perf.max_life as f32),
+        }
+    }
+
+    /// The best rank `points` is enough for. A hair under counts, so float
+    /// rounding can't cost a rank that was earned exactly.
+    pub fn rank_for(&self, points: f32) -> Rank {
+        self.ranks.iter()
+            .filter(|&(_, &needed)| points + 1e-3 >= needed)
+            .map(|(&rank, _)| rank)
+            .max()
+            .unwrap_or(Rank::F)
+            .max(Rank::F)
+    }
+
+    pub fn grade(&self, perf: &Performance) -> Grade {
+        let total_weight: f32 = Criterion::ALL.iter().map(|&c| self.weight(c).max(0.0)).sum();
+        let parts = Criterion::ALL.iter()
+            .filter(|&&c| self.weight(c) > 0.0)
+            .map(|&criterion| {
+                let (value, weight) = (self.value(criterion, perf), self.weight(criterion));
+                Part { criterion, value, weight, points: value * weight / total_weight * 100.0 }
+            })
+            .collect::<Vec<_>>();
+        let points = parts.iter().map(|p| p.points).sum::<f32>();
+
+        let started = perf.caught + perf.missed > 0 || perf.damage > 0;
+        let rank = if started { self.rank_for(points) } else { Rank::None };
+        let next = self.ranks.iter()
+            .filter(|&(&r, _)| r > rank.max(Rank::F))
+            .map(|(&r, &needed)| (r, needed))
+            .min_by_key(|&(r, _)| r);
+        Grade { rank, points, parts, next }
+    }
+}
+
+#[derive(Deserialize)]
+struct GradingFile {
+    policy: BTreeMap<String, Policy>,
+}
+
+/// Every policy by name. There's always a `default`.
+pub struct Grading {
+    policies: BTreeMap<String, Policy>,
+}
+
+impl Grading {
+    /// No file just means the built-in default, but a broken one is an error.
+    pub fn load(resource_dir: &Path) -> GameResult<Grading> {
+        let path = resource_dir.join(GRADING_FILE);
+        let mut policies = storage::read_toml::<GradingFile>(&path)?.map_or(BTreeMap::new(), |file| file.policy);
+        policies.entry(DEFAULT_POLICY.to_string()).or_default();
+        Ok(Grading { policies })
+    }
+
+    pub fn has(&self, name: &str) -> bool {
+        self.policies.contains_key(name)
+    }
+
+    /// Falls back to the default for names it doesn't know.
+    pub fn get(&self, name: &str) -> &Policy {
+        self.policies.get(name).unwrap_or(&self.policies[DEFAULT_POLICY])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Only accuracy counts, so the points are easy to steer.
+    fn accuracy_only() -> Policy {
+        Policy { weights: Weights { accuracy: 1.0, ..Weights::default() }, ..Policy::default() }
+    }
+
+    fn caught_of_100(caught: u32) -> Performance {
+        Performance { caught, missed: 100 - caught, max_life: 10, ..Performance::default() }
+    }
+
+    #[test]
+    fn nothing_happened_is_no_rank() {
+        let grade = Policy::default().grade(&Performance { max_life: 10, ..Performance::default() });
+        assert_eq!(grade.rank, Rank::None);
+    }
+
+    #[test]
+    fn one_miss_is_a_rank() {
+        let grade = accuracy_only().grade(&Performance { missed: 1, max_life: 10, ..Performance::default() });
+        assert_eq!(grade.rank, Rank::F);
+        assert_eq!(grade.points, 0.0);
+    }
+
+    #[test]
+    fn ranks_start_exactly_at_their_threshold() {
+        let policy = accuracy_only();
+        for (&rank, &needed) in &policy.ranks {
+            let caught = needed as u32;
+            assert_eq!(policy.grade(&caught_of_100(caught)).rank, rank, "{} points", caught);
+            let below = policy.grade(&caught_of_100(caught - 1)).rank;
+            assert!(below < rank, "{} points gave {}, should be under {}", caught - 1, below, rank);
+        }
+    }
+
+    #[test]
+    fn full_marks_is_the_top_rank() {
+        let grade = accuracy_only().grade(&caught_of_100(100));
+        assert_eq!(grade.rank, Rank::SSPlus);
+        assert_eq!(grade.next, None);
+    }
+
+    #[test]
+    fn next_rank_is_the_one_up() {
+        let grade = accuracy_only().grade(&caught_of_100(60));
+        assert_eq!(grade.rank, Rank::C);
+        assert_eq!(grade.next, Some((Rank::B, 62.0)));
+    }
+
+    #[test]
+    fn weights_split_the_points() {
+        let policy = Policy {
+            weights: Weights { accuracy: 3.0, damage: 1.0, ..Weights::default() },
+            ..Policy::default()
+        };
+        let perf = Performance { caught: 10, missed: 0, damage: 10, max_life: 10, ..Performance::default() };
+        let grade = policy.grade(&perf);
+        assert_eq!(grade.parts.len(), 2);
+        assert!((grade.points - 75.0).abs() < 1e-4);
+        assert_eq!(grade.rank, Rank::A);
+    }
+
+    #[test]
+    fn ranks_are_ordered_and_read_back() {
+        let all = [Rank::None, Rank::F, Rank::D, Rank::C, Rank::B, Rank::A, Rank::S, Rank::SS, Rank::SSPlus];
+        assert!(all.windows(2).all(|w| w[0] < w[1]));
+        for rank in all {
+            let text = toml::to_string(&BTreeMap::from([("rank", rank)])).unwrap();
+            assert!(text.contains(&format!("\"{}\"", rank)));
+            let back: BTreeMap<String, Rank> = toml::from_str(&text).unwrap();
+            assert_eq!(back["rank"], rank);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! A stage as it was played: where it started, the dice, the tuning and the
+//! buttons held each tick. `Run::tick` does the same thing given the same
+//! start and input, so that's all it takes to play it back exactly, e.g. to
+//! render it frame by frame with `--render`.
+
+use std::path::{Path, PathBuf};
+
+use ggez::{GameError, GameResult};
+use oorandom::Rand32;
+use serde::{Deserialize, Serialize};
+
+use crate::tuning::Tuning;
+use crate::{capture, save, storage, ControllerState};
+
+/// In the user dir, next to `screenshots`.
+pub const FOLDER: &str = "replays";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Replay {
+    /// By id and name, like `RunSave`.
+    pub mode: String,
+    pub stage: String,
+    /// The dice as the stage started, see `save::rng_state`.
+    pub rng: (i64, i64),
+    /// Buttons held (see `to_bits`) and for how many ticks in a row.
+    pub input: Vec<(u8, u32)>,
+    /// As it was then, so later changes to `tuning.toml` don't change how it
+    /// plays. Last, because toml wants tables after plain values.
+    pub tuning: Tuning,
+}
+
+fn to_bits(input: &ControllerState) -> u8 {
+    [input.left, input.right, input.up, input.down, input.dash].iter()
+        .enumerate()
+        .map(|(i, &held)| (held as u8) << i)
+        .sum()
+}
+
+fn from_bits(bits: u8) -> ControllerState {
+    let held = |i: u8| bits & (1 << i) != 0;
+    ControllerState { left: held(0), right: held(1), up: held(2), down: held(3), dash: held(4) }
+}
+
+impl Replay {
+    /// Before the stage's first tick.
+    pub fn new(mode: &str, stage: &str, rng: &Rand32, tuning: &Tuning) -> Replay {
+        Replay {
+            mode: mode.to_string(),
+            stage: stage.to_string(),
+            rng: save::rng_state(rng),
+            input: Vec::new(),
+            tuning: tuning.clone(),
+        }
+    }
+
+    pub fn rng(&self) -> Rand32 {
+        save::rng_from_state(self.rng)
+    }
+
+    /// What was held for the next tick.
+    pub fn push(&mut self, input: &ControllerState) {
+        let bits = to_bits(input);
+        match self.input.last_mut() {
+            Some((last, count)) if *last == bits => *count += 1,
+            _ => self.input.push((bits, 1)),
+        }
+    }
+
+    /// What was held each tick, in order.
+    pub fn ticks(&self) -> impl Iterator<Item = ControllerState> + '_ {
+        self.input.iter().flat_map(|&(bits, count)| std::iter::repeat_n(from_bits(bits), count as usize))
+    }
+
+    pub fn len(&self) -> usize {
+        self.input.iter().map(|&(_, count)| count as usize).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.input.is_empty()
+    }
+
+    /// Into the replays folder, named for when it was saved.
+    pub fn save(&self) -> GameResult<PathBuf> {
+        let path = capture::free_path(&storage::user_dir()?.join(FOLDER), "", ".toml");
+        storage::write_toml(&path, self)?;
+        Ok(path)
+    }
+
+    pub fn read(path: &Path) -> GameResult<Replay> {
+        storage::read_toml(path)?
+            .ok_or_else(|| GameError::ResourceNotFound(path.display().to_string(), Vec::new()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::campaign::test_stage;
+    use crate::modes::Mode;
+    use crate::sim::Run;
+    use crate::{bot, sim};
+
+    #[test]
+    fn plays_back_the_same() {
+        let (tuning, rules) = (Tuning::default(), Mode::campaign().rules);
+        let stage = test_stage();
+        let rng = Rand32::new(7);
+        let mut replay = Replay::new("campaign", "Test", &rng, &tuning);
+        let mut run = Run::new(rules.start_life(&tuning), rng, &stage);
+        for _ in 0..sim::TICKS_PER_SECOND * 30 {
+            let input = bot::think(&run, &tuning);
+            replay.push(&input);
+            run.tick(&input, &tuning, &rules, &stage, false);
+        }
+
+        let text = toml::to_string(&replay).unwrap();
+        let replay: Replay = toml::from_str(&text).unwrap();
+        let mut again = Run::new(rules.start_life(&replay.tuning), replay.rng(), &stage);
+        for input in replay.ticks() {
+            again.tick(&input, &replay.tuning, &rules, &stage, false);
+        }
+        assert_eq!(replay.len(), (sim::TICKS_PER_SECOND * 30) as usize);
+        assert_eq!((again.score, again.caught, again.missed), (run.score, run.caught, run.missed));
+        assert_eq!(again.player().life, run.player().life);
+    }
+}
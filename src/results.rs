@@ -0,0 +1,104 @@
+use ggez::{Context, GameResult};
+use glam::*;
+
+use crate::locale::Locale;
+use crate::menu::{Item, Menu, MenuEvent, MenuInput, MenuSound};
+use crate::rank::{Criterion, Grade, Performance, Policy};
+use crate::settings::Accessibility;
+use crate::SCREEN_SIZE;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    Part(usize),
+    Total,
+    Next,
+    Continue,
+}
+
+/// End of stage: what each criterion was worth, the total and the rank it
+/// came to.
+pub struct Results {
+    menu: Menu<Row>,
+    grade: Grade,
+    perf: Performance,
+    policy: Policy,
+}
+
+fn items(grade: &Grade, perf: &Performance, policy: &Policy, locale: &Locale) -> Vec<Item<Row>> {
+    let total_weight = grade.parts.iter().map(|p| p.weight).sum::<f32>();
+    let mut items = grade.parts.iter().enumerate()
+        .map(|(i, part)| {
+            let value = match part.criterion {
+                Criterion::Accuracy => {
+                    let total = perf.caught + perf.missed;
+                    locale.fmt("results.accuracy_value", &[("caught", &perf.caught), ("total", &total)])
+                }
+                Criterion::Combo => {
+                    locale.fmt("results.combo_value", &[("combo", &perf.max_combo), ("target", &policy.combo_target)])
+                }
+                Criterion::Time => {
+                    let (time, target) = (perf.time.floor(), policy.time_target.round());
+                    locale.fmt("results.time_value", &[("time", &time), ("target", &target)])
+                }
+                Criterion::Damage => {
+                    locale.fmt("results.damage_value", &[("damage", &perf.damage), ("max", &perf.max_life)])
+                }
+            };
+            // What it'd have been worth done perfectly.
+            let max = part.weight / total_weight * 100.0;
+            let (points, max) = (format!("{:.1}", part.points), format!("{:.1}", max));
+            Item::info(Row::Part(i), locale.t(part.criterion.text_key()))
+                .detail(locale.fmt("results.part", &[("value", &value), ("points", &points), ("max", &max)]))
+        })
+        .collect::<Vec<_>>();
+
+    let points = format!("{:.1}", grade.points);
+    items.push(Item::info(Row::Total, locale.t("results.total")).detail(locale.fmt("results.total_value", &[("points", &points)])));
+    let next = match grade.next {
+        Some((rank, needed)) => {
+            let more = format!("{:.1}", (needed - grade.points).max(0.1));
+            locale.fmt("results.next", &[("points", &more), ("rank", &rank)])
+        }
+        None => locale.t("results.top").to_string(),
+    };
+    items.push(Item::info(Row::Next, next));
+    items.push(Item::button(Row::Continue, locale.t("results.continue")));
+    items
+}
+
+impl Results {
+    pub fn new(grade: Grade, perf: Performance, policy: Policy, message: String, locale: &Locale) -> Results {
+        let mut results = Results { menu: Menu::new("", Vec::new()), grade, perf, policy };
+        results.relabel(locale);
+        results.menu.message = message;
+        results
+    }
+
+    /// Builds it again in a new language, keeping the message.
+    pub fn relabel(&mut self, locale: &Locale) {
+        let heading = locale.fmt("results.heading", &[("rank", &self.grade.rank)]);
+        let mut menu = Menu::new(heading, items(&self.grade, &self.perf, &self.policy, locale))
+            .with_hint(locale.t("results.hint"));
+        menu.message = std::mem::take(&mut self.menu.message);
+        self.menu = menu;
+    }
+
+    /// True once the player has seen enough.
+    pub fn input(&mut self, input: MenuInput) -> bool {
+        matches!(self.menu.input(input), MenuEvent::Pressed(Row::Continue) | MenuEvent::Back)
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.menu.update(dt);
+    }
+
+    pub fn take_sound(&mut self) -> Option<MenuSound> {
+        self.menu.take_sound()
+    }
+
+    pub fn draw(&self, ctx: &mut Context, locale: &Locale, accessibility: &Accessibility) -> GameResult {
+        self.menu.draw(ctx, locale, accessibility)?;
+        let (w, h) = SCREEN_SIZE;
+        self.grade.rank.draw_badge(ctx, locale, vec2(w - 100.0, h - 160.0), 48.0)
+    }
+}
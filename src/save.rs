@@ -0,0 +1,156 @@
+//! A run put down partway through, to pick up again later. It's everything
+//! the game needs to carry on exactly where it was, so it's also how to hand
+//! someone else a bug: save it, send them the file, and they can load it with
+//! `--load <file>`.
+
+use std::fs;
+use std::path::Path;
+
+use ggez::{GameError, GameResult};
+use oorandom::Rand32;
+use serde::{Deserialize, Serialize};
+
+use crate::camera::Camera;
+use crate::ecs::{Entity, World};
+use crate::sim::Run;
+use crate::storage;
+
+/// In the user dir. Title screen offers to continue while there's one.
+pub const RUN_FILE: &str = "run.toml";
+
+/// Anything not in here (backdrop, HUD counters, toasts) is just for show and
+/// starts over on loading.
+#[derive(Serialize, Deserialize)]
+pub struct RunSave {
+    /// By id and name rather than index, so adding stages doesn't break saves.
+    pub mode: String,
+    pub stage: String,
+    /// Tiles bottom row first, like `Stage::layout`.
+    pub map: Vec<Vec<usize>>,
+    pub score: u32,
+    pub combo: u32,
+    pub caught: u32,
+    pub missed: u32,
+    pub max_combo: u32,
+    pub damage: i32,
+    pub difficulty: f32,
+    pub stage_time: f32,
+    pub is_first_frame: bool,
+    /// `Rand32`'s state. toml only has signed numbers, so these are its bits.
+    pub rng: (i64, i64),
+    pub camera: Camera,
+    pub player: Entity,
+    pub world: World,
+}
+
+impl RunSave {
+    /// Everything about `run`, plus what the game keeps outside it.
+    pub fn new(run: &Run, mode: &str, stage: &str, map: &[Vec<usize>], camera: Camera) -> RunSave {
+        RunSave {
+            mode: mode.to_string(),
+            stage: stage.to_string(),
+            map: map.to_vec(),
+            score: run.score,
+            combo: run.combo,
+            caught: run.caught,
+            missed: run.missed,
+            max_combo: run.max_combo,
+            damage: run.damage,
+            difficulty: run.difficulty,
+            stage_time: run.stage_time,
+            is_first_frame: run.is_first_frame,
+            rng: rng_state(&run.rng),
+            camera,
+            player: run.player,
+            world: run.world.clone(),
+        }
+    }
+
+    /// Puts the saved run back over `run`, which should have just been started
+    /// on the same stage. Take `mode`, `stage`, `map` and `camera` out first.
+    pub fn restore(self, run: &mut Run) {
+        run.score = self.score;
+        run.combo = self.combo;
+        run.caught = self.caught;
+        run.missed = self.missed;
+        run.max_combo = self.max_combo;
+        run.damage = self.damage;
+        run.difficulty = self.difficulty;
+        run.stage_time = self.stage_time;
+        run.is_first_frame = self.is_first_frame;
+        run.rng = rng_from_state(self.rng);
+        run.player = self.player;
+        run.world = self.world;
+    }
+}
+
+pub fn rng_state(rng: &Rand32) -> (i64, i64) {
+    let (state, inc) = rng.state();
+    (state as i64, inc as i64)
+}
+
+pub fn rng_from_state((state, inc): (i64, i64)) -> Rand32 {
+    Rand32::from_state((state as u64, inc as u64))
+}
+
+pub fn exists() -> bool {
+    storage::user_dir().is_ok_and(|dir| dir.join(RUN_FILE).exists())
+}
+
+pub fn save(run: &RunSave) -> GameResult {
+    storage::save(RUN_FILE, run)
+}
+
+/// Takes the saved run, if there is one. It's removed so the same run can't be
+/// played twice from the same point.
+pub fn take() -> GameResult<Option<RunSave>> {
+    let run = storage::load(RUN_FILE)?;
+    if run.is_some() {
+        fs::remove_file(storage::user_dir()?.join(RUN_FILE))?;
+    }
+    Ok(run)
+}
+
+/// One someone sent over. Left where it is, so it can be loaded again.
+pub fn read(path: &Path) -> GameResult<RunSave> {
+    storage::read_toml(path)?.ok_or_else(|| GameError::ResourceNotFound(path.display().to_string(), Vec::new()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot;
+    use crate::campaign::test_stage;
+    use crate::modes::Mode;
+    use crate::sim::TICKS_PER_SECOND;
+    use crate::tuning::Tuning;
+
+    fn positions(run: &Run) -> Vec<(u32, u32)> {
+        run.world.transforms.iter().map(|(_, t)| (t.pos.x.to_bits(), t.pos.y.to_bits())).collect()
+    }
+
+    #[test]
+    fn a_loaded_run_carries_on_the_same() {
+        let (tuning, rules, stage) = (Tuning::default(), Mode::campaign().rules, test_stage());
+        let mut run = Run::new(rules.start_life(&tuning), Rand32::new(5), &stage);
+        let ticks = |run: &mut Run, n| {
+            for _ in 0..n {
+                let input = bot::think(run, &tuning);
+                run.tick(&input, &tuning, &rules, &stage, false);
+            }
+        };
+        ticks(&mut run, TICKS_PER_SECOND * 10);
+
+        let saved = RunSave::new(&run, "campaign", &stage.name, &[], Camera::default());
+        let text = toml::to_string_pretty(&toml::Value::try_from(&saved).unwrap()).unwrap();
+        let saved: RunSave = toml::from_str(&text).unwrap();
+        let mut loaded = Run::new(rules.start_life(&tuning), Rand32::new(0), &stage);
+        saved.restore(&mut loaded);
+
+        ticks(&mut run, TICKS_PER_SECOND * 10);
+        ticks(&mut loaded, TICKS_PER_SECOND * 10);
+        assert_eq!((run.score, run.combo, run.caught, run.missed, run.damage), (loaded.score, loaded.combo, loaded.caught, loaded.missed, loaded.damage));
+        assert_eq!(positions(&run), positions(&loaded));
+        assert_eq!(run.rng.state(), loaded.rng.state());
+    }
+}
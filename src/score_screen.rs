@@ -0,0 +1,116 @@
+use ggez::{Context, GameResult};
+
+use crate::locale::Locale;
+use crate::menu::{Control, Item, Menu, MenuEvent, MenuInput, MenuSound};
+use crate::modes::Mode;
+use crate::scores::{Entry, Leaderboards, Scores};
+use crate::settings::Accessibility;
+
+const NAME_LENGTH: usize = 12;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    Mode,
+    Entry(usize),
+    Name,
+    Save,
+    Back,
+}
+
+/// The high score tables, either just to look at, one mode at a time, or
+/// with a new score waiting for a name.
+pub struct ScoreScreen {
+    menu: Menu<Row>,
+    /// Whose table it is.
+    mode: usize,
+    /// The score being entered, name still to fill in.
+    pending: Option<Entry>,
+}
+
+fn table(scores: &Scores, locale: &Locale) -> Vec<Item<Row>> {
+    if scores.entries.is_empty() {
+        return vec![Item::info(Row::Entry(0), locale.t("scores.none"))];
+    }
+    scores.entries.iter().enumerate()
+        .map(|(i, e)| Item::info(Row::Entry(i), format!("{}. {}", i + 1, e.name))
+            .detail(locale.fmt("scores.entry", &[("score", &e.score), ("stage", &e.stage), ("rank", &e.rank)])))
+        .collect()
+}
+
+fn view_items(boards: &Leaderboards, modes: &[Mode], mode: usize, locale: &Locale) -> Vec<Item<Row>> {
+    let names = modes.iter().map(|m| locale.t(&m.name_key()).to_string()).collect();
+    let mut items = vec![Item::choice(Row::Mode, locale.t("scores.mode"), names, mode)];
+    items.extend(table(boards.get(mode), locale));
+    items.push(Item::button(Row::Back, locale.t("scores.back")));
+    items
+}
+
+impl ScoreScreen {
+    pub fn view(boards: &Leaderboards, modes: &[Mode], mode: usize, locale: &Locale) -> ScoreScreen {
+        let menu = Menu::new(locale.t("scores.heading"), view_items(boards, modes, mode, locale))
+            .with_hint(locale.t("scores.view_hint"));
+        ScoreScreen { menu, mode, pending: None }
+    }
+
+    /// Which mode's table is showing.
+    pub fn mode(&self) -> usize {
+        self.mode
+    }
+
+    pub fn entering(boards: &Leaderboards, modes: &[Mode], mode: usize, entry: Entry, locale: &Locale) -> ScoreScreen {
+        let scores = boards.get(mode);
+        let mut items = table(scores, locale);
+        items.push(Item::text(Row::Name, locale.t("scores.name"), scores.last_name.as_str(), NAME_LENGTH));
+        items.push(Item::button(Row::Save, locale.t("scores.save")));
+        let heading = locale.fmt("scores.new", &[("mode", &locale.t(&modes[mode].name_key()))]);
+        let mut menu = Menu::new(heading, items)
+            .with_hint(locale.t("scores.hint"));
+        menu.message = locale.fmt("scores.summary", &[("score", &entry.score), ("stage", &entry.stage), ("rank", &entry.rank)]);
+        menu.select(Row::Name);
+        ScoreScreen { menu, mode, pending: Some(entry) }
+    }
+
+    /// Still taking a name, so it can't just be rebuilt.
+    pub fn is_entering(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    pub fn is_typing(&self) -> bool {
+        self.menu.is_typing()
+    }
+
+    /// True once it's done and can close.
+    pub fn input(&mut self, boards: &mut Leaderboards, modes: &[Mode], locale: &Locale, input: MenuInput) -> bool {
+        match self.menu.input(input) {
+            MenuEvent::Back | MenuEvent::Pressed(Row::Back) => true,
+            MenuEvent::Changed(Row::Mode) => {
+                if let Some(Control::Choice { index, .. }) = self.menu.item(Row::Mode).map(|item| &item.control) {
+                    self.mode = *index;
+                }
+                self.menu.set_items(view_items(boards, modes, self.mode, locale));
+                false
+            }
+            MenuEvent::Pressed(Row::Name | Row::Save) => {
+                if let Some(mut entry) = self.pending.take() {
+                    let name = self.menu.item(Row::Name).map_or("", |item| item.text_value()).trim().to_string();
+                    entry.name = if name.is_empty() { locale.t("scores.default_name").to_string() } else { name };
+                    boards.add(self.mode, entry);
+                }
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.menu.update(dt);
+    }
+
+    pub fn take_sound(&mut self) -> Option<MenuSound> {
+        self.menu.take_sound()
+    }
+
+    pub fn draw(&self, ctx: &mut Context, locale: &Locale, accessibility: &Accessibility) -> GameResult {
+        self.menu.draw(ctx, locale, accessibility)
+    }
+}
@@ -0,0 +1,91 @@
+use serde::{Deserialize, Serialize};
+
+use crate::modes::{self, Mode};
+use crate::rank::Rank;
+use crate::storage;
+
+/// The campaign's. Other modes get `scores_<mode>.toml`.
+pub const SCORES_FILE: &str = "scores.toml";
+
+fn scores_file(mode: &str) -> String {
+    if mode == modes::CAMPAIGN {
+        SCORES_FILE.to_string()
+    } else {
+        format!("scores_{}.toml", mode)
+    }
+}
+
+/// How many make the table.
+const KEEP: usize = 10;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Entry {
+    pub name: String,
+    pub stage: String,
+    pub score: u32,
+    pub rank: Rank,
+}
+
+/// The best scores from any stage in one mode, highest first.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Scores {
+    /// Filled in for next time so you don't have to type it again.
+    pub last_name: String,
+    pub entries: Vec<Entry>,
+}
+
+impl Scores {
+    fn load(file: &str) -> Scores {
+        match storage::load(file) {
+            Ok(Some(scores)) => scores,
+            Ok(None) => Scores::default(),
+            Err(e) => {
+                println!("Couldn't read high scores, starting a new table: {}", e);
+                Scores::default()
+            }
+        }
+    }
+
+    fn save(&self, file: &str) {
+        if let Err(e) = storage::save(file, self) {
+            println!("Couldn't save high scores: {}", e);
+        }
+    }
+
+    pub fn qualifies(&self, score: u32) -> bool {
+        score > 0 && (self.entries.len() < KEEP || self.entries.iter().any(|e| score > e.score))
+    }
+
+    /// Ties go below the older score.
+    pub fn add(&mut self, entry: Entry) {
+        let at = self.entries.iter().position(|e| entry.score > e.score).unwrap_or(self.entries.len());
+        self.last_name = entry.name.clone();
+        self.entries.insert(at, entry);
+        self.entries.truncate(KEEP);
+    }
+}
+
+/// A table for each mode, in the same order as the modes.
+pub struct Leaderboards {
+    boards: Vec<(String, Scores)>,
+}
+
+impl Leaderboards {
+    pub fn load(modes: &[Mode]) -> Leaderboards {
+        Leaderboards {
+            boards: modes.iter().map(|m| (m.id.clone(), Scores::load(&scores_file(&m.id)))).collect(),
+        }
+    }
+
+    pub fn get(&self, mode: usize) -> &Scores {
+        &self.boards[mode].1
+    }
+
+    /// Adds it to the mode's table and saves that.
+    pub fn add(&mut self, mode: usize, entry: Entry) {
+        let (id, scores) = &mut self.boards[mode];
+        scores.add(entry);
+        scores.save(&scores_file(id));
+    }
+}
@@ -0,0 +1,319 @@
+//! Which menu screen is up, if any, and where each one leads. The settings
+//! menu isn't one of these, it opens over whatever else is showing.
+
+use ggez::{Context, GameResult};
+
+use crate::campaign::{Progress, Stage};
+use crate::locale::Locale;
+use crate::menu::{Item, Menu, MenuEvent, MenuInput, MenuSound};
+use crate::modes::Mode;
+use crate::results::Results;
+use crate::score_screen::ScoreScreen;
+use crate::scores::{Entry, Leaderboards};
+use crate::settings::Accessibility;
+use crate::stats::{Achievement, Stats};
+use crate::stage_select::{SelectResult, StageSelect};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TitleItem {
+    Continue,
+    Play,
+    Scores,
+    Stats,
+    Settings,
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StatsRow {
+    Stat(usize),
+    Achievement(usize),
+    Back,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseItem {
+    Resume,
+    Settings,
+    Save,
+    GiveUp,
+}
+
+/// What the screens read from the game.
+pub struct Data<'a> {
+    pub stages: &'a [Stage],
+    pub modes: &'a [Mode],
+    pub progress: &'a Progress,
+    /// The last one played, for menus to start on.
+    pub mode: usize,
+    pub stats: &'a Stats,
+    pub achievements: &'a [Achievement],
+    pub locale: &'a Locale,
+}
+
+/// What the game should do after a screen handled an input.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Go {
+    Stay,
+    Title,
+    ModeSelect { selected: usize },
+    StageSelect { mode: usize, selected: usize, message: String },
+    Scores,
+    Stats,
+    Settings,
+    Start { mode: usize, stage: usize },
+    /// The run saved from the pause menu.
+    Continue,
+    Resume,
+    /// Saves the run to pick up later and goes back to the title.
+    SaveRun,
+    /// From the pause menu. Modes that don't end on their own count it as
+    /// finishing.
+    GiveUp,
+    Quit,
+}
+
+/// Where to go once a finished stage's results (and maybe high score) are done.
+#[derive(Debug, Default)]
+pub struct After {
+    pub mode: usize,
+    pub stage: usize,
+    pub message: String,
+}
+
+impl After {
+    fn go(self) -> Go {
+        Go::StageSelect { mode: self.mode, selected: self.stage, message: self.message }
+    }
+}
+
+pub enum Screen {
+    Title(Menu<TitleItem>),
+    ModeSelect(Menu<usize>),
+    /// Paused partway through this stage, in this mode.
+    Pause(Menu<PauseItem>, usize, usize),
+    StageSelect(StageSelect),
+    /// How the stage went, then the score to enter if it made the table.
+    Results(Results, Option<Entry>, After),
+    /// With where to go after, if it's taking a new score rather than just showing them.
+    Scores(ScoreScreen, Option<After>),
+    Stats(Menu<StatsRow>),
+}
+
+/// "Continue" is only there while there's a saved run.
+fn title_menu(locale: &Locale, can_continue: bool) -> Menu<TitleItem> {
+    let resume = can_continue.then(|| Item::button(TitleItem::Continue, locale.t("title.continue")));
+    let items = resume.into_iter().chain([
+        Item::button(TitleItem::Play, locale.t("title.play")),
+        Item::button(TitleItem::Scores, locale.t("title.scores")),
+        Item::button(TitleItem::Stats, locale.t("title.stats")),
+        Item::button(TitleItem::Settings, locale.t("title.settings")),
+        Item::button(TitleItem::Quit, locale.t("title.quit")),
+    ]).collect();
+    Menu::new(locale.t("title.heading"), items).with_hint(locale.t("title.hint"))
+}
+
+fn mode_menu(modes: &[Mode], locale: &Locale) -> Menu<usize> {
+    let items = modes.iter().enumerate()
+        .map(|(i, mode)| Item::button(i, locale.t(&mode.name_key())).detail(locale.t(&mode.description_key())))
+        .collect();
+    Menu::new(locale.t("modes.heading"), items)
+}
+
+/// Modes that only end when you stop get "Finish" instead of "Give up".
+fn pause_menu(stage: &Stage, mode: &Mode, locale: &Locale) -> Menu<PauseItem> {
+    let give_up = if mode.rules.is_open_ended() { "pause.finish" } else { "pause.give_up" };
+    let mut menu = Menu::new(locale.t("pause.heading"), vec![
+        Item::button(PauseItem::Resume, locale.t("pause.resume")),
+        Item::button(PauseItem::Settings, locale.t("pause.settings")),
+        Item::button(PauseItem::Save, locale.t("pause.save")),
+        Item::button(PauseItem::GiveUp, locale.t(give_up)),
+    ]).with_hint(locale.t("pause.hint"));
+    let goal = if mode.rules.stage_goal { stage.goal_text(locale) } else { locale.t(&mode.name_key()).to_string() };
+    menu.message = format!("{}: {}", stage.name, goal);
+    menu
+}
+
+/// Lifetime stats, then every achievement. Locked ones are greyed out but
+/// can still be scrolled to, to see what they want.
+fn stats_menu(stats: &Stats, achievements: &[Achievement], locale: &Locale) -> Menu<StatsRow> {
+    let seconds = stats.play_time as u64;
+    let counts = [
+        ("stats.candy_caught", stats.candy_caught.to_string()),
+        ("stats.candy_missed", stats.candy_missed.to_string()),
+        ("stats.jumps", stats.jumps.to_string()),
+        ("stats.hits_taken", stats.hits_taken.to_string()),
+        ("stats.longest_combo", stats.longest_combo.to_string()),
+        ("stats.stages_cleared", stats.stages_cleared.to_string()),
+        ("stats.play_time", format!("{}:{:02}:{:02}", seconds / 3600, seconds / 60 % 60, seconds % 60)),
+    ];
+    let mut items = counts.into_iter().enumerate()
+        .map(|(i, (key, value))| Item::info(StatsRow::Stat(i), locale.t(key)).detail(value))
+        .collect::<Vec<_>>();
+
+    let unlocked = achievements.iter().filter(|a| stats.unlocked.contains(&a.id)).count();
+    let (count, total) = (unlocked, achievements.len());
+    items.push(Item::info(StatsRow::Stat(items.len()), locale.fmt("stats.achievements", &[("count", &count), ("total", &total)])));
+    for (i, a) in achievements.iter().enumerate() {
+        let done = stats.unlocked.contains(&a.id);
+        items.push(Item::button(StatsRow::Achievement(i), locale.t(&a.name_key()))
+            .detail(locale.t(&a.description_key()))
+            .enabled(done));
+    }
+    items.push(Item::button(StatsRow::Back, locale.t("stats.back")));
+    Menu::new(locale.t("stats.heading"), items)
+}
+
+/// Swaps in a rebuilt menu without losing the place.
+fn replace<T: Copy + PartialEq>(menu: &mut Menu<T>, fresh: Menu<T>) {
+    let selected = menu.selected();
+    *menu = fresh;
+    if let Some(item) = selected {
+        menu.select(item);
+    }
+}
+
+impl Screen {
+    pub fn title(locale: &Locale, can_continue: bool) -> Screen {
+        Screen::Title(title_menu(locale, can_continue))
+    }
+
+    pub fn mode_select(modes: &[Mode], selected: usize, locale: &Locale) -> Screen {
+        let mut menu = mode_menu(modes, locale);
+        menu.select(selected);
+        Screen::ModeSelect(menu)
+    }
+
+    pub fn pause(data: &Data, stage: usize, mode: usize) -> Screen {
+        Screen::Pause(pause_menu(&data.stages[stage], &data.modes[mode], data.locale), stage, mode)
+    }
+
+    pub fn stats(stats: &Stats, achievements: &[Achievement], locale: &Locale) -> Screen {
+        Screen::Stats(stats_menu(stats, achievements, locale))
+    }
+
+    /// Builds it again in a new language, on the same item. Messages already
+    /// shown stay as they were, and a half-typed name is left alone.
+    pub fn relabel(&mut self, data: &Data, boards: &Leaderboards) {
+        let locale = data.locale;
+        match self {
+            Screen::Title(menu) => {
+                let can_continue = menu.item(TitleItem::Continue).is_some();
+                replace(menu, title_menu(locale, can_continue));
+            }
+            Screen::ModeSelect(menu) => replace(menu, mode_menu(data.modes, locale)),
+            Screen::Pause(menu, stage, mode) => replace(menu, pause_menu(&data.stages[*stage], &data.modes[*mode], locale)),
+            Screen::StageSelect(select) => {
+                let message = select.message().to_string();
+                *select = StageSelect::new(data, select.mode(), select.selected(), message);
+            }
+            Screen::Results(results, ..) => results.relabel(locale),
+            Screen::Scores(screen, _) if screen.is_entering() => (),
+            Screen::Scores(screen, _) => *screen = ScoreScreen::view(boards, data.modes, screen.mode(), locale),
+            Screen::Stats(menu) => replace(menu, stats_menu(data.stats, data.achievements, locale)),
+        }
+    }
+
+    /// Letters should go to a text box rather than be read as controls.
+    pub fn is_typing(&self) -> bool {
+        match self {
+            Screen::Scores(screen, _) => screen.is_typing(),
+            _ => false,
+        }
+    }
+
+    pub fn input(&mut self, input: MenuInput, data: &Data, boards: &mut Leaderboards) -> Go {
+        let locale = data.locale;
+        match self {
+            Screen::Title(menu) => match menu.input(input) {
+                MenuEvent::Pressed(TitleItem::Continue) => Go::Continue,
+                MenuEvent::Pressed(TitleItem::Play) => Go::ModeSelect { selected: data.mode },
+                MenuEvent::Pressed(TitleItem::Scores) => Go::Scores,
+                MenuEvent::Pressed(TitleItem::Stats) => Go::Stats,
+                MenuEvent::Pressed(TitleItem::Settings) => Go::Settings,
+                MenuEvent::Pressed(TitleItem::Quit) => Go::Quit,
+                _ => Go::Stay,
+            },
+            Screen::ModeSelect(menu) => match menu.input(input) {
+                MenuEvent::Pressed(mode) => {
+                    let latest = data.progress.unlocked.clamp(1, data.stages.len()) - 1;
+                    Go::StageSelect { mode, selected: latest, message: String::new() }
+                }
+                MenuEvent::Back => Go::Title,
+                _ => Go::Stay,
+            },
+            Screen::Pause(menu, ..) => match menu.input(input) {
+                MenuEvent::Pressed(PauseItem::Resume) | MenuEvent::Back => Go::Resume,
+                MenuEvent::Pressed(PauseItem::Settings) => Go::Settings,
+                MenuEvent::Pressed(PauseItem::Save) => Go::SaveRun,
+                MenuEvent::Pressed(PauseItem::GiveUp) => Go::GiveUp,
+                _ => Go::Stay,
+            },
+            Screen::StageSelect(select) => match select.input(data, input) {
+                SelectResult::Stay => Go::Stay,
+                SelectResult::Start(stage) => Go::Start { mode: select.mode(), stage },
+                SelectResult::Back => Go::ModeSelect { selected: select.mode() },
+            },
+            Screen::Results(results, entry, after) => {
+                if !results.input(input) {
+                    return Go::Stay;
+                }
+                let after = std::mem::take(after);
+                match entry.take() {
+                    Some(entry) => {
+                        let screen = ScoreScreen::entering(boards, data.modes, after.mode, entry, locale);
+                        *self = Screen::Scores(screen, Some(after));
+                        Go::Stay
+                    }
+                    None => after.go(),
+                }
+            }
+            Screen::Scores(screen, after) => {
+                if !screen.input(boards, data.modes, locale, input) {
+                    return Go::Stay;
+                }
+                after.take().map_or(Go::Title, After::go)
+            }
+            Screen::Stats(menu) => match menu.input(input) {
+                MenuEvent::Pressed(StatsRow::Back) | MenuEvent::Back => Go::Title,
+                _ => Go::Stay,
+            },
+        }
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        match self {
+            Screen::Title(menu) => menu.update(dt),
+            Screen::ModeSelect(menu) => menu.update(dt),
+            Screen::Pause(menu, ..) => menu.update(dt),
+            Screen::StageSelect(select) => select.update(dt),
+            Screen::Results(results, ..) => results.update(dt),
+            Screen::Scores(screen, _) => screen.update(dt),
+            Screen::Stats(menu) => menu.update(dt),
+        }
+    }
+
+    pub fn take_sound(&mut self) -> Option<MenuSound> {
+        match self {
+            Screen::Title(menu) => menu.take_sound(),
+            Screen::ModeSelect(menu) => menu.take_sound(),
+            Screen::Pause(menu, ..) => menu.take_sound(),
+            Screen::StageSelect(select) => select.take_sound(),
+            Screen::Results(results, ..) => results.take_sound(),
+            Screen::Scores(screen, _) => screen.take_sound(),
+            Screen::Stats(menu) => menu.take_sound(),
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut Context, locale: &Locale, accessibility: &Accessibility) -> GameResult {
+        match self {
+            Screen::Title(menu) => menu.draw(ctx, locale, accessibility),
+            Screen::ModeSelect(menu) => menu.draw(ctx, locale, accessibility),
+            Screen::Pause(menu, ..) => menu.draw(ctx, locale, accessibility),
+            Screen::StageSelect(select) => select.draw(ctx, locale, accessibility),
+            Screen::Results(results, ..) => results.draw(ctx, locale, accessibility),
+            Screen::Scores(screen, _) => screen.draw(ctx, locale, accessibility),
+            Screen::Stats(menu) => menu.draw(ctx, locale, accessibility),
+        }
+    }
+}
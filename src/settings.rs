@@ -0,0 +1,383 @@
+use ggez::conf::{FullscreenType, WindowMode};
+use ggez::event::{Button, KeyCode};
+use serde::{Deserialize, Serialize};
+
+use crate::locale::DEFAULT_LANGUAGE;
+use crate::storage;
+
+pub const SETTINGS_FILE: &str = "settings.toml";
+
+/// Everything the player can change without recompiling. Missing keys in the
+/// file fall back to the defaults, so old files keep working as we add things.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// A file name in `resources/lang`, without the `.toml`.
+    pub language: String,
+    pub display: Display,
+    pub audio: Audio,
+    pub controls: Controls,
+    pub accessibility: Accessibility,
+    /// Clip length is in the settings menu, the rest only in the file.
+    pub capture: Capture,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            language: DEFAULT_LANGUAGE.to_string(),
+            display: Display::default(),
+            audio: Audio::default(),
+            controls: Controls::default(),
+            accessibility: Accessibility::default(),
+            capture: Capture::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Display {
+    pub width: f32,
+    pub height: f32,
+    pub fullscreen: bool,
+    /// Only read when the window is made, so changing it needs a restart.
+    pub vsync: bool,
+    pub lighting: LightingMode,
+}
+
+/// How much of the lighting pass to run. Lower ones are for slow or software
+/// renderers, and the game drops down by itself if one doesn't work.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LightingMode {
+    /// Plain sprites, no darkness.
+    Off,
+    /// Darkness, lights and vignette, no shaders.
+    Basic,
+    /// Basic plus a colour-grading shader.
+    Full,
+}
+
+impl LightingMode {
+    pub const ALL: [LightingMode; 3] = [LightingMode::Off, LightingMode::Basic, LightingMode::Full];
+
+    /// Key for its name in the language files.
+    pub fn text_key(self) -> &'static str {
+        match self {
+            LightingMode::Off => "lighting.off",
+            LightingMode::Basic => "lighting.basic",
+            LightingMode::Full => "lighting.full",
+        }
+    }
+}
+
+impl Default for Display {
+    fn default() -> Self {
+        Display {
+            width: 640.0,
+            height: 480.0,
+            fullscreen: false,
+            vsync: true,
+            lighting: LightingMode::Full,
+        }
+    }
+}
+
+impl Display {
+    pub fn window_mode(&self) -> WindowMode {
+        WindowMode::default()
+            .dimensions(self.width, self.height)
+            .fullscreen_type(if self.fullscreen { FullscreenType::Desktop } else { FullscreenType::Windowed })
+    }
+}
+
+/// Volumes are 0 to 1.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Audio {
+    pub master: f32,
+    pub music: f32,
+    pub effects: f32,
+}
+
+impl Default for Audio {
+    fn default() -> Self {
+        Audio {
+            master: 1.0,
+            music: 0.7,
+            effects: 1.0,
+        }
+    }
+}
+
+impl Audio {
+    pub fn music_volume(&self) -> f32 {
+        self.master * self.music
+    }
+
+    pub fn effects_volume(&self) -> f32 {
+        self.master * self.effects
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Accessibility {
+    /// Bigger HUD text.
+    pub large_text: bool,
+    /// Dark backing behind HUD text so it reads over the background.
+    pub high_contrast: bool,
+    /// No sparkles when catching candy.
+    pub reduce_motion: bool,
+    pub landing_markers: LandingMarkers,
+}
+
+/// Shows where candy will come down, see `markers.rs`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LandingMarkers {
+    #[default]
+    Off,
+    /// A mark on the floor under each candy.
+    Markers,
+    /// A shadow that grows and darkens as the candy gets close.
+    Shadows,
+}
+
+impl LandingMarkers {
+    pub const ALL: [LandingMarkers; 3] = [LandingMarkers::Off, LandingMarkers::Markers, LandingMarkers::Shadows];
+
+    /// Key for its name in the language files.
+    pub fn text_key(self) -> &'static str {
+        match self {
+            LandingMarkers::Off => "landing_markers.off",
+            LandingMarkers::Markers => "landing_markers.markers",
+            LandingMarkers::Shadows => "landing_markers.shadows",
+        }
+    }
+}
+
+/// Clips of the last few seconds, see `capture.rs`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Capture {
+    /// How far back a clip goes. Off (0) until it's turned on, since keeping
+    /// frames means reading the screen back every few frames.
+    pub clip_seconds: f32,
+    /// Frames kept a second.
+    pub clip_fps: u32,
+    /// Size of the frames next to the 640x480 the game is laid out in.
+    pub clip_scale: f32,
+    pub clip_format: ClipFormat,
+    /// Keep a replay of every stage played, see `replay.rs`.
+    pub save_replays: bool,
+}
+
+impl Default for Capture {
+    fn default() -> Self {
+        Capture {
+            clip_seconds: 0.0,
+            clip_fps: 10,
+            clip_scale: 0.5,
+            clip_format: ClipFormat::Gif,
+            save_replays: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipFormat {
+    Gif,
+    /// A folder with a PNG per frame.
+    Png,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Action {
+    Left,
+    Right,
+    Jump,
+    Down,
+    Dash,
+    /// Picks things in menus.
+    Confirm,
+    Screenshot,
+    /// Saves the last few seconds.
+    Clip,
+    Settings,
+    Quit,
+}
+
+impl Action {
+    pub const ALL: [Action; 10] = [
+        Action::Left,
+        Action::Right,
+        Action::Jump,
+        Action::Down,
+        Action::Dash,
+        Action::Confirm,
+        Action::Screenshot,
+        Action::Clip,
+        Action::Settings,
+        Action::Quit,
+    ];
+
+    /// Key for its name in the language files.
+    pub fn text_key(self) -> &'static str {
+        match self {
+            Action::Left => "action.left",
+            Action::Right => "action.right",
+            Action::Jump => "action.jump",
+            Action::Down => "action.down",
+            Action::Dash => "action.dash",
+            Action::Confirm => "action.confirm",
+            Action::Screenshot => "action.screenshot",
+            Action::Clip => "action.clip",
+            Action::Settings => "action.settings",
+            Action::Quit => "action.quit",
+        }
+    }
+}
+
+/// Key names per action, as written in the file (`left = ["Left", "A"]`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Controls {
+    pub left: Vec<String>,
+    pub right: Vec<String>,
+    pub jump: Vec<String>,
+    pub down: Vec<String>,
+    pub dash: Vec<String>,
+    pub confirm: Vec<String>,
+    pub screenshot: Vec<String>,
+    pub clip: Vec<String>,
+    pub settings: Vec<String>,
+    pub quit: Vec<String>,
+}
+
+impl Default for Controls {
+    fn default() -> Self {
+        let keys = |names: &[&str]| names.iter().map(|s| s.to_string()).collect();
+        Controls {
+            left: keys(&["Left", "A"]),
+            right: keys(&["Right", "D"]),
+            jump: keys(&["Up", "W", "Space"]),
+            down: keys(&["Down", "S"]),
+            dash: keys(&["LShift", "X"]),
+            confirm: keys(&["Return"]),
+            screenshot: keys(&["P"]),
+            clip: keys(&["O"]),
+            settings: keys(&["Tab"]),
+            quit: keys(&["Escape"]),
+        }
+    }
+}
+
+impl Controls {
+    pub fn keys(&self, action: Action) -> &Vec<String> {
+        match action {
+            Action::Left => &self.left,
+            Action::Right => &self.right,
+            Action::Jump => &self.jump,
+            Action::Down => &self.down,
+            Action::Dash => &self.dash,
+            Action::Confirm => &self.confirm,
+            Action::Screenshot => &self.screenshot,
+            Action::Clip => &self.clip,
+            Action::Settings => &self.settings,
+            Action::Quit => &self.quit,
+        }
+    }
+
+    fn keys_mut(&mut self, action: Action) -> &mut Vec<String> {
+        match action {
+            Action::Left => &mut self.left,
+            Action::Right => &mut self.right,
+            Action::Jump => &mut self.jump,
+            Action::Down => &mut self.down,
+            Action::Dash => &mut self.dash,
+            Action::Confirm => &mut self.confirm,
+            Action::Screenshot => &mut self.screenshot,
+            Action::Clip => &mut self.clip,
+            Action::Settings => &mut self.settings,
+            Action::Quit => &mut self.quit,
+        }
+    }
+
+    pub fn action_for(&self, keycode: KeyCode) -> Option<Action> {
+        let name = key_name(keycode)?;
+        Action::ALL.iter().copied().find(|&a| self.keys(a).iter().any(|k| k == name))
+    }
+
+    /// Makes `keycode` the only key for `action`, taking it off anything else.
+    pub fn rebind(&mut self, action: Action, keycode: KeyCode) {
+        if let Some(name) = key_name(keycode) {
+            for other in Action::ALL {
+                self.keys_mut(other).retain(|k| k != name);
+            }
+            *self.keys_mut(action) = vec![name.to_string()];
+        }
+    }
+}
+
+const NAMED_KEYS: &[(KeyCode, &str)] = &[
+    (KeyCode::Left, "Left"), (KeyCode::Right, "Right"), (KeyCode::Up, "Up"), (KeyCode::Down, "Down"),
+    (KeyCode::Space, "Space"), (KeyCode::Return, "Return"), (KeyCode::Escape, "Escape"),
+    (KeyCode::Tab, "Tab"), (KeyCode::Back, "Back"),
+    (KeyCode::LShift, "LShift"), (KeyCode::RShift, "RShift"),
+    (KeyCode::LControl, "LControl"), (KeyCode::RControl, "RControl"),
+    (KeyCode::A, "A"), (KeyCode::B, "B"), (KeyCode::C, "C"), (KeyCode::D, "D"), (KeyCode::E, "E"),
+    (KeyCode::F, "F"), (KeyCode::G, "G"), (KeyCode::H, "H"), (KeyCode::I, "I"), (KeyCode::J, "J"),
+    (KeyCode::K, "K"), (KeyCode::L, "L"), (KeyCode::M, "M"), (KeyCode::N, "N"), (KeyCode::O, "O"),
+    (KeyCode::P, "P"), (KeyCode::Q, "Q"), (KeyCode::R, "R"), (KeyCode::S, "S"), (KeyCode::T, "T"),
+    (KeyCode::U, "U"), (KeyCode::V, "V"), (KeyCode::W, "W"), (KeyCode::X, "X"), (KeyCode::Y, "Y"),
+    (KeyCode::Z, "Z"),
+    (KeyCode::Key0, "0"), (KeyCode::Key1, "1"), (KeyCode::Key2, "2"), (KeyCode::Key3, "3"),
+    (KeyCode::Key4, "4"), (KeyCode::Key5, "5"), (KeyCode::Key6, "6"), (KeyCode::Key7, "7"),
+    (KeyCode::Key8, "8"), (KeyCode::Key9, "9"),
+    (KeyCode::F1, "F1"), (KeyCode::F2, "F2"), (KeyCode::F3, "F3"), (KeyCode::F4, "F4"),
+    (KeyCode::F5, "F5"), (KeyCode::F6, "F6"), (KeyCode::F7, "F7"), (KeyCode::F8, "F8"),
+    (KeyCode::F9, "F9"), (KeyCode::F10, "F10"), (KeyCode::F11, "F11"), (KeyCode::F12, "F12"),
+];
+
+/// Gamepads aren't rebindable yet, they get the usual layout. Menus read
+/// pads their own way, see `MenuInput::from_button`.
+pub fn button_action(button: Button) -> Option<Action> {
+    match button {
+        Button::DPadLeft => Some(Action::Left),
+        Button::DPadRight => Some(Action::Right),
+        Button::South | Button::DPadUp => Some(Action::Jump),
+        Button::DPadDown => Some(Action::Down),
+        Button::West | Button::RightTrigger => Some(Action::Dash),
+        Button::Select => Some(Action::Settings),
+        Button::Start => Some(Action::Quit),
+        _ => None,
+    }
+}
+
+/// The name a key is written as in the settings file, if it's one we allow binding.
+pub fn key_name(keycode: KeyCode) -> Option<&'static str> {
+    NAMED_KEYS.iter().find(|(k, _)| *k == keycode).map(|(_, name)| *name)
+}
+
+impl Settings {
+    /// Falls back to the defaults (and says why) if the file is broken.
+    pub fn load() -> Settings {
+        match storage::load(SETTINGS_FILE) {
+            Ok(Some(settings)) => settings,
+            Ok(None) => Settings::default(),
+            Err(e) => {
+                println!("Couldn't read settings, using defaults: {}", e);
+                Settings::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = storage::save(SETTINGS_FILE, self) {
+            println!("Couldn't save settings: {}", e);
+        }
+    }
+}
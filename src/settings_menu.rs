@@ -0,0 +1,197 @@
+use ggez::event::KeyCode;
+use ggez::{Context, GameResult};
+
+use crate::locale::Locale;
+use crate::menu::{Control, Item, Menu, MenuEvent, MenuInput, MenuSound};
+use crate::settings::{Action, LandingMarkers, LightingMode, Settings};
+
+const RESOLUTIONS: [(f32, f32); 4] = [(640.0, 480.0), (800.0, 600.0), (960.0, 720.0), (1280.0, 960.0)];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Row {
+    Language,
+    Resolution,
+    Fullscreen,
+    Vsync,
+    Lighting,
+    MasterVolume,
+    MusicVolume,
+    EffectsVolume,
+    LargeText,
+    HighContrast,
+    ReduceMotion,
+    LandingMarkers,
+    ClipSeconds,
+    Bind(Action),
+}
+
+/// What the game needs to do after the menu handled a key.
+#[derive(Debug, PartialEq, Eq)]
+pub enum MenuResult {
+    Stay,
+    /// Window size or fullscreen changed and should be applied now.
+    DisplayChanged,
+    /// The text needs loading again, in `settings.language`.
+    LanguageChanged,
+    Close,
+}
+
+/// The in-game settings screen. It edits a `Settings` in place; the caller
+/// saves it when the screen closes.
+pub struct SettingsMenu {
+    menu: Menu<Row>,
+    /// Set while waiting for the next key press to bind to this action.
+    rebinding: Option<Action>,
+    /// (code, name) of each language there's a file for.
+    languages: Vec<(String, String)>,
+}
+
+fn items(settings: &Settings, rebinding: Option<Action>, languages: &[(String, String)], locale: &Locale) -> Vec<Item<Row>> {
+    let language = languages.iter().position(|(code, _)| *code == settings.language).unwrap_or(0);
+    let language_names = languages.iter().map(|(_, name)| name.clone()).collect();
+    let display = &settings.display;
+    let resolution = RESOLUTIONS.iter()
+        .position(|&(w, h)| w == display.width && h == display.height)
+        .unwrap_or(0);
+    let resolutions = RESOLUTIONS.iter().map(|(w, h)| format!("{}x{}", w, h)).collect();
+    let lighting = LightingMode::ALL.iter().position(|&m| m == display.lighting).unwrap_or(0);
+    let lightings = LightingMode::ALL.iter().map(|m| locale.t(m.text_key()).to_string()).collect();
+    let audio = &settings.audio;
+    let volume = |row, key, value: f32| Item::slider(row, locale.t(key), value, 0.0, 1.0, 0.1)
+        .detail(format!("{:.0}%", value * 100.0));
+    let toggle = |row, key, on| Item::toggle(row, locale.t(key), on);
+    let accessibility = &settings.accessibility;
+    let markers = LandingMarkers::ALL.iter().position(|&m| m == accessibility.landing_markers).unwrap_or(0);
+    let markers_names = LandingMarkers::ALL.iter().map(|m| locale.t(m.text_key()).to_string()).collect();
+    let clip_seconds = settings.capture.clip_seconds;
+    let clip_length = if clip_seconds > 0.0 {
+        format!("{:.0}s", clip_seconds)
+    } else {
+        locale.t("settings.clip_off").to_string()
+    };
+
+    let mut items = vec![
+        Item::choice(Row::Language, locale.t("settings.language"), language_names, language),
+        Item::choice(Row::Resolution, locale.t("settings.window_size"), resolutions, resolution),
+        toggle(Row::Fullscreen, "settings.fullscreen", display.fullscreen),
+        toggle(Row::Vsync, "settings.vsync", display.vsync),
+        Item::choice(Row::Lighting, locale.t("settings.lighting"), lightings, lighting),
+        volume(Row::MasterVolume, "settings.master_volume", audio.master),
+        volume(Row::MusicVolume, "settings.music_volume", audio.music),
+        volume(Row::EffectsVolume, "settings.effects_volume", audio.effects),
+        toggle(Row::LargeText, "settings.large_text", accessibility.large_text),
+        toggle(Row::HighContrast, "settings.high_contrast", accessibility.high_contrast),
+        toggle(Row::ReduceMotion, "settings.reduce_motion", accessibility.reduce_motion),
+        Item::choice(Row::LandingMarkers, locale.t("settings.landing_markers"), markers_names, markers),
+        Item::slider(Row::ClipSeconds, locale.t("settings.clip_length"), clip_seconds, 0.0, 30.0, 5.0).detail(clip_length),
+    ];
+    items.extend(Action::ALL.iter().map(|&action| {
+        let keys = if rebinding == Some(action) {
+            locale.t("settings.press_key").to_string()
+        } else {
+            settings.controls.keys(action).join(", ")
+        };
+        Item::button(Row::Bind(action), locale.t(action.text_key())).detail(keys)
+    }));
+    items
+}
+
+impl SettingsMenu {
+    pub fn new(settings: &Settings, languages: Vec<(String, String)>, locale: &Locale) -> SettingsMenu {
+        let menu = Menu::new(locale.t("settings.heading"), items(settings, None, &languages, locale))
+            .with_hint(locale.t("settings.hint"));
+        SettingsMenu { menu, rebinding: None, languages }
+    }
+
+    /// After the language changes, so everything's in the new one.
+    pub fn relabel(&mut self, settings: &Settings, locale: &Locale) {
+        let selected = self.menu.selected();
+        let mut menu = Menu::new(locale.t("settings.heading"), items(settings, self.rebinding, &self.languages, locale))
+            .with_hint(locale.t("settings.hint"));
+        if let Some(row) = selected {
+            menu.select(row);
+        }
+        self.menu = menu;
+    }
+
+    pub fn key_down(&mut self, settings: &mut Settings, locale: &Locale, keycode: KeyCode) -> MenuResult {
+        if let Some(action) = self.rebinding.take() {
+            // Escape backs out rather than binding, so you can't lose it.
+            if keycode != KeyCode::Escape {
+                settings.controls.rebind(action, keycode);
+            }
+            self.menu.set_items(items(settings, None, &self.languages, locale));
+            return MenuResult::Stay;
+        }
+        if settings.controls.action_for(keycode) == Some(Action::Settings) {
+            return self.input(settings, locale, MenuInput::Back);
+        }
+        match MenuInput::from_key(&settings.controls, keycode, false) {
+            Some(input) => self.input(settings, locale, input),
+            None => MenuResult::Stay,
+        }
+    }
+
+    pub fn input(&mut self, settings: &mut Settings, locale: &Locale, input: MenuInput) -> MenuResult {
+        if self.rebinding.is_some() {
+            // Only keys can be bound, so a pad button just cancels.
+            self.rebinding = None;
+            self.menu.set_items(items(settings, None, &self.languages, locale));
+            return MenuResult::Stay;
+        }
+
+        let mut result = MenuResult::Stay;
+        match self.menu.input(input) {
+            MenuEvent::None => (),
+            MenuEvent::Back => return MenuResult::Close,
+            MenuEvent::Pressed(Row::Bind(action)) => self.rebinding = Some(action),
+            MenuEvent::Pressed(_) => (),
+            MenuEvent::Changed(row) => {
+                let Some(item) = self.menu.item(row) else { return MenuResult::Stay };
+                match (row, &item.control) {
+                    (Row::Language, Control::Choice { index, .. }) => {
+                        if let Some((code, _)) = self.languages.get(*index) {
+                            settings.language = code.clone();
+                            result = MenuResult::LanguageChanged;
+                        }
+                    }
+                    (Row::Resolution, Control::Choice { index, .. }) => {
+                        (settings.display.width, settings.display.height) = RESOLUTIONS[*index];
+                        result = MenuResult::DisplayChanged;
+                    }
+                    (Row::Fullscreen, Control::Toggle(on)) => {
+                        settings.display.fullscreen = *on;
+                        result = MenuResult::DisplayChanged;
+                    }
+                    (Row::Vsync, Control::Toggle(on)) => settings.display.vsync = *on,
+                    (Row::Lighting, Control::Choice { index, .. }) => settings.display.lighting = LightingMode::ALL[*index],
+                    (Row::MasterVolume, Control::Slider { value, .. }) => settings.audio.master = *value,
+                    (Row::MusicVolume, Control::Slider { value, .. }) => settings.audio.music = *value,
+                    (Row::EffectsVolume, Control::Slider { value, .. }) => settings.audio.effects = *value,
+                    (Row::LargeText, Control::Toggle(on)) => settings.accessibility.large_text = *on,
+                    (Row::HighContrast, Control::Toggle(on)) => settings.accessibility.high_contrast = *on,
+                    (Row::ReduceMotion, Control::Toggle(on)) => settings.accessibility.reduce_motion = *on,
+                    (Row::LandingMarkers, Control::Choice { index, .. }) => {
+                        settings.accessibility.landing_markers = LandingMarkers::ALL[*index];
+                    }
+                    (Row::ClipSeconds, Control::Slider { value, .. }) => settings.capture.clip_seconds = *value,
+                    _ => (),
+                }
+            }
+        }
+        self.menu.set_items(items(settings, self.rebinding, &self.languages, locale));
+        result
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.menu.update(dt);
+    }
+
+    pub fn take_sound(&mut self) -> Option<MenuSound> {
+        self.menu.take_sound()
+    }
+
+    pub fn draw(&self, ctx: &mut Context, locale: &Locale, settings: &Settings) -> GameResult {
+        self.menu.draw(ctx, locale, &settings.accessibility)
+    }
+}
@@ -0,0 +1,75 @@
+//! Menu blips. There are no sound files for these yet, so they're made up as
+//! tiny WAVs on startup. Swap in `SoundData::new(ctx, path)` when there are.
+
+use std::f32::consts::TAU;
+
+use ggez::audio::{SoundData, SoundSource, Source};
+use ggez::Context;
+
+use crate::menu::MenuSound;
+
+const SAMPLE_RATE: u32 = 22050;
+
+/// A sine that drops from `from` to `to` Hz over `seconds`, fading out.
+fn blip(from: f32, to: f32, seconds: f32) -> SoundData {
+    let count = (SAMPLE_RATE as f32 * seconds) as usize;
+    let mut phase = 0.0f32;
+    let samples = (0..count).map(|i| {
+        let t = i as f32 / count as f32;
+        phase += (from + (to - from) * t) * TAU / SAMPLE_RATE as f32;
+        let loudness = 0.3 * (1.0 - t) * (1.0 - t);
+        (phase.sin() * loudness * i16::MAX as f32) as i16
+    });
+
+    let data_len = count as u32 * 2;
+    let mut wav = Vec::with_capacity(44 + data_len as usize);
+    wav.extend_from_slice(b"RIFF");
+    wav.extend_from_slice(&(36 + data_len).to_le_bytes());
+    wav.extend_from_slice(b"WAVEfmt ");
+    wav.extend_from_slice(&16u32.to_le_bytes());
+    wav.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    wav.extend_from_slice(&1u16.to_le_bytes()); // mono
+    wav.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    wav.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    wav.extend_from_slice(&2u16.to_le_bytes());
+    wav.extend_from_slice(&16u16.to_le_bytes());
+    wav.extend_from_slice(b"data");
+    wav.extend_from_slice(&data_len.to_le_bytes());
+    for sample in samples {
+        wav.extend_from_slice(&sample.to_le_bytes());
+    }
+    SoundData::from_bytes(&wav)
+}
+
+pub struct Sfx {
+    sounds: Vec<(MenuSound, SoundData)>,
+}
+
+impl Sfx {
+    pub fn load() -> Sfx {
+        Sfx {
+            sounds: vec![
+                (MenuSound::Move, blip(660.0, 620.0, 0.05)),
+                (MenuSound::Change, blip(880.0, 840.0, 0.05)),
+                (MenuSound::Confirm, blip(660.0, 1320.0, 0.1)),
+                (MenuSound::Back, blip(520.0, 260.0, 0.1)),
+                (MenuSound::Denied, blip(180.0, 140.0, 0.15)),
+            ],
+        }
+    }
+
+    /// Doesn't stop the game over a sound, just says if it didn't play.
+    pub fn play(&self, ctx: &mut Context, sound: MenuSound, volume: f32) {
+        let Some((_, data)) = self.sounds.iter().find(|(s, _)| *s == sound) else { return };
+        if volume <= 0.0 {
+            return;
+        }
+        let played = Source::from_data(ctx, data.clone()).and_then(|mut source| {
+            source.set_volume(volume);
+            source.play_detached(ctx)
+        });
+        if let Err(e) = played {
+            println!("Couldn't play a menu sound: {}", e);
+        }
+    }
+}
@@ -0,0 +1,290 @@
+//! A stage being played, a tick at a time: the world, the score and what the
+//! rules count. Nothing in here needs a window, so the game and the balance
+//! simulator (`bin/balance.rs`) run the exact same thing.
+
+use glam::*;
+use oorandom::Rand32;
+
+use crate::campaign::{Goal, Stage};
+use crate::ecs::HazardKind;
+use crate::ecs::{Entity, World};
+use crate::modes::Rules;
+use crate::player::{player_handle_input, update_player_position, update_player_sprite, Player};
+use crate::rank::Performance;
+use crate::terrain::Terrain;
+use crate::trajectory::{self, Ground};
+use crate::tuning::Tuning;
+use crate::{broadphase, hazards, systems, ControllerState, FLOOR_Y, SCREEN_SIZE, SPAWN_HEIGHT};
+
+/// The game always steps this many times a second, however fast it draws.
+pub const TICKS_PER_SECOND: u32 = 60;
+
+pub struct Run {
+    pub world: World,
+    pub player: Entity,
+    pub score: u32,
+    pub combo: u32,
+    /// Candy caught this stage, for `Goal::Candy`.
+    pub caught: u32,
+    /// Seconds into this stage, for `Goal::Time`.
+    pub stage_time: f32,
+    /// Candy missed, best combo and life lost to hazards this stage, for the rank.
+    pub missed: u32,
+    pub max_combo: u32,
+    pub damage: i32,
+    pub difficulty: f32,
+    pub is_first_frame: bool,
+    pub rng: Rand32,
+    /// Life the cat started with, the most it can have.
+    pub max_life: i32,
+    /// What the cat stands on. Flat unless the mode plays random arenas.
+    pub terrain: Terrain,
+}
+
+/// What happened in a tick, for the game to count, show and play sounds for.
+#[derive(Debug, Default)]
+pub struct Events {
+    pub jumped: bool,
+    pub caught: u32,
+    pub missed: u32,
+    /// A hazard got the cat.
+    pub hurt: bool,
+    pub double_jump_unlocked: bool,
+    /// Where the cat ended up, for the camera to follow.
+    pub player_pos: Vec2,
+    pub end: Option<End>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum End {
+    OutOfLife,
+    /// The stage goal, or the mode's time limit.
+    Finished,
+}
+
+impl Run {
+    /// The cat at the stage's start. Its hazards come in on the first tick.
+    pub fn new(life: i32, rng: Rand32, stage: &Stage) -> Run {
+        let mut world = World::default();
+        let player = systems::spawn_player(&mut world, stage.start(), life);
+        Run {
+            world,
+            player,
+            score: 0,
+            combo: 0,
+            caught: 0,
+            stage_time: 0.0,
+            missed: 0,
+            max_combo: 0,
+            damage: 0,
+            difficulty: 0.0,
+            is_first_frame: true,
+            rng,
+            max_life: life,
+            terrain: Terrain::flat(),
+        }
+    }
+
+    pub fn player(&self) -> &Player {
+        self.world.players.get(self.player).expect("the cat is never despawned")
+    }
+
+    /// Whole seconds left in modes with a time limit, 0 in the rest.
+    pub fn time_left(&self, rules: &Rules) -> u32 {
+        rules.time_limit.map_or(0.0, |t| (t - self.stage_time).max(0.0).ceil()) as u32
+    }
+
+    pub fn goal_reached(&self, rules: &Rules, stage: &Stage) -> bool {
+        if !rules.stage_goal {
+            return rules.time_limit.is_some_and(|t| self.stage_time >= t);
+        }
+        match stage.goal {
+            Goal::Candy(n) => self.caught >= n,
+            Goal::Time(t) => self.stage_time >= t,
+        }
+    }
+
+    pub fn performance(&self) -> Performance {
+        Performance {
+            caught: self.caught,
+            missed: self.missed,
+            max_combo: self.max_combo,
+            time: self.stage_time,
+            damage: self.damage,
+            max_life: self.max_life,
+        }
+    }
+
+    /// One step of `1 / TICKS_PER_SECOND` seconds. `effects` is whether to
+    /// spawn sparkles, which don't change how the game plays.
+    pub fn tick(&mut self, input: &ControllerState, tuning: &Tuning, rules: &Rules, stage: &Stage, effects: bool) -> Events {
+        let seconds = 1.0 / (TICKS_PER_SECOND as f32);
+        let (screen_width, screen_height) = SCREEN_SIZE;
+        let mut events = Events::default();
+        self.stage_time += seconds;
+
+        self.difficulty += seconds * tuning.difficulty_rate * rules.difficulty_rate;
+        let rate = (seconds + 1.0) * 0.005 * rules.spawn_rate * (1.0 + rules.spawn_ramp * self.difficulty);
+        // Placed hazards were put there for the stage's own layout.
+        if self.is_first_frame && rules.hazards && !rules.random_map {
+            for placed in &stage.hazards {
+                match placed.kind {
+                    HazardKind::Bat => systems::spawn_bat(&mut self.world, placed.at, tuning),
+                    HazardKind::Ghost => systems::spawn_ghost(&mut self.world, placed.at, tuning),
+                    HazardKind::Pumpkin => systems::spawn_pumpkin(&mut self.world, placed.at, Vec2::ZERO, tuning),
+                };
+            }
+        }
+        if self.rng.rand_float() < rate || self.is_first_frame {
+            self.is_first_frame = false;
+
+            let pos = Vec2::new(stage.spawn_x(self.rng.rand_float()), SPAWN_HEIGHT);
+            let mut velx = ((self.rng.rand_float() - 0.5)*2.0)*40.0;
+            let vely = self.rng.rand_float()*60.0+20.0;
+
+            // Thrown the other way if it'd come down somewhere the cat can't go.
+            let landing = trajectory::landing(pos, vec2(velx, vely), tuning.gravity, &Ground::flat(FLOOR_Y));
+            if landing.is_some_and(|l| l.point.x.abs() > screen_width / 2.0) {
+                velx *= -1.0;
+            }
+
+            systems::spawn_candy(&mut self.world, pos, Vec2::new(velx, vely ));
+        }
+        if rules.hazards {
+            hazards::maybe_spawn(&mut self.world, &mut self.rng, tuning, self.difficulty, seconds);
+        }
+
+        let world = &mut self.world;
+        systems::remember_positions(world);
+        let (player, transform, body) = (
+            world.players.get_mut(self.player).unwrap(),
+            world.transforms.get_mut(self.player).unwrap(),
+            world.bodies.get_mut(self.player).unwrap(),
+        );
+        events.jumped = player_handle_input(player, body, input, tuning, seconds);
+
+        let shockwave = update_player_position(player, transform, body, &self.terrain, tuning, seconds);
+        if transform.pos.x.abs() > screen_width / 2.0 {
+            transform.pos.x = screen_width / 2.0 * transform.pos.x.signum();
+            body.velocity.x = -body.velocity.x * 0.9;
+            if player.grounded {
+                body.velocity.x *= 0.4;
+            }
+        }
+        let (player_pos, player_vel) = (transform.pos, body.velocity);
+        events.player_pos = player_pos;
+        if let Some(sprite) = world.sprites.get_mut(self.player) {
+            update_player_sprite(player, sprite);
+        }
+
+        if shockwave && effects {
+            for dir in [-1.0, 1.0] {
+                systems::spawn_sparkle(world, player_pos, vec2(dir * tuning.shockwave_radius * 3.0, 0.0));
+            }
+        }
+
+        hazards::think(world, player_pos, tuning, seconds);
+        systems::integrate(world, tuning.gravity, seconds);
+
+        let grid = broadphase::Grid::build(world);
+        let contacts = hazards::contact(world, &grid, self.player, tuning);
+        let mut caught = grid.touching(world, self.player).into_iter()
+            .map(|(e, _)| e)
+            .filter(|&e| world.catchables.get(e).is_some())
+            .collect::<Vec<_>>();
+        if shockwave {
+            let radius = tuning.shockwave_radius;
+            let top = player_pos.y + tuning.shockwave_height;
+            let area = grid.query_aabb(
+                vec2(player_pos.x - radius, screen_height * -0.5),
+                vec2(player_pos.x + radius, top));
+            for e in area {
+                let Some(transform) = world.transforms.get(e) else { continue };
+                let low_and_near = (transform.pos.x - player_pos.x).abs() < radius && transform.pos.y < top;
+                if world.catchables.get(e).is_some() && low_and_near && !caught.contains(&e) {
+                    caught.push(e);
+                }
+            }
+        }
+        let missed = world.catchables.iter()
+            .filter(|&(e, _)| !caught.contains(&e))
+            .filter(|&(e, _)| world.transforms.get(e).is_some_and(|t| t.pos.y < screen_height * -0.5))
+            .map(|(e, _)| e)
+            .collect::<Vec<_>>();
+
+        events.caught = caught.len() as u32;
+        for candy in caught {
+            self.caught += 1;
+            self.combo += 1;
+            self.max_combo = self.max_combo.max(self.combo);
+            self.score += self.combo;
+            world.despawn(candy);
+            if effects {
+                systems::spawn_sparkle(world, Vec2::new(player_pos.x, player_pos.y+16.0), player_vel);
+            }
+        }
+        if !missed.is_empty() || contacts.hurt || contacts.stolen > 0 {
+            self.combo = 0;
+        }
+        events.hurt = contacts.hurt;
+        self.damage += contacts.damage;
+        events.missed = missed.len() as u32;
+        self.missed += events.missed;
+        for &candy in &missed {
+            world.despawn(candy);
+        }
+
+        let player = world.players.get_mut(self.player).unwrap();
+        if rules.misses_hurt {
+            player.life -= missed.len() as i32;
+        }
+
+        if !player.moves.double_jump_unlocked && self.score >= tuning.double_jump_unlock_score {
+            player.moves.double_jump_unlocked = true;
+            events.double_jump_unlocked = true;
+        }
+
+        systems::animate(world, seconds);
+        systems::expire(world, seconds);
+        systems::squash_candy(world);
+
+        if self.player().life <= 0 {
+            events.end = Some(End::OutOfLife);
+        } else if self.goal_reached(rules, stage) {
+            events.end = Some(End::Finished);
+        }
+        events
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::bot;
+    use crate::campaign::test_stage;
+    use crate::modes::Mode;
+
+    fn play(seed: u64) -> Run {
+        let (tuning, rules, stage) = (Tuning::default(), Mode::campaign().rules, test_stage());
+        let mut run = Run::new(rules.start_life(&tuning), Rand32::new(seed), &stage);
+        for _ in 0..TICKS_PER_SECOND * 120 {
+            let input = bot::think(&run, &tuning);
+            if run.tick(&input, &tuning, &rules, &stage, false).end.is_some() {
+                break;
+            }
+        }
+        run
+    }
+
+    #[test]
+    fn same_seed_plays_the_same() {
+        let (a, b) = (play(3), play(3));
+        assert_eq!((a.score, a.caught, a.missed, a.damage), (b.score, b.caught, b.missed, b.damage));
+        assert_eq!(a.stage_time.to_bits(), b.stage_time.to_bits());
+    }
+
+    #[test]
+    fn bot_catches_candy() {
+        assert!(play(0).caught > 0);
+    }
+}
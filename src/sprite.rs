@@ -9,10 +9,18 @@ pub struct SpriteSheet {
     frames: Rc<[Rect]>
 }
 
-pub struct Sprite {
-    sheet: SpriteSheet,
-    pub pos: Vec2,
-    pub scale: Vec2
+/// A run of frames from a sheet, played at `fps`, drawn stretched by `scale`.
+pub struct Clip {
+    pub frames: &'static [usize],
+    pub fps: f32,
+    pub scale: Vec2,
+}
+
+impl Clip {
+    /// Loops forever.
+    pub fn frame(&self, time: f32) -> usize {
+        self.frames[(time * self.fps) as usize % self.frames.len()]
+    }
 }
 
 impl SpriteSheet {
@@ -33,18 +41,7 @@ impl SpriteSheet {
         }
     }
 
-    pub fn sprite(&self, pos: Vec2) -> Sprite {
-        Sprite {
-            sheet: self.clone(),
-            pos,
-            scale: vec2(1.0, 1.0)
-        }
-    }
-}
-
-impl Sprite {
-    pub fn draw_frame(&self, ctx: &mut Context, frame: usize) -> Result<(), GameError> {
-        let src = self.sheet.frames[frame];
-        draw(ctx, &self.sheet.image, DrawParam::new().dest(self.pos).scale(self.scale).offset(vec2(0.5, 0.5)).src(src))
+    pub fn draw_frame(&self, ctx: &mut Context, frame: usize, params: DrawParam) -> Result<(), GameError> {
+        draw(ctx, &self.image, params.src(self.frames[frame]))
     }
 }
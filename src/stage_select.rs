@@ -0,0 +1,92 @@
+use ggez::{Context, GameResult};
+
+use crate::locale::Locale;
+use crate::menu::{Item, Menu, MenuEvent, MenuInput, MenuSound};
+use crate::screens::Data;
+use crate::settings::Accessibility;
+
+/// What the game needs to do after the screen handled an input.
+#[derive(Debug, PartialEq, Eq)]
+pub enum SelectResult {
+    Stay,
+    Start(usize),
+    Back,
+}
+
+/// Pick a stage to play in a mode. Shown after picking the mode and after
+/// every stage, with how the last one went.
+pub struct StageSelect {
+    menu: Menu<usize>,
+    mode: usize,
+}
+
+/// Goals and ranks only matter to modes that play by the stage's goal.
+fn items(data: &Data, mode: usize) -> Vec<Item<usize>> {
+    let (progress, locale) = (data.progress, data.locale);
+    let stage_goal = data.modes[mode].rules.stage_goal;
+    data.stages.iter().enumerate()
+        .map(|(i, stage)| {
+            let number = i + 1;
+            if progress.is_unlocked(i) {
+                let item = Item::button(i, locale.fmt("stages.entry", &[("number", &number), ("stage", &stage.name)]));
+                if !stage_goal {
+                    return item;
+                }
+                let best = progress.best.get(&stage.name).copied().unwrap_or_default();
+                let goal = stage.goal_text(locale);
+                item.detail(locale.fmt("stages.detail", &[("goal", &goal), ("rank", &stage.rank), ("best", &best)]))
+            } else {
+                Item::button(i, locale.fmt("stages.locked", &[("number", &number)])).enabled(false)
+            }
+        })
+        .collect()
+}
+
+impl StageSelect {
+    pub fn new(data: &Data, mode: usize, selected: usize, message: String) -> StageSelect {
+        let locale = data.locale;
+        let heading = locale.fmt("stages.heading", &[("mode", &locale.t(&data.modes[mode].name_key()))]);
+        let mut menu = Menu::new(heading, items(data, mode))
+            .with_hint(locale.t("stages.hint"));
+        menu.select(selected);
+        menu.message = message;
+        StageSelect { menu, mode }
+    }
+
+    pub fn mode(&self) -> usize {
+        self.mode
+    }
+
+    pub fn selected(&self) -> usize {
+        self.menu.selected().unwrap_or(0)
+    }
+
+    pub fn message(&self) -> &str {
+        &self.menu.message
+    }
+
+    pub fn input(&mut self, data: &Data, input: MenuInput) -> SelectResult {
+        match self.menu.input(input) {
+            MenuEvent::Pressed(i) => return SelectResult::Start(i),
+            MenuEvent::Back => return SelectResult::Back,
+            _ => (),
+        }
+        if input == MenuInput::Confirm && self.menu.selected().is_some_and(|i| !data.progress.is_unlocked(i)) {
+            self.menu.message = data.locale.t("stages.still_locked").to_string();
+        }
+        self.menu.set_items(items(data, self.mode));
+        SelectResult::Stay
+    }
+
+    pub fn update(&mut self, dt: f32) {
+        self.menu.update(dt);
+    }
+
+    pub fn take_sound(&mut self) -> Option<MenuSound> {
+        self.menu.take_sound()
+    }
+
+    pub fn draw(&self, ctx: &mut Context, locale: &Locale, accessibility: &Accessibility) -> GameResult {
+        self.menu.draw(ctx, locale, accessibility)
+    }
+}
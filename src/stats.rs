@@ -0,0 +1,140 @@
+//! Lifetime counts of what the player has done, and the achievements they
+//! unlock. Achievements are listed in `resources/achievements.toml`; their
+//! names and descriptions are in the language files.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+use crate::rank::Rank;
+use crate::storage;
+
+pub const STATS_FILE: &str = "stats.toml";
+pub const ACHIEVEMENTS_FILE: &str = "achievements.toml";
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Stats {
+    pub candy_caught: u64,
+    pub candy_missed: u64,
+    pub jumps: u64,
+    pub hits_taken: u64,
+    pub longest_combo: u32,
+    pub stages_cleared: u64,
+    /// Seconds spent in stages, not menus.
+    pub play_time: f64,
+    /// Best rank ever got on each stage, cleared or not, by stage name.
+    pub best_rank: BTreeMap<String, Rank>,
+    /// Ids of unlocked achievements.
+    pub unlocked: BTreeSet<String>,
+}
+
+/// A number in `Stats` an achievement can wait for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stat {
+    CandyCaught,
+    CandyMissed,
+    Jumps,
+    HitsTaken,
+    LongestCombo,
+    StagesCleared,
+    PlayTime,
+}
+
+impl Stats {
+    pub fn load() -> Stats {
+        match storage::load(STATS_FILE) {
+            Ok(Some(stats)) => stats,
+            Ok(None) => Stats::default(),
+            Err(e) => {
+                println!("Couldn't read stats, starting fresh: {}", e);
+                Stats::default()
+            }
+        }
+    }
+
+    pub fn save(&self) {
+        if let Err(e) = storage::save(STATS_FILE, self) {
+            println!("Couldn't save stats: {}", e);
+        }
+    }
+
+    pub fn get(&self, stat: Stat) -> f64 {
+        match stat {
+            Stat::CandyCaught => self.candy_caught as f64,
+            Stat::CandyMissed => self.candy_missed as f64,
+            Stat::Jumps => self.jumps as f64,
+            Stat::HitsTaken => self.hits_taken as f64,
+            Stat::LongestCombo => self.longest_combo as f64,
+            Stat::StagesCleared => self.stages_cleared as f64,
+            Stat::PlayTime => self.play_time,
+        }
+    }
+
+    /// Records a finished stage's rank, keeping the best.
+    pub fn finish_stage(&mut self, stage: &str, rank: Rank, cleared: bool) {
+        if cleared {
+            self.stages_cleared += 1;
+        }
+        let best = self.best_rank.entry(stage.to_string()).or_default();
+        *best = rank.max(*best);
+    }
+
+    /// Unlocks anything newly earned and returns it.
+    pub fn check<'a>(&mut self, achievements: &'a [Achievement]) -> Vec<&'a Achievement> {
+        let earned = achievements.iter()
+            .filter(|a| !self.unlocked.contains(&a.id) && a.earned(self))
+            .collect::<Vec<_>>();
+        for a in &earned {
+            self.unlocked.insert(a.id.clone());
+        }
+        earned
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(untagged)]
+pub enum Condition {
+    /// `stat` reaches `at`.
+    Stat { stat: Stat, at: f64 },
+    /// Any stage finished with at least `rank`.
+    Rank { rank: Rank },
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct Achievement {
+    /// Also where its text is: `achievement.<id>.name` and `.description`.
+    pub id: String,
+    #[serde(flatten)]
+    pub condition: Condition,
+}
+
+impl Achievement {
+    fn earned(&self, stats: &Stats) -> bool {
+        match &self.condition {
+            Condition::Stat { stat, at } => stats.get(*stat) >= *at,
+            Condition::Rank { rank } => stats.best_rank.values().any(|best| best >= rank),
+        }
+    }
+
+    pub fn name_key(&self) -> String {
+        format!("achievement.{}.name", self.id)
+    }
+
+    pub fn description_key(&self) -> String {
+        format!("achievement.{}.description", self.id)
+    }
+}
+
+#[derive(Deserialize)]
+struct AchievementsFile {
+    achievement: Vec<Achievement>,
+}
+
+/// No file just means no achievements, but a broken one is an error.
+pub fn load_achievements(resource_dir: &Path) -> ggez::GameResult<Vec<Achievement>> {
+    let path = resource_dir.join(ACHIEVEMENTS_FILE);
+    Ok(storage::read_toml::<AchievementsFile>(&path)?.map_or(Vec::new(), |file| file.achievement))
+}
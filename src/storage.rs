@@ -0,0 +1,54 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use ggez::{GameError, GameResult};
+use serde::{de::DeserializeOwned, Serialize};
+
+/// Where our own files (settings, saves...) live. This is the same writable
+/// directory ggez mounts as the user config dir, so we can read them without a
+/// `Context`, e.g. before the window is made.
+pub fn user_dir() -> GameResult<PathBuf> {
+    directories::ProjectDirs::from("", "dunkyl", "pogin")
+        .map(|dirs| dirs.config_dir().to_path_buf())
+        .ok_or_else(|| GameError::FilesystemError("No home directory to save in".to_string()))
+}
+
+/// Reads a toml file from the user dir. A missing file isn't an error, you get `None`.
+pub fn load<T: DeserializeOwned>(name: &str) -> GameResult<Option<T>> {
+    read_toml(&user_dir()?.join(name))
+}
+
+pub fn save<T: Serialize>(name: &str, value: &T) -> GameResult {
+    write_toml(&user_dir()?.join(name), value)
+}
+
+/// The comments and blank lines at the top of a file written by hand, to put
+/// back when writing over it. Empty if there's no file yet.
+pub fn header(path: &Path) -> String {
+    fs::read_to_string(path).unwrap_or_default().lines()
+        .take_while(|line| line.is_empty() || line.starts_with('#'))
+        .map(|line| format!("{}\n", line))
+        .collect()
+}
+
+pub fn read_toml<T: DeserializeOwned>(path: &Path) -> GameResult<Option<T>> {
+    if !path.exists() {
+        return Ok(None);
+    }
+    let text = fs::read_to_string(path)?;
+    toml::from_str(&text)
+        .map(Some)
+        .map_err(|e| GameError::ConfigError(format!("{}: {}", path.display(), e)))
+}
+
+pub fn write_toml<T: Serialize>(path: &Path, value: &T) -> GameResult {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // Going through a Value first lets toml put tables after plain values for us.
+    let text = toml::Value::try_from(value)
+        .and_then(|v| toml::to_string_pretty(&v))
+        .map_err(|e| GameError::ConfigError(format!("{}: {}", path.display(), e)))?;
+    fs::write(path, text)?;
+    Ok(())
+}
@@ -0,0 +1,148 @@
+//! Things that run over the components in `ecs::World` every tick, and
+//! functions for putting together each kind of entity.
+
+use ggez::{Context, GameResult};
+use glam::*;
+
+use crate::ecs::*;
+use crate::player::Player;
+use crate::tuning::Tuning;
+use crate::assets::Assets;
+use crate::{world_to_screen_coords, SCREEN_SIZE};
+
+const CANDY_LAYER: i32 = 0;
+const HAZARD_LAYER: i32 = 1;
+const SPARKLE_LAYER: i32 = 2;
+const PLAYER_LAYER: i32 = 3;
+
+pub fn spawn_player(world: &mut World, pos: Vec2, life: i32) -> Entity {
+    let e = world.spawn();
+    world.transforms.insert(e, Transform::new(pos));
+    // The cat does its own falling, see `player::update_player_position`.
+    world.bodies.insert(e, Body { velocity: vec2(0.0, 0.0), gravity_scale: 0.0 });
+    world.sprites.insert(e, SpriteRef::new(SpriteId::Cat, PLAYER_LAYER));
+    world.colliders.insert(e, Collider { radius: 10.0 });
+    world.players.insert(e, Player::new(life));
+    e
+}
+
+pub fn spawn_candy(world: &mut World, pos: Vec2, velocity: Vec2) -> Entity {
+    let e = world.spawn();
+    world.transforms.insert(e, Transform::new(pos));
+    world.bodies.insert(e, Body { velocity, gravity_scale: 1.0 });
+    world.sprites.insert(e, SpriteRef::new(SpriteId::Candy, CANDY_LAYER));
+    world.colliders.insert(e, Collider { radius: 10.0 });
+    world.catchables.insert(e, Catchable {});
+    e
+}
+
+/// The burst when candy is caught.
+pub fn spawn_sparkle(world: &mut World, pos: Vec2, velocity: Vec2) -> Entity {
+    const FPS: f32 = 30.0;
+    let e = world.spawn();
+    world.transforms.insert(e, Transform::new(pos));
+    world.bodies.insert(e, Body { velocity, gravity_scale: 1.0 });
+    world.sprites.insert(e, SpriteRef::new(SpriteId::Sparkle, SPARKLE_LAYER));
+    world.animators.insert(e, Animator { time: 0.0, fps: FPS });
+    world.lifetimes.insert(e, Lifetime { remaining: SpriteId::Sparkle.frames() as f32 / FPS });
+    e
+}
+
+fn spawn_hazard(world: &mut World, pos: Vec2, velocity: Vec2, hazard: Hazard, sprite: SpriteId, radius: f32) -> Entity {
+    let e = world.spawn();
+    let gravity_scale = if hazard.kind == HazardKind::Pumpkin { 1.0 } else { 0.0 };
+    world.transforms.insert(e, Transform::new(pos));
+    world.bodies.insert(e, Body { velocity, gravity_scale });
+    world.sprites.insert(e, SpriteRef::new(sprite, HAZARD_LAYER));
+    world.colliders.insert(e, Collider { radius });
+    world.hazards.insert(e, hazard);
+    e
+}
+
+/// Starts just off the side at `pos` and swoops across, see `hazards::think`.
+pub fn spawn_bat(world: &mut World, pos: Vec2, tuning: &Tuning) -> Entity {
+    let hazard = Hazard { kind: HazardKind::Bat, damage: tuning.bat_damage, age: 0.0, origin: pos, carrying: false };
+    spawn_hazard(world, pos, vec2(0.0, 0.0), hazard, SpriteId::Bat, 12.0)
+}
+
+pub fn spawn_ghost(world: &mut World, pos: Vec2, tuning: &Tuning) -> Entity {
+    let hazard = Hazard { kind: HazardKind::Ghost, damage: tuning.ghost_damage, age: 0.0, origin: pos, carrying: false };
+    spawn_hazard(world, pos, vec2(0.0, 0.0), hazard, SpriteId::Ghost, 14.0)
+}
+
+pub fn spawn_pumpkin(world: &mut World, pos: Vec2, velocity: Vec2, tuning: &Tuning) -> Entity {
+    let hazard = Hazard { kind: HazardKind::Pumpkin, damage: tuning.pumpkin_damage, age: 0.0, origin: pos, carrying: false };
+    spawn_hazard(world, pos, velocity, hazard, SpriteId::Pumpkin, 12.0)
+}
+
+/// Call before anything moves in a tick.
+pub fn remember_positions(world: &mut World) {
+    for (_, transform) in world.transforms.iter_mut() {
+        transform.prev_pos = transform.pos;
+    }
+}
+
+/// Moves everything with a body, except the cat which moves itself.
+pub fn integrate(world: &mut World, gravity: f32, dt: f32) {
+    for (e, body) in world.bodies.iter_mut() {
+        if world.players.get(e).is_some() {
+            continue;
+        }
+        if let Some(transform) = world.transforms.get_mut(e) {
+            transform.pos += body.velocity * dt;
+        }
+        body.velocity.y -= gravity * body.gravity_scale * dt;
+    }
+}
+
+pub fn animate(world: &mut World, dt: f32) {
+    for (e, animator) in world.animators.iter_mut() {
+        animator.time += dt;
+        if let Some(sprite) = world.sprites.get_mut(e) {
+            let last = sprite.id.frames() - 1;
+            sprite.frame = ((animator.time * animator.fps) as usize).min(last);
+        }
+    }
+}
+
+/// Removes things whose `Lifetime` has run out.
+pub fn expire(world: &mut World, dt: f32) {
+    let mut expired = Vec::new();
+    for (e, lifetime) in world.lifetimes.iter_mut() {
+        lifetime.remaining -= dt;
+        if lifetime.remaining <= 0.0 {
+            expired.push(e);
+        }
+    }
+    for e in expired {
+        world.despawn(e);
+    }
+}
+
+/// Candy on the way up is squashed, so it looks like it pops out of somewhere.
+pub fn squash_candy(world: &mut World) {
+    for (e, _) in world.catchables.iter() {
+        if let (Some(body), Some(sprite)) = (world.bodies.get(e), world.sprites.get_mut(e)) {
+            let scale = if body.velocity.y > 0.0 {
+                1.0 - body.velocity.y.clamp(0.0, 80.0) / 80.0
+            } else {
+                1.0
+            };
+            sprite.scale = vec2(scale, scale);
+        }
+    }
+}
+
+pub fn render(world: &World, assets: &Assets, ctx: &mut Context) -> GameResult {
+    let mut sprites = world.sprites.iter()
+        .filter_map(|(e, sprite)| Some((world.transforms.get(e)?, sprite)))
+        .collect::<Vec<_>>();
+    // Stable, so things on the same layer keep their slot order. That isn't
+    // the order they were made in, since `World::spawn` reuses slots.
+    sprites.sort_by_key(|(_, sprite)| sprite.layer);
+    for (transform, sprite) in sprites {
+        let dest = world_to_screen_coords(SCREEN_SIZE.0, SCREEN_SIZE.1, transform.pos);
+        assets.draw_sprite(ctx, sprite, dest)?;
+    }
+    Ok(())
+}
@@ -0,0 +1,100 @@
+//! What the cat stands on. Usually that's just `FLOOR_Y` all the way across
+//! and the tiles are scenery, but random arenas (see `arena`) have ground at
+//! different heights, pits and floating ledges the cat really lands on.
+
+use crate::campaign::MAP_WIDTH;
+use crate::trajectory::TILE_SIZE;
+use crate::{FLOOR_Y, SCREEN_SIZE};
+
+/// How far above the top of a tile the cat stands, the same as `FLOOR_Y` is
+/// above the usual two rows of ground.
+pub const FEET: f32 = 14.0;
+
+/// Pixels of slack, so standing exactly on something counts as being above it.
+const SLACK: f32 = 0.5;
+
+#[derive(Debug, Clone)]
+pub struct Terrain {
+    /// Height the cat stands at on the ground stacked up from the bottom row,
+    /// left column first. It can't walk into this from the side.
+    ground: Vec<f32>,
+    /// Heights it can stand at on floating tiles, which it can jump up through
+    /// and land on from above.
+    ledges: Vec<Vec<f32>>,
+}
+
+impl Default for Terrain {
+    fn default() -> Self {
+        Terrain::flat()
+    }
+}
+
+impl Terrain {
+    /// `FLOOR_Y` across the whole screen and nothing else, whatever the tiles say.
+    pub fn flat() -> Terrain {
+        Terrain { ground: vec![FLOOR_Y; MAP_WIDTH], ledges: vec![Vec::new(); MAP_WIDTH] }
+    }
+
+    /// From a layout, bottom row first like `Stage::layout`. A column with
+    /// nothing at the bottom stands the cat on the bottom of the screen.
+    pub fn from_layout(layout: &[Vec<usize>]) -> Terrain {
+        let bottom = -SCREEN_SIZE.1 / 2.0;
+        let solid = |x: usize, y: usize| layout.get(y).and_then(|row| row.get(x)).is_some_and(|&t| t != 0);
+        let mut terrain = Terrain { ground: Vec::new(), ledges: Vec::new() };
+        for x in 0..MAP_WIDTH {
+            let stack = (0..layout.len()).take_while(|&y| solid(x, y)).count();
+            terrain.ground.push(bottom + stack as f32 * TILE_SIZE + FEET);
+            terrain.ledges.push((stack + 1..layout.len())
+                .filter(|&y| solid(x, y) && !solid(x, y + 1))
+                .map(|y| bottom + (y + 1) as f32 * TILE_SIZE + FEET)
+                .collect());
+        }
+        terrain
+    }
+
+    fn column(x: f32) -> usize {
+        let column = ((x + SCREEN_SIZE.0 / 2.0) / TILE_SIZE).floor();
+        (column.max(0.0) as usize).min(MAP_WIDTH - 1)
+    }
+
+    /// The highest the cat can stand at `x` without going up, i.e. what it
+    /// lands on coming down from `y`.
+    pub fn floor_below(&self, x: f32, y: f32) -> f32 {
+        let column = Terrain::column(x);
+        self.ledges[column].iter()
+            .copied()
+            .filter(|&ledge| ledge <= y + SLACK)
+            .fold(self.ground[column], f32::max)
+    }
+
+    /// Whether the cat at height `y` would be inside the ground at `x`.
+    pub fn blocks(&self, x: f32, y: f32) -> bool {
+        y + SLACK < self.ground[Terrain::column(x)]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lands_on_ledges_only_from_above() {
+        // Two rows of ground, a step up in the last column and a ledge on
+        // row 4 over the first.
+        let mut layout = vec![vec![1; MAP_WIDTH]; 2];
+        layout.push(vec![0; MAP_WIDTH]);
+        layout[2][MAP_WIDTH - 1] = 1;
+        layout.push(vec![0; MAP_WIDTH]);
+        layout.push(vec![0; MAP_WIDTH]);
+        layout[4][0] = 1;
+        let terrain = Terrain::from_layout(&layout);
+
+        let left = -SCREEN_SIZE.0 / 2.0 + 1.0;
+        let ledge = -SCREEN_SIZE.1 / 2.0 + 5.0 * TILE_SIZE + FEET;
+        assert_eq!(terrain.floor_below(left, FLOOR_Y), FLOOR_Y);
+        assert_eq!(terrain.floor_below(left, ledge), ledge);
+        assert_eq!(terrain.floor_below(0.0, ledge), FLOOR_Y);
+        assert!(terrain.blocks(SCREEN_SIZE.0 / 2.0 - 1.0, FLOOR_Y));
+        assert!(!terrain.blocks(SCREEN_SIZE.0 / 2.0 - 1.0, FLOOR_Y + TILE_SIZE));
+    }
+}
@@ -1,40 +1,104 @@
-use ggez::conf;
-use ggez::event::{self, EventHandler, KeyCode, KeyMods};
-use ggez::graphics::{self, Color, Image, FillOptions};
-use ggez::{Context, ContextBuilder, GameResult};
-use ggez::audio::Source;
-use glam::*;
-use oorandom::Rand32;
-
-use std::env;
-use std::path::{self, Path};
-use std::rc::Rc;
-
-pub struct TileRule {
-    empty: Box<[IVec2]>
+use ggez::graphics::{self, Image, Rect};
+use ggez::{Context, GameError, GameResult};
+use serde::Deserialize;
+
+use std::io::Read;
+use std::path::Path;
+
+/// A tile that shows other tiles off the sheet in turn, like a candle
+/// flickering.
+#[derive(Debug, Clone, Deserialize)]
+pub struct TileAnim {
+    pub tile: usize,
+    pub frames: Vec<usize>,
+    pub fps: f32,
+}
+
+/// A decoration tileset file, see `resources/decor.toml`.
+#[derive(Deserialize)]
+struct DecorFile {
+    image: String,
+    #[serde(default)]
+    anim: Vec<TileAnim>,
 }
 
 pub struct TileSet {
     sprite_sheet: Image,
     count: usize,
-    rules: Box<[TileRule]>
+    anims: Vec<TileAnim>,
 }
 
+/// Tiles in a full ground sheet. `draw_map` picks them by which neighbours
+/// are empty.
+const GROUND_TILES: usize = 15;
+
 impl TileSet {
-    pub fn new(ctx: &mut Context, sheet: impl AsRef<Path>, rules: Vec<Vec<IVec2>>) -> Self {
-        let img = Image::new(ctx, sheet).unwrap();
-        let count = (dbg!(img.width()) / dbg!(img.height())) as usize;
-        assert_eq!(rules.len(), count);
-        let rules = rules.into_iter().map(|coords| TileRule {
-            empty: coords.into_boxed_slice() } ).collect::<Vec<_>>().into_boxed_slice();
-        TileSet {
-            sprite_sheet: img,
-            count,
-            rules,
+    /// A ground sheet: a strip of square tiles in the usual order. Older
+    /// sheets stop early, and a single square image is used for every tile.
+    pub fn autotiled(ctx: &mut Context, sheet: impl AsRef<Path>) -> GameResult<Self> {
+        let name = sheet.as_ref().display().to_string();
+        let img = Image::new(ctx, sheet)?;
+        let count = (img.width() / img.height()) as usize;
+        if count == 0 || count > GROUND_TILES {
+            return Err(GameError::ConfigError(format!(
+                "{}: a ground sheet should be 1 to {} square tiles in a row, not {}x{}",
+                name, GROUND_TILES, img.width(), img.height())));
+        }
+        Ok(TileSet { sprite_sheet: img, count, anims: Vec::new() })
+    }
+
+    /// Decoration: a strip of square tiles used as they are, no autotiling,
+    /// some of them animated.
+    pub fn decor(ctx: &mut Context, path: &str) -> GameResult<Self> {
+        let mut text = String::new();
+        ggez::filesystem::open(ctx, path)?.read_to_string(&mut text)?;
+        let file: DecorFile = toml::from_str(&text)
+            .map_err(|e| GameError::ConfigError(format!("{}: {}", path, e)))?;
+        let img = Image::new(ctx, &file.image)?;
+        let count = (img.width() / img.height()) as usize;
+        if count == 0 {
+            return Err(GameError::ConfigError(format!(
+                "{}: {} should be square tiles in a row, not {}x{}", path, file.image, img.width(), img.height())));
+        }
+        for anim in &file.anim {
+            if anim.tile >= count {
+                return Err(GameError::ConfigError(format!(
+                    "{}: animates tile {}, but {} only has {}", path, anim.tile, file.image, count)));
+            }
+            if let Some(&frame) = anim.frames.iter().find(|&&f| f >= count) {
+                return Err(GameError::ConfigError(format!(
+                    "{}: tile {} plays tile {}, but {} only has {}", path, anim.tile, frame, file.image, count)));
+            }
         }
+        let anims = file.anim.into_iter().filter(|anim| !anim.frames.is_empty()).collect();
+        Ok(TileSet { sprite_sheet: img, count, anims })
     }
 
     pub fn img(&self) -> graphics::Image {
         self.sprite_sheet.clone()
     }
+
+    /// Part of the sheet for one quarter (`sub_x`, `sub_y`) of a map tile. Tiles
+    /// the sheet doesn't have fall back to the plain filled one.
+    pub fn src(&self, tile_id: usize, sub_x: i32, sub_y: i32) -> Rect {
+        if self.count == 1 {
+            return Rect::new(sub_x as f32 * 0.5, sub_y as f32 * 0.5, 0.5, 0.5);
+        }
+        let tile_id = if tile_id < self.count { tile_id } else { 12.min(self.count - 1) };
+        self.tile_src(tile_id)
+    }
+
+    /// The whole of one tile on the sheet.
+    pub fn tile_src(&self, tile_id: usize) -> Rect {
+        Rect::new(tile_id.min(self.count - 1) as f32 / self.count as f32, 0.0, 1.0 / self.count as f32, 1.0)
+    }
+
+    /// Which tile `tile_id` shows `time` seconds in. Each `spot` starts at a
+    /// different point so a row of candles doesn't flicker in step.
+    pub fn frame(&self, tile_id: usize, time: f32, spot: usize) -> usize {
+        match self.anims.iter().find(|anim| anim.tile == tile_id) {
+            Some(anim) => anim.frames[((time * anim.fps) as usize + spot * 7) % anim.frames.len()],
+            None => tile_id,
+        }
+    }
 }
\ No newline at end of file
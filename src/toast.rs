@@ -0,0 +1,65 @@
+//! Little notices that drop in at the top of the screen for a few seconds,
+//! one at a time, e.g. for achievements.
+
+use std::collections::VecDeque;
+
+use ggez::graphics::{self, Color, DrawMode, DrawParam, Rect};
+use ggez::{Context, GameResult};
+use glam::*;
+
+use crate::locale::Locale;
+use crate::settings::Accessibility;
+use crate::SCREEN_SIZE;
+
+/// Seconds each one stays up, including sliding in and out.
+const SHOW_TIME: f32 = 3.0;
+const SLIDE_TIME: f32 = 0.25;
+const SIZE: (f32, f32) = (300.0, 48.0);
+
+pub struct Toast {
+    pub heading: String,
+    pub text: String,
+}
+
+#[derive(Default)]
+pub struct Toasts {
+    queue: VecDeque<Toast>,
+    /// Seconds the front one has been up.
+    shown: f32,
+}
+
+impl Toasts {
+    pub fn push(&mut self, heading: impl Into<String>, text: impl Into<String>) {
+        self.queue.push_back(Toast { heading: heading.into(), text: text.into() });
+    }
+
+    /// Runs in real time, so toasts still go away while paused.
+    pub fn update(&mut self, dt: f32) {
+        if self.queue.is_empty() {
+            return;
+        }
+        self.shown += dt;
+        if self.shown >= SHOW_TIME {
+            self.queue.pop_front();
+            self.shown = 0.0;
+        }
+    }
+
+    pub fn draw(&self, ctx: &mut Context, locale: &Locale, accessibility: &Accessibility) -> GameResult {
+        let Some(toast) = self.queue.front() else { return Ok(()) };
+        let t = (self.shown.min(SHOW_TIME - self.shown) / SLIDE_TIME).clamp(0.0, 1.0);
+        let (y, alpha) = if accessibility.reduce_motion { (8.0, t) } else { (8.0 - (1.0 - t) * (SIZE.1 + 8.0), 1.0) };
+        let x = (SCREEN_SIZE.0 - SIZE.0) / 2.0;
+
+        let panel = graphics::MeshBuilder::new()
+            .rectangle(DrawMode::fill(), Rect::new(x, y, SIZE.0, SIZE.1), Color::new(0.1, 0.05, 0.15, 0.9 * alpha))?
+            .rectangle(DrawMode::stroke(2.0), Rect::new(x, y, SIZE.0, SIZE.1), Color::new(1.0, 0.6, 0.1, alpha))?
+            .build(ctx)?;
+        graphics::draw(ctx, &panel, DrawParam::new())?;
+        let heading = locale.text(&toast.heading, 12.0);
+        graphics::draw(ctx, &heading, (vec2(x + 10.0, y + 6.0), 0.0, Color::new(1.0, 0.8, 0.2, alpha)))?;
+        let text = locale.text(&toast.text, 16.0);
+        graphics::draw(ctx, &text, (vec2(x + 10.0, y + 22.0), 0.0, Color::new(1.0, 1.0, 1.0, alpha)))?;
+        Ok(())
+    }
+}
@@ -0,0 +1,118 @@
+//! Where falling things will come down. Candy flies on plain arcs, so this
+//! steps them the same way `systems::integrate` does, a tick at a time, and
+//! gets the same answer the game will.
+
+use glam::*;
+
+use crate::campaign::MAP_WIDTH;
+use crate::sim::TICKS_PER_SECOND;
+use crate::SCREEN_SIZE;
+
+pub const TILE_SIZE: f32 = 32.0;
+
+/// Furthest ahead it looks, in seconds. Anything that takes longer is
+/// probably going up forever.
+const HORIZON: f32 = 10.0;
+
+/// How high the ground is in each tile column of the map.
+#[derive(Debug, Clone)]
+pub struct Ground {
+    /// Top of the ground in world coordinates, left column first.
+    heights: Vec<f32>,
+}
+
+impl Ground {
+    /// Solid tiles stacked up from the bottom row. Floating ones are scenery
+    /// that candy falls past, and a column with nothing at the bottom is a
+    /// pit down to the bottom of the screen.
+    pub fn from_layout(layout: &[Vec<usize>]) -> Ground {
+        let bottom = -SCREEN_SIZE.1 / 2.0;
+        let heights = (0..MAP_WIDTH)
+            .map(|x| {
+                let solid = layout.iter().take_while(|row| row.get(x).is_some_and(|&t| t != 0)).count();
+                bottom + solid as f32 * TILE_SIZE
+            })
+            .collect();
+        Ground { heights }
+    }
+
+    /// The same height all the way across, e.g. the height the cat catches at.
+    pub fn flat(height: f32) -> Ground {
+        Ground { heights: vec![height; MAP_WIDTH] }
+    }
+
+    /// Past the sides of the screen it carries on like the edge columns.
+    pub fn height_at(&self, x: f32) -> f32 {
+        let column = ((x + SCREEN_SIZE.0 / 2.0) / TILE_SIZE).floor();
+        self.heights[(column.max(0.0) as usize).min(self.heights.len() - 1)]
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Landing {
+    pub point: Vec2,
+    /// Seconds from now.
+    pub time: f32,
+}
+
+/// Where something at `pos` going at `velocity` comes down on `ground`, if it
+/// does. `None` if it's already under it or won't come down for a long time.
+pub fn landing(pos: Vec2, velocity: Vec2, gravity: f32, ground: &Ground) -> Option<Landing> {
+    let dt = 1.0 / TICKS_PER_SECOND as f32;
+    if pos.y <= ground.height_at(pos.x) {
+        return None;
+    }
+    let (mut pos, mut velocity, mut time) = (pos, velocity, 0.0);
+    while time < HORIZON {
+        let next = pos + velocity * dt;
+        velocity.y -= gravity * dt;
+        let height = ground.height_at(next.x);
+        if next.y <= height {
+            // Partway through the tick, for a point that's on the ground
+            // rather than just under it.
+            let t = ((pos.y - height) / (pos.y - next.y)).clamp(0.0, 1.0);
+            return Some(Landing { point: vec2(pos.x + (next.x - pos.x) * t, height), time: time + t * dt });
+        }
+        pos = next;
+        time += dt;
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ecs::World;
+    use crate::systems;
+
+    #[test]
+    fn lands_where_the_game_puts_it() {
+        let (gravity, floor) = (200.0, -160.0);
+        let (pos, velocity) = (vec2(-100.0, 150.0), vec2(35.0, 70.0));
+        let landing = landing(pos, velocity, gravity, &Ground::flat(floor)).unwrap();
+
+        let mut world = World::default();
+        let candy = systems::spawn_candy(&mut world, pos, velocity);
+        let dt = 1.0 / TICKS_PER_SECOND as f32;
+        let mut ticks = 0;
+        while world.transforms.get(candy).unwrap().pos.y > floor {
+            systems::integrate(&mut world, gravity, dt);
+            ticks += 1;
+        }
+        let at = world.transforms.get(candy).unwrap().pos;
+        assert_eq!(ticks, (landing.time / dt).ceil() as i32);
+        assert!((at.x - landing.point.x).abs() < velocity.x * dt);
+    }
+
+    #[test]
+    fn ground_is_the_stack_from_the_bottom() {
+        let mut layout = vec![vec![0; MAP_WIDTH]; 15];
+        layout[0][0] = 1;
+        layout[1][0] = 1;
+        layout[5][0] = 1;
+        let ground = Ground::from_layout(&layout);
+        let bottom = -SCREEN_SIZE.1 / 2.0;
+        assert_eq!(ground.height_at(-SCREEN_SIZE.0 / 2.0 + 1.0), bottom + 2.0 * TILE_SIZE);
+        assert_eq!(ground.height_at(0.0), bottom);
+    }
+}
@@ -0,0 +1,200 @@
+use std::fs;
+use std::path::Path;
+
+use ggez::{GameError, GameResult};
+use serde::{Deserialize, Serialize};
+
+use crate::storage;
+
+pub const TUNING_FILE: &str = "tuning.toml";
+
+/// How the game feels. Lives in `resources/tuning.toml` so it can be changed
+/// without recompiling, and the tuning overlay (F2) writes back to it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Tuning {
+    /// Acceleration in pixels per second.
+    pub player_thrust: f32,
+    /// Acceleration when stopping or turning around.
+    pub player_break_thrust: f32,
+    /// Top running speed.
+    pub player_vel: f32,
+    /// Upwards speed the moment the cat jumps.
+    pub jump_velocity: f32,
+    /// Upwards speed is multiplied by this when jump is let go early.
+    pub jump_release_cut: f32,
+    /// Seconds after leaving a ledge that jumping still works.
+    pub coyote_time: f32,
+    /// Seconds a jump press is remembered for before landing.
+    pub jump_buffer_time: f32,
+    /// Pulls on candy and sparkles.
+    pub gravity: f32,
+    /// Gravity on the cat going up, as a multiple of `gravity`.
+    pub rise_gravity_scale: f32,
+    /// Gravity on the cat coming down. Heavier than going up so jumps feel snappy.
+    pub fall_gravity_scale: f32,
+    /// Upwards speed of the second jump.
+    pub double_jump_velocity: f32,
+    /// Score that unlocks the double jump.
+    pub double_jump_unlock_score: u32,
+    /// Sideways speed during an air dash.
+    pub dash_speed: f32,
+    /// Seconds an air dash lasts.
+    pub dash_time: f32,
+    /// Seconds after a dash before the next one.
+    pub dash_cooldown: f32,
+    /// Downwards speed of a ground pound.
+    pub pound_speed: f32,
+    /// How far either side a ground pound reaches.
+    pub shockwave_radius: f32,
+    /// Candy lower than this above the floor gets caught by a ground pound.
+    pub shockwave_height: f32,
+    /// How fast difficulty goes up, per second.
+    pub difficulty_rate: f32,
+    /// Misses allowed before it's game over.
+    pub player_life: i32,
+    /// Difficulty reached before hazards start turning up.
+    pub hazard_grace: f32,
+    /// Chance per second of a new hazard, once they've started.
+    pub hazard_rate: f32,
+    /// Seconds the cat can't be hurt again after being hit.
+    pub hurt_time: f32,
+    /// Sideways speed of a bat.
+    pub bat_speed: f32,
+    /// How hard a bat knocks candy away.
+    pub bat_knock: f32,
+    pub bat_damage: i32,
+    /// Drifting speed of a ghost.
+    pub ghost_speed: f32,
+    /// Seconds before a ghost gives up and fades away.
+    pub ghost_time: f32,
+    pub ghost_damage: i32,
+    /// Seconds the cat can't move after a pumpkin lands on it.
+    pub pumpkin_stun_time: f32,
+    pub pumpkin_damage: i32,
+}
+
+impl Default for Tuning {
+    fn default() -> Self {
+        Tuning {
+            player_thrust: 600.0,
+            player_break_thrust: 1800.0,
+            player_vel: 400.0,
+            jump_velocity: 400.0,
+            jump_release_cut: 0.4,
+            coyote_time: 0.1,
+            jump_buffer_time: 0.1,
+            gravity: 200.0,
+            rise_gravity_scale: 5.0,
+            fall_gravity_scale: 7.0,
+            double_jump_velocity: 340.0,
+            double_jump_unlock_score: 50,
+            dash_speed: 700.0,
+            dash_time: 0.15,
+            dash_cooldown: 0.6,
+            pound_speed: 700.0,
+            shockwave_radius: 80.0,
+            shockwave_height: 40.0,
+            difficulty_rate: 1.15,
+            player_life: 12,
+            hazard_grace: 20.0,
+            hazard_rate: 0.15,
+            hurt_time: 1.0,
+            bat_speed: 180.0,
+            bat_knock: 150.0,
+            bat_damage: 1,
+            ghost_speed: 40.0,
+            ghost_time: 12.0,
+            ghost_damage: 1,
+            pumpkin_stun_time: 1.0,
+            pumpkin_damage: 2,
+        }
+    }
+}
+
+/// One value the overlay can change.
+pub enum Knob<'a> {
+    Float(&'a mut f32, f32),
+    Int(&'a mut i32),
+    /// Can go down to 0, unlike `Int`.
+    UInt(&'a mut u32, u32),
+}
+
+/// A knob's value, without being able to change it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Reading {
+    Float(f32),
+    Int(i32),
+    UInt(u32),
+}
+
+/// Writes `knobs` and `readings` from one list, so they can't disagree.
+macro_rules! knobs {
+    ($($field:ident: $kind:ident $(($step:expr))?,)*) => {
+        /// Every value in order, with its name and how much one press changes it.
+        pub fn knobs(&mut self) -> Vec<(&'static str, Knob<'_>)> {
+            vec![$((stringify!($field), Knob::$kind(&mut self.$field $(, $step)?)),)*]
+        }
+
+        /// The same as `knobs`, for showing rather than changing.
+        pub fn readings(&self) -> Vec<(&'static str, Reading)> {
+            vec![$((stringify!($field), Reading::$kind(self.$field)),)*]
+        }
+    };
+}
+
+impl Tuning {
+    pub fn load(resource_dir: &Path) -> Tuning {
+        match storage::read_toml(&resource_dir.join(TUNING_FILE)) {
+            Ok(Some(tuning)) => tuning,
+            Ok(None) => Tuning::default(),
+            Err(e) => {
+                println!("Couldn't read tuning, using defaults: {}", e);
+                Tuning::default()
+            }
+        }
+    }
+
+    /// Keeps the comments at the top of the file.
+    pub fn save(&self, resource_dir: &Path) -> GameResult {
+        let path = resource_dir.join(TUNING_FILE);
+        let values = toml::to_string(self)
+            .map_err(|e| GameError::ConfigError(format!("{}: {}", path.display(), e)))?;
+        fs::write(&path, storage::header(&path) + &values)?;
+        Ok(())
+    }
+
+    knobs! {
+        player_thrust: Float(50.0),
+        player_break_thrust: Float(50.0),
+        player_vel: Float(20.0),
+        jump_velocity: Float(20.0),
+        jump_release_cut: Float(0.05),
+        coyote_time: Float(0.01),
+        jump_buffer_time: Float(0.01),
+        gravity: Float(10.0),
+        rise_gravity_scale: Float(0.25),
+        fall_gravity_scale: Float(0.25),
+        double_jump_velocity: Float(20.0),
+        double_jump_unlock_score: UInt(5),
+        dash_speed: Float(50.0),
+        dash_time: Float(0.01),
+        dash_cooldown: Float(0.05),
+        pound_speed: Float(50.0),
+        shockwave_radius: Float(5.0),
+        shockwave_height: Float(5.0),
+        difficulty_rate: Float(0.05),
+        player_life: Int,
+        hazard_grace: Float(1.0),
+        hazard_rate: Float(0.01),
+        hurt_time: Float(0.1),
+        bat_speed: Float(10.0),
+        bat_knock: Float(10.0),
+        bat_damage: Int,
+        ghost_speed: Float(5.0),
+        ghost_time: Float(1.0),
+        ghost_damage: Int,
+        pumpkin_stun_time: Float(0.1),
+        pumpkin_damage: Int,
+    }
+}
@@ -0,0 +1,85 @@
+use ggez::event::KeyCode;
+use ggez::graphics::{self, Color, DrawMode, Rect};
+use ggez::{Context, GameResult};
+use glam::*;
+
+use crate::locale::Locale;
+use crate::tuning::{Knob, Reading, Tuning};
+
+/// Rows shown at once. The list scrolls to keep the selected one in view.
+const VISIBLE: usize = 20;
+
+/// Designer overlay for changing `Tuning` while playing. Uses keys the game
+/// doesn't, so the cat can still be moved around to feel the change:
+/// `[`/`]` pick a value, `-`/`=` change it, F5 saves to the tuning file.
+#[derive(Default)]
+pub struct TuningOverlay {
+    selected: usize,
+    /// Last thing that happened, e.g. "Saved".
+    status: String,
+}
+
+impl TuningOverlay {
+    /// Returns true if the key was for the overlay. `save` is called for F5.
+    pub fn key_down(&mut self, tuning: &mut Tuning, keycode: KeyCode, save: impl FnOnce(&Tuning) -> GameResult) -> bool {
+        let count = tuning.knobs().len();
+        let dir = match keycode {
+            KeyCode::LBracket => {
+                self.selected = (self.selected + count - 1) % count;
+                return true;
+            }
+            KeyCode::RBracket => {
+                self.selected = (self.selected + 1) % count;
+                return true;
+            }
+            KeyCode::F5 => {
+                self.status = match save(tuning) {
+                    Ok(()) => "Saved".to_string(),
+                    Err(e) => format!("Couldn't save: {}", e),
+                };
+                return true;
+            }
+            KeyCode::Minus => -1.0,
+            KeyCode::Equals => 1.0,
+            _ => return false,
+        };
+
+        let (_, knob) = tuning.knobs().swap_remove(self.selected);
+        match knob {
+            Knob::Float(value, step) => *value = (*value + step * dir).max(0.0),
+            Knob::Int(value) => *value = (*value + dir as i32).max(1),
+            Knob::UInt(value, step) => *value = if dir < 0.0 { value.saturating_sub(step) } else { *value + step },
+        }
+        self.status.clear();
+        true
+    }
+
+    pub fn draw(&self, ctx: &mut Context, locale: &Locale, tuning: &Tuning) -> GameResult {
+        let readings = tuning.readings();
+        let rows = readings.len().min(VISIBLE);
+        let first = self.selected.saturating_sub(VISIBLE - 1);
+        let (x, y) = (crate::SCREEN_SIZE.0 - 250.0, 80.0);
+        let backing = graphics::Mesh::new_rectangle(
+            ctx, DrawMode::fill(),
+            Rect::new(x - 6.0, y - 6.0, 246.0, rows as f32 * 16.0 + 40.0),
+            Color::new(0.0, 0.0, 0.0, 0.7))?;
+        graphics::draw(ctx, &backing, graphics::DrawParam::new())?;
+
+        for (row, (i, (name, reading))) in readings.into_iter().enumerate().skip(first).take(rows).enumerate() {
+            let value = match reading {
+                Reading::Float(value) => format!("{:.2}", value),
+                Reading::Int(value) => format!("{}", value),
+                Reading::UInt(value) => format!("{}", value),
+            };
+            let color = if i == self.selected { Color::new(1.0, 0.8, 0.2, 1.0) } else { Color::WHITE };
+            let text = locale.text(format!("{}: {}", name, value), 12.0);
+            graphics::draw(ctx, &text, (vec2(x, y + row as f32 * 16.0), 0.0, color))?;
+        }
+
+        let count = rows as f32;
+        let hint = if self.status.is_empty() { "[ ] pick  - = change  F5 save" } else { &self.status };
+        let hint = locale.text(hint, 12.0);
+        graphics::draw(ctx, &hint, (vec2(x, y + count * 16.0 + 8.0), 0.0, Color::WHITE))?;
+        Ok(())
+    }
+}